@@ -0,0 +1,169 @@
+//! Readiness and watchdog notification for services installed with corky.
+//!
+//! With `[corky] ready = "notify"` systemd runs the service as `Type=notify`
+//! and these calls speak the `sd_notify(3)` protocol over `$NOTIFY_SOCKET`.
+//! Supervisor has no such socket, so corky sets `$CORKY_READY_FILE` instead
+//! and waits for the service to write `READY=1` there. Outside either, every
+//! call is a no-op, so the same binary still runs from a shell.
+//!
+//! ```no_run
+//! fn main() -> std::io::Result<()> {
+//!     // ... bind sockets, load config ...
+//!     corky_sdk::ready()?;
+//!     if let Some(interval) = corky_sdk::watchdog_interval() {
+//!         std::thread::spawn(move || loop {
+//!             let _ = corky_sdk::watchdog();
+//!             std::thread::sleep(interval);
+//!         });
+//!     }
+//!     Ok(())
+//! }
+//! ```
+
+use std::collections::BTreeMap;
+use std::env;
+use std::ffi::OsStr;
+use std::fs;
+use std::io;
+use std::os::linux::net::SocketAddrExt;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::net::{SocketAddr, UnixDatagram};
+use std::path::Path;
+use std::time::Duration;
+
+/// Environment variable corky sets for supervisor programs: where to report readiness.
+pub const READY_FILE_ENV: &str = "CORKY_READY_FILE";
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Notifications
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Startup is complete; `corky start` and `corky install` return once this is sent.
+pub fn ready() -> io::Result<()> {
+    notify("READY=1")
+}
+
+/// Free-form status shown by `systemctl status` (or kept in the ready file).
+pub fn status(message: &str) -> io::Result<()> {
+    notify(&format!("STATUS={}", message.replace('\n', " ")))
+}
+
+/// Keep-alive ping; call it every [`watchdog_interval`].
+pub fn watchdog() -> io::Result<()> {
+    notify("WATCHDOG=1")
+}
+
+/// The service is shutting down.
+pub fn stopping() -> io::Result<()> {
+    notify("STOPPING=1")
+}
+
+/// How often to call [`watchdog`]: half of systemd's WatchdogSec=, or None
+/// when no watchdog is armed for this process.
+pub fn watchdog_interval() -> Option<Duration> {
+    let usec: u64 = env::var("WATCHDOG_USEC").ok()?.parse().ok()?;
+    // WATCHDOG_PID names the process the watchdog is meant for
+    if let Ok(pid) = env::var("WATCHDOG_PID")
+        && pid.parse() != Ok(std::process::id())
+    {
+        return None;
+    }
+    (usec > 0).then(|| Duration::from_micros(usec / 2))
+}
+
+/// Send raw newline-separated `KEY=VALUE` assignments.
+pub fn notify(state: &str) -> io::Result<()> {
+    if let Some(socket) = env::var_os("NOTIFY_SOCKET") {
+        return send_notify(&socket, state);
+    }
+    if let Some(path) = env::var_os(READY_FILE_ENV) {
+        return update_ready_file(Path::new(&path), state);
+    }
+    Ok(())
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Transports
+// ─────────────────────────────────────────────────────────────────────────────
+
+fn send_notify(socket: &OsStr, state: &str) -> io::Result<()> {
+    let sock = UnixDatagram::unbound()?;
+    match socket.as_bytes().strip_prefix(b"@") {
+        // Abstract namespace socket
+        Some(name) => {
+            let addr = SocketAddr::from_abstract_name(name)?;
+            sock.send_to_addr(state.as_bytes(), &addr)?;
+        }
+        None => {
+            sock.send_to(state.as_bytes(), socket)?;
+        }
+    }
+    Ok(())
+}
+
+/// Merge `state` into the ready file, replacing it atomically so a waiting
+/// corky never reads a partial write. A watchdog ping only refreshes its mtime.
+fn update_ready_file(path: &Path, state: &str) -> io::Result<()> {
+    let mut fields: BTreeMap<String, String> = fs::read_to_string(path)
+        .map(|text| parse_fields(&text))
+        .unwrap_or_default();
+    for (key, value) in parse_fields(state) {
+        if key != "WATCHDOG" {
+            fields.insert(key, value);
+        }
+    }
+    let contents: String = fields
+        .iter()
+        .map(|(k, v)| format!("{}={}\n", k, v))
+        .collect();
+    let tmp = path.with_extension(format!("tmp.{}", std::process::id()));
+    fs::write(&tmp, contents)?;
+    fs::rename(&tmp, path)
+}
+
+fn parse_fields(text: &str) -> BTreeMap<String, String> {
+    text.lines()
+        .filter_map(|line| line.split_once('='))
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Tests
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("corky-sdk-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_send_notify() {
+        let dir = scratch_dir("notify");
+        let path = dir.join("notify.sock");
+        let listener = UnixDatagram::bind(&path).unwrap();
+        send_notify(path.as_os_str(), "READY=1\nSTATUS=up").unwrap();
+        let mut buf = [0u8; 64];
+        let n = listener.recv(&mut buf).unwrap();
+        assert_eq!(&buf[..n], b"READY=1\nSTATUS=up");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_update_ready_file() {
+        let dir = scratch_dir("ready");
+        let path = dir.join("svc.ready");
+        update_ready_file(&path, "STATUS=loading").unwrap();
+        update_ready_file(&path, "READY=1\nSTATUS=serving").unwrap();
+        update_ready_file(&path, "WATCHDOG=1").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "READY=1\nSTATUS=serving\n");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,303 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::diff::expected_config;
+use crate::envfile::{redact_config, write_config_file};
+use crate::escalate::escalator_or_exit;
+use crate::init::{
+    compute_file_checksum, elevate_privileges, ensure_corky_prefix, exit_error, is_root,
+    primary_group_for_user, run_cmd, run_cmd_expect_ok, run_cmd_quiet, section,
+    supervisor_conf_for, systemd_fragment_path, systemd_unit_path, InitBackend, C_BGREEN, C_CYAN, C_GREEN, C_RESET,
+    C_YELLOW, ENV_INIT_BACKEND, UNIT_DIR_SYSTEM,
+};
+use crate::manifest::{load_manifest, manifest_path, write_manifest, InstallManifest};
+use crate::migrate::{parse_supervisor_conf, parse_systemd_unit};
+
+// ─────────────────────────────────────────────────────────────────────────────
+// corky adopt
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Bring an existing unit or supervisor program under corky management by
+/// recording it in the registry, optionally renaming it to `corky-<name>`.
+pub fn adopt_service(backend: &InitBackend, unit: &str, rename: bool, dry_run: bool) {
+    if !is_root() && !dry_run {
+        println!(
+            "{C_GREEN}[INFO]{C_RESET} Elevating with {} to adopt service...",
+            escalator_or_exit()
+        );
+        let args: Vec<String> = env::args().skip(1).collect();
+        let backend_str = backend.to_string();
+        elevate_privileges(&args, &[(ENV_INIT_BACKEND, &backend_str)]);
+    }
+
+    let name = unit.trim_end_matches(".service").trim_end_matches(".conf");
+    let new_name = if rename {
+        ensure_corky_prefix(name)
+    } else {
+        name.to_string()
+    };
+    if load_manifest(&new_name).is_some() {
+        exit_error(&format!(
+            "{} is already managed by corky ({}).",
+            new_name,
+            manifest_path(&new_name).display()
+        ));
+    }
+
+    section(&format!("Reading {}", name));
+    let (source, mut manifest) = match backend {
+        InitBackend::Systemd { .. } => read_systemd_unit(name),
+        InitBackend::Supervisor => read_supervisor_program(name),
+    };
+    println!("{C_GREEN}[INFO]{C_RESET} Found {}", source.display());
+    println!("  Command: {} {}", manifest.exec_path.display(), manifest.args.join(" "));
+    println!("  User:    {}", manifest.user);
+    println!("  Dir:     {}", manifest.working_dir.display());
+
+    if manifest.group.is_empty() {
+        manifest.group = primary_group_for_user(&manifest.user);
+    }
+    if manifest.description.is_empty() {
+        manifest.description = format!("{} (adopted by corky)", name);
+    }
+    manifest.backend = backend.to_string();
+    manifest.checksum = compute_file_checksum(&manifest.exec_path).unwrap_or_default();
+
+    if rename && new_name != name {
+        manifest.service_name = new_name.clone();
+        rename_into_corky(backend, name, &source, &manifest, dry_run);
+    }
+
+    section("Registering");
+    if dry_run {
+        println!(
+            "{C_CYAN}[DRY-RUN]{C_RESET} Would write manifest: {}",
+            manifest_path(&new_name).display()
+        );
+        return;
+    }
+    match write_manifest(&manifest) {
+        Ok(path) => println!("{C_GREEN}[INFO]{C_RESET} Wrote manifest: {}", path.display()),
+        Err(e) => exit_error(&format!("Could not write install manifest: {}", e)),
+    }
+
+    println!("{C_BGREEN}[SUCCESS]{C_RESET} {} is now managed by corky.", new_name);
+    if !rename {
+        println!(
+            "  Its config was left untouched; `corky diff {} --fix` rewrites it in corky's format.",
+            new_name
+        );
+    }
+}
+
+fn read_systemd_unit(name: &str) -> (PathBuf, InstallManifest) {
+    let fragment = systemd_fragment_path(name).unwrap_or_else(|| systemd_unit_path(name));
+
+    let text = fs::read_to_string(&fragment).unwrap_or_else(|e| {
+        exit_error(&format!("Read {}: {}", fragment.display(), e));
+    });
+    let manifest = parse_systemd_unit(name, &text).unwrap_or_else(|| {
+        exit_error(&format!("{} has no ExecStart= to adopt.", fragment.display()))
+    });
+    (fragment, manifest)
+}
+
+fn read_supervisor_program(name: &str) -> (PathBuf, InstallManifest) {
+    let conf = supervisor_conf_for(name);
+    let text = fs::read_to_string(&conf).unwrap_or_else(|_| {
+        exit_error(&format!("No supervisor config declares [program:{}].", name));
+    });
+    let section = program_section(&text, name).unwrap_or_else(|| {
+        exit_error(&format!("{} does not declare [program:{}].", conf.display(), name))
+    });
+    let manifest = parse_supervisor_conf(name, &section).unwrap_or_else(|| {
+        exit_error(&format!("[program:{}] has no command= to adopt.", name))
+    });
+    (conf, manifest)
+}
+
+/// Replace the legacy unit/program with a corky-generated one under the new name.
+fn rename_into_corky(
+    backend: &InitBackend,
+    old_name: &str,
+    source: &Path,
+    manifest: &InstallManifest,
+    dry_run: bool,
+) {
+    let new_name = manifest.service_name.as_str();
+    let (path, contents) = expected_config(backend, manifest);
+
+    section(&format!("Renaming {} -> {}", old_name, new_name));
+    if dry_run {
+        println!("{C_CYAN}[DRY-RUN]{C_RESET} Would write: {}", path.display());
+        println!(
+            "---------- {} ----------\n{}",
+            path.display(),
+            redact_config(&contents, new_name)
+        );
+        println!("{C_CYAN}[DRY-RUN]{C_RESET} Would stop {} and start {}", old_name, new_name);
+        println!("{C_CYAN}[DRY-RUN]{C_RESET} Would remove {} from {}", old_name, source.display());
+        return;
+    }
+
+    match backend {
+        InitBackend::Systemd { .. } => {
+            let old_unit = format!("{}.service", old_name);
+            let was_enabled = run_cmd_quiet("systemctl", &["is-enabled", &old_unit]);
+            let was_active = run_cmd_quiet("systemctl", &["is-active", &old_unit]);
+
+            fs::write(&path, contents).unwrap_or_else(|e| {
+                exit_error(&format!("write {}: {}", path.display(), e));
+            });
+            let _ = run_cmd("systemctl", &["disable", "--now", &old_unit]);
+            if source.starts_with(UNIT_DIR_SYSTEM) {
+                remove_file_logged(source);
+            } else {
+                eprintln!(
+                    "{C_YELLOW}[WARN]{C_RESET} {} is vendor-provided; left in place (disabled).",
+                    source.display()
+                );
+            }
+            run_cmd_expect_ok("systemctl", &["daemon-reload"]);
+            if was_enabled {
+                run_cmd_expect_ok("systemctl", &["enable", new_name]);
+            }
+            if was_active {
+                run_cmd_expect_ok("systemctl", &["start", new_name]);
+            }
+        }
+        InitBackend::Supervisor => {
+            write_config_file(backend, &path, &contents, new_name).unwrap_or_else(|e| {
+                exit_error(&format!("write {}: {}", path.display(), e));
+            });
+            let _ = run_cmd("supervisorctl", &["stop", old_name]);
+            let text = fs::read_to_string(source).unwrap_or_default();
+            let rest = remove_program_section(&text, old_name);
+            if rest.lines().all(|l| l.trim().is_empty() || l.trim().starts_with(';')) {
+                remove_file_logged(source);
+            } else if let Err(e) = fs::write(source, rest) {
+                eprintln!("{C_YELLOW}[WARN]{C_RESET} write {}: {}", source.display(), e);
+            }
+            // update drops the old program and starts the new one (autostart)
+            run_cmd_expect_ok("supervisorctl", &["reread"]);
+            run_cmd_expect_ok("supervisorctl", &["update"]);
+        }
+    }
+    println!("{C_GREEN}[INFO]{C_RESET} Wrote {}", path.display());
+}
+
+fn remove_file_logged(path: &Path) {
+    match fs::remove_file(path) {
+        Ok(()) => println!("{C_GREEN}[INFO]{C_RESET} Removed {}", path.display()),
+        Err(e) => eprintln!("{C_YELLOW}[WARN]{C_RESET} remove {}: {}", path.display(), e),
+    }
+}
+
+/// Lines belonging to `[program:<name>]`, up to the next section header.
+fn program_section(text: &str, name: &str) -> Option<String> {
+    let header = format!("[program:{}]", name);
+    let mut lines = text.lines().skip_while(|l| l.trim() != header);
+    let first = lines.next()?;
+    let mut out = vec![first];
+    out.extend(lines.take_while(|l| !l.trim_start().starts_with('[')));
+    Some(out.join("\n"))
+}
+
+/// `text` with the `[program:<name>]` section replaced by `block`, leaving
+/// every other program in the file alone. None if the section is missing.
+pub fn replace_program_section(text: &str, name: &str, block: &str) -> Option<String> {
+    let header = format!("[program:{}]", name);
+    let mut out = Vec::new();
+    let mut skipping = false;
+    let mut found = false;
+    for line in text.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with('[') {
+            if skipping {
+                // keep a blank line between the new block and the next section
+                out.push("");
+            }
+            skipping = trimmed.trim_end() == header;
+            if skipping {
+                found = true;
+                out.extend(block.trim_end().lines());
+            }
+        }
+        if !skipping {
+            out.push(line);
+        }
+    }
+    let mut rest = out.join("\n");
+    rest.push('\n');
+    found.then_some(rest)
+}
+
+/// Whether `text` declares any section besides `[program:<name>]`.
+pub fn has_other_sections(text: &str, name: &str) -> bool {
+    let header = format!("[program:{}]", name);
+    text.lines()
+        .map(str::trim)
+        .any(|l| l.starts_with('[') && l != header)
+}
+
+pub fn remove_program_section(text: &str, name: &str) -> String {
+    let header = format!("[program:{}]", name);
+    let mut out = Vec::new();
+    let mut skipping = false;
+    for line in text.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with('[') {
+            skipping = trimmed.trim_end() == header;
+        }
+        if !skipping {
+            out.push(line);
+        }
+    }
+    let mut rest = out.join("\n");
+    rest.push('\n');
+    rest
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Tests
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONF: &str = "[program:a]\ncommand=/bin/a\n\n[program:b]\ncommand=/bin/b\nuser=bob\n";
+
+    #[test]
+    fn test_program_section() {
+        assert_eq!(
+            program_section(CONF, "b").unwrap(),
+            "[program:b]\ncommand=/bin/b\nuser=bob"
+        );
+        assert_eq!(program_section(CONF, "a").unwrap(), "[program:a]\ncommand=/bin/a\n");
+        assert!(program_section(CONF, "c").is_none());
+    }
+
+    #[test]
+    fn test_remove_program_section() {
+        assert_eq!(remove_program_section(CONF, "a"), "[program:b]\ncommand=/bin/b\nuser=bob\n");
+        assert_eq!(remove_program_section(CONF, "b"), "[program:a]\ncommand=/bin/a\n\n");
+    }
+
+    #[test]
+    fn test_replace_program_section() {
+        let block = "[program:a]\ncommand=/opt/a --new\nautostart=true\n";
+        assert_eq!(
+            replace_program_section(CONF, "a", block).unwrap(),
+            "[program:a]\ncommand=/opt/a --new\nautostart=true\n\n[program:b]\ncommand=/bin/b\nuser=bob\n"
+        );
+        assert_eq!(
+            replace_program_section(CONF, "b", "[program:b]\ncommand=/opt/b\n").unwrap(),
+            "[program:a]\ncommand=/bin/a\n\n[program:b]\ncommand=/opt/b\n"
+        );
+        assert!(replace_program_section(CONF, "c", block).is_none());
+        assert!(has_other_sections(CONF, "a"));
+        assert!(!has_other_sections("; x\n[program:a]\ncommand=/bin/a\n", "a"));
+    }
+}
@@ -0,0 +1,326 @@
+use std::fs;
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::deploy::{asset_relative_path, copy_recursive};
+use crate::init::private_temp_dir;
+use crate::pack::{gzip, tar, PackEntry};
+
+/// Cargo.toml-shaped metadata inside an archive: `[package]` name and
+/// description, the package's `[corky]` table and `[archive]` checksum.
+pub const ARCHIVE_MANIFEST: &str = "corky.toml";
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Install sources
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Where `corky install` gets the binary from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum InstallSource {
+    /// `cargo build` the package in the current directory.
+    Build,
+    /// A prebuilt binary; metadata still comes from ./Cargo.toml.
+    Artifact(PathBuf),
+    /// A `corky package` archive carrying the binary and its metadata.
+    Archive(PathBuf),
+}
+
+/// An archive unpacked into a private temporary directory, removed on drop.
+pub struct ExtractedArchive {
+    dir: PathBuf,
+}
+
+impl ExtractedArchive {
+    pub fn open(archive: &Path) -> Result<Self, String> {
+        if !archive.is_file() {
+            return Err(format!("{} not found.", archive.display()));
+        }
+        let dir = private_temp_dir("corky-archive")
+            .map_err(|e| format!("create temp dir: {}", e))?;
+        let extracted = ExtractedArchive { dir };
+        // Unpacked as root: only plain files and directories may come out
+        let listing = Command::new("tar")
+            .arg("-tvzf")
+            .arg(archive)
+            .output()
+            .ok()
+            .filter(|o| o.status.success())
+            .ok_or_else(|| format!("Could not read {}.", archive.display()))?;
+        if let Some(member) = String::from_utf8_lossy(&listing.stdout)
+            .lines()
+            .find(|l| is_link_member(l))
+        {
+            return Err(format!(
+                "{} contains a link, which corky won't unpack: {}",
+                archive.display(),
+                member
+            ));
+        }
+        let ok = Command::new("tar")
+            .arg("-xzf")
+            .arg(archive)
+            .arg("-C")
+            .arg(&extracted.dir)
+            .arg("--no-same-owner")
+            .status()
+            .is_ok_and(|s| s.success());
+        if !ok {
+            return Err(format!("Could not extract {}.", archive.display()));
+        }
+        reject_links(&extracted.dir)
+            .map_err(|e| format!("{}: {}", archive.display(), e))?;
+        if !extracted.manifest_path().is_file() {
+            return Err(format!(
+                "{} has no {}; create it with `corky package`.",
+                archive.display(),
+                ARCHIVE_MANIFEST
+            ));
+        }
+        Ok(extracted)
+    }
+
+    pub fn manifest_path(&self) -> PathBuf {
+        self.dir.join(ARCHIVE_MANIFEST)
+    }
+
+    pub fn binary_path(&self, pkg_name: &str) -> PathBuf {
+        self.dir.join("bin").join(pkg_name)
+    }
+
+    /// Root that `[corky] assets` paths are relative to.
+    pub fn assets_dir(&self) -> PathBuf {
+        self.dir.join("assets")
+    }
+
+    /// The binary checksum recorded when the archive was made.
+    pub fn checksum(&self) -> Option<String> {
+        let text = fs::read_to_string(self.manifest_path()).ok()?;
+        let table: toml::Table = toml::from_str(&text).ok()?;
+        Some(table.get("archive")?.get("checksum")?.as_str()?.to_string())
+    }
+}
+
+/// A `tar -tv` line for anything but a plain file or directory; hard links
+/// list as regular files "link to" their target with some tars.
+fn is_link_member(line: &str) -> bool {
+    !matches!(line.chars().next(), Some('-' | 'd')) || line.contains(" link to ")
+}
+
+/// Fail on symlinks and hard-linked files anywhere under `dir`.
+fn reject_links(dir: &Path) -> Result<(), String> {
+    for entry in fs::read_dir(dir).map_err(|e| e.to_string())? {
+        let path = entry.map_err(|e| e.to_string())?.path();
+        let metadata = fs::symlink_metadata(&path).map_err(|e| e.to_string())?;
+        if metadata.file_type().is_symlink() || (metadata.is_file() && metadata.nlink() > 1) {
+            return Err(format!("{} is a link", path.display()));
+        }
+        if metadata.is_dir() {
+            reject_links(&path)?;
+        }
+    }
+    Ok(())
+}
+
+impl Drop for ExtractedArchive {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Writing archives (`corky package --format tar`)
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Bundle the binary, its `[corky]` metadata and assets into a tarball that
+/// `corky install --archive` accepts on hosts without cargo.
+pub fn write_archive(
+    output: &Path,
+    pkg_name: &str,
+    description: &str,
+    binary: &Path,
+    checksum: &str,
+    assets: &[String],
+    mtime: u64,
+) -> Result<(), String> {
+    let stage = private_temp_dir("corky-package").map_err(|e| format!("create temp dir: {}", e))?;
+    let result = stage_archive(&stage, pkg_name, description, binary, checksum, assets)
+        .and_then(|()| write_tarball(&stage, output, mtime));
+    let _ = fs::remove_dir_all(&stage);
+    result
+}
+
+/// Lay out corky.toml, bin/<name> and assets/ under `stage`.
+fn stage_archive(
+    stage: &Path,
+    pkg_name: &str,
+    description: &str,
+    binary: &Path,
+    checksum: &str,
+    assets: &[String],
+) -> Result<(), String> {
+    let cargo_toml = fs::read_to_string("Cargo.toml").map_err(|e| format!("Cargo.toml: {}", e))?;
+    let manifest = archive_manifest(&cargo_toml, pkg_name, description, checksum)?;
+    let bin_dir = stage.join("bin");
+    fs::create_dir_all(&bin_dir).map_err(|e| format!("create {}: {}", bin_dir.display(), e))?;
+    fs::write(stage.join(ARCHIVE_MANIFEST), manifest)
+        .map_err(|e| format!("write {}: {}", ARCHIVE_MANIFEST, e))?;
+
+    let staged_bin = bin_dir.join(pkg_name);
+    fs::copy(binary, &staged_bin).map_err(|e| format!("copy {}: {}", binary.display(), e))?;
+    fs::set_permissions(&staged_bin, fs::Permissions::from_mode(0o755))
+        .map_err(|e| format!("chmod {}: {}", staged_bin.display(), e))?;
+
+    for asset in assets {
+        let relative = asset_relative_path(asset).ok_or_else(|| {
+            format!("[corky] assets: '{}' must be a relative path inside the package.", asset)
+        })?;
+        copy_recursive(&relative, &stage.join("assets").join(&relative))
+            .map_err(|e| format!("[corky] assets: {}: {}", asset, e))?;
+    }
+    Ok(())
+}
+
+/// corky.toml contents: the package's `[corky]` table kept verbatim.
+fn archive_manifest(
+    cargo_toml: &str,
+    pkg_name: &str,
+    description: &str,
+    checksum: &str,
+) -> Result<String, String> {
+    let parsed: toml::Table =
+        toml::from_str(cargo_toml).map_err(|e| format!("Failed to parse Cargo.toml: {}", e))?;
+    let mut package = toml::Table::new();
+    package.insert("name".into(), pkg_name.into());
+    package.insert("description".into(), description.into());
+    let mut archive = toml::Table::new();
+    archive.insert("checksum".into(), checksum.into());
+
+    let mut doc = toml::Table::new();
+    doc.insert("package".into(), package.into());
+    if let Some(corky) = parsed.get("corky") {
+        doc.insert("corky".into(), corky.clone());
+    }
+    doc.insert("archive".into(), archive.into());
+    toml::to_string(&doc).map_err(|e| e.to_string())
+}
+
+/// Write the staged tree as a .tar.gz without the host's tar.
+fn write_tarball(stage: &Path, output: &Path, mtime: u64) -> Result<(), String> {
+    if let Some(parent) = output.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent).map_err(|e| format!("create {}: {}", parent.display(), e))?;
+    }
+    let mut entries = Vec::new();
+    stage_entries(stage, Path::new(""), &mut entries)
+        .map_err(|e| format!("read {}: {}", stage.display(), e))?;
+    let tarball = gzip(&tar(&entries, "", mtime)?);
+    fs::write(output, tarball).map_err(|e| format!("write {}: {}", output.display(), e))
+}
+
+/// Everything under `dir` in name order, at paths relative to the stage.
+fn stage_entries(dir: &Path, relative: &Path, entries: &mut Vec<PackEntry>) -> std::io::Result<()> {
+    let mut children: Vec<_> = fs::read_dir(dir)?.flatten().map(|e| e.path()).collect();
+    children.sort();
+    for child in children {
+        let path = relative.join(child.file_name().unwrap_or_default());
+        let metadata = fs::symlink_metadata(&child)?;
+        if metadata.is_dir() {
+            entries.push(PackEntry::dir(&path.to_string_lossy()));
+            stage_entries(&child, &path, entries)?;
+        } else {
+            let mode = metadata.permissions().mode() & 0o777;
+            entries.push(PackEntry::file(&path.to_string_lossy(), mode, fs::read(&child)?));
+        }
+    }
+    Ok(())
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Tests
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn test_archive_manifest() {
+        let cargo_toml = r#"
+[package]
+name = "api"
+version = "1.2.0"
+
+[dependencies]
+serde = "1"
+
+[corky]
+is_corky_package = true
+user = "create:api"
+
+[corky.limits]
+memory_max = "512M"
+"#;
+        let text = archive_manifest(cargo_toml, "api", "The API", "00ff").unwrap();
+        let doc: toml::Table = toml::from_str(&text).unwrap();
+        assert_eq!(doc["package"]["name"].as_str(), Some("api"));
+        assert_eq!(doc["package"]["description"].as_str(), Some("The API"));
+        assert_eq!(doc["corky"]["user"].as_str(), Some("create:api"));
+        assert_eq!(doc["corky"]["limits"]["memory_max"].as_str(), Some("512M"));
+        assert_eq!(doc["archive"]["checksum"].as_str(), Some("00ff"));
+        assert!(!doc.contains_key("dependencies"));
+    }
+
+    #[test]
+    fn test_archive_roundtrip() {
+        let stage = env::temp_dir().join(format!("corky-archive-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&stage);
+        fs::create_dir_all(stage.join("bin")).unwrap();
+        fs::write(
+            stage.join(ARCHIVE_MANIFEST),
+            "[package]\nname = \"api\"\n\n[archive]\nchecksum = \"abc\"\n",
+        )
+        .unwrap();
+        fs::write(stage.join("bin").join("api"), "binary").unwrap();
+        let tarball = stage.with_extension("tar.gz");
+        write_tarball(&stage, &tarball, 0).unwrap();
+
+        let extracted = ExtractedArchive::open(&tarball).unwrap();
+        assert_eq!(extracted.checksum().as_deref(), Some("abc"));
+        assert_eq!(fs::read_to_string(extracted.binary_path("api")).unwrap(), "binary");
+        let dir = extracted.dir.clone();
+        drop(extracted);
+        assert!(!dir.exists());
+        fs::remove_dir_all(&stage).unwrap();
+        fs::remove_file(&tarball).unwrap();
+    }
+
+    #[test]
+    fn test_archive_rejects_symlinked_assets() {
+        let stage = env::temp_dir().join(format!("corky-archive-link-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&stage);
+        fs::create_dir_all(stage.join("bin")).unwrap();
+        fs::create_dir_all(stage.join("assets")).unwrap();
+        fs::write(stage.join(ARCHIVE_MANIFEST), "[package]\nname = \"api\"\n").unwrap();
+        fs::write(stage.join("bin").join("api"), "binary").unwrap();
+        std::os::unix::fs::symlink("/etc/shadow", stage.join("assets").join("x")).unwrap();
+        let tarball = stage.with_extension("tar.gz");
+        let packed = Command::new("tar")
+            .arg("-czf")
+            .arg(&tarball)
+            .arg("-C")
+            .arg(&stage)
+            .args([ARCHIVE_MANIFEST, "bin", "assets"])
+            .status()
+            .unwrap();
+        assert!(packed.success());
+
+        let err = ExtractedArchive::open(&tarball).err().unwrap();
+        assert!(err.contains("contains a link"), "{}", err);
+        assert!(is_link_member("lrwxrwxrwx root/root 0 2026-01-01 00:00 assets/x -> /etc/shadow"));
+        assert!(is_link_member("hrw-r--r-- root/root 0 2026-01-01 00:00 assets/y link to bin/api"));
+        assert!(!is_link_member("-rw-r--r-- root/root 6 2026-01-01 00:00 bin/api"));
+        fs::remove_dir_all(&stage).unwrap();
+        fs::remove_file(&tarball).unwrap();
+    }
+}
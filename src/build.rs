@@ -0,0 +1,300 @@
+use serde::Deserialize;
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::init::{exit_error, C_GREEN, C_RED, C_RESET, ENV_ELEVATED_FLAG};
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Build options (`[corky.build]`)
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Environment variable cargo reads for its target directory.
+const ENV_CARGO_TARGET_DIR: &str = "CARGO_TARGET_DIR";
+/// The built binary's absolute path, resolved before elevating; root's HOME
+/// and cargo config may point `cargo metadata` at a different target dir.
+pub const ENV_ARTIFACT_PATH: &str = "CORKY_ARTIFACT_PATH";
+
+/// How `cargo build` is invoked for install and dev. Unset fields keep
+/// cargo's defaults, except that the profile defaults to release.
+#[derive(Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct BuildOptions {
+    /// Features to enable (`--features`).
+    #[serde(default)]
+    pub features: Vec<String>,
+    /// Build without the package's default features.
+    #[serde(default)]
+    pub no_default_features: bool,
+    /// Cargo profile; "release" when unset.
+    pub profile: Option<String>,
+    /// Target triple, e.g. "x86_64-unknown-linux-musl".
+    pub target: Option<String>,
+    /// Require Cargo.lock to be up to date (`--locked`).
+    #[serde(default)]
+    pub locked: bool,
+    /// Build without network access (`--offline`).
+    #[serde(default)]
+    pub offline: bool,
+}
+
+impl BuildOptions {
+    /// `self` from Cargo.toml with command-line flags applied on top:
+    /// features add up, switches turn on, profile and target replace.
+    pub fn with_overrides(&self, cli: &BuildOptions) -> BuildOptions {
+        let mut features = self.features.clone();
+        for feature in &cli.features {
+            if !features.contains(feature) {
+                features.push(feature.clone());
+            }
+        }
+        BuildOptions {
+            features,
+            no_default_features: self.no_default_features || cli.no_default_features,
+            profile: cli.profile.clone().or_else(|| self.profile.clone()),
+            target: cli.target.clone().or_else(|| self.target.clone()),
+            locked: self.locked || cli.locked,
+            offline: self.offline || cli.offline,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        let is_word = |s: &str| {
+            !s.is_empty()
+                && s.chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        };
+        if let Some(profile) = &self.profile
+            && !is_word(profile)
+        {
+            return Err(format!("invalid profile '{}'", profile));
+        }
+        if let Some(target) = &self.target
+            && !target.split('.').all(is_word)
+        {
+            return Err(format!("invalid target '{}'", target));
+        }
+        for feature in &self.features {
+            // "dep/feature" and "dep?/feature" are valid feature references
+            if !feature.split(['/', '?']).filter(|s| !s.is_empty()).all(is_word) {
+                return Err(format!("invalid feature '{}'", feature));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn profile(&self) -> &str {
+        self.profile.as_deref().unwrap_or("release")
+    }
+
+    /// Arguments after `cargo`.
+    pub fn cargo_args(&self) -> Vec<String> {
+        let mut args = vec!["build".to_string()];
+        match self.profile() {
+            "release" => args.push("--release".to_string()),
+            profile => args.extend(["--profile".to_string(), profile.to_string()]),
+        }
+        if !self.features.is_empty() {
+            args.extend(["--features".to_string(), self.features.join(",")]);
+        }
+        if self.no_default_features {
+            args.push("--no-default-features".to_string());
+        }
+        if let Some(target) = &self.target {
+            args.extend(["--target".to_string(), target.clone()]);
+        }
+        if self.locked {
+            args.push("--locked".to_string());
+        }
+        if self.offline {
+            args.push("--offline".to_string());
+        }
+        args
+    }
+
+    /// `cargo build ...` as shown to the user.
+    pub fn display(&self) -> String {
+        format!("cargo {}", self.cargo_args().join(" "))
+    }
+
+    pub fn command(&self) -> Command {
+        let mut cmd = Command::new("cargo");
+        cmd.args(self.cargo_args());
+        cmd
+    }
+
+    /// Run the build in the foreground, exiting with cargo's status on failure.
+    pub fn build_or_exit(&self) {
+        let build_cmd = self.display();
+        println!("{C_GREEN}[INFO]{C_RESET} Running: {}", build_cmd);
+        let status = self
+            .command()
+            .stdin(Stdio::inherit())
+            .stdout(Stdio::inherit())
+            .stderr(Stdio::inherit())
+            .status()
+            .unwrap_or_else(|e| exit_error(&format!("Failed to run {}: {}", build_cmd, e)));
+        if !status.success() {
+            eprintln!("{C_RED}[ERROR]{C_RESET} {} failed.", build_cmd);
+            std::process::exit(status.code().unwrap_or(1));
+        }
+    }
+
+    /// Where the build leaves binary `bin`.
+    pub fn artifact_path(&self, bin: &str) -> PathBuf {
+        self.artifact_path_in(&target_dir(), bin)
+    }
+
+    /// `artifact_path` as an absolute path, for passing through elevation.
+    pub fn absolute_artifact_path(&self, bin: &str) -> PathBuf {
+        env::current_dir().unwrap_or_default().join(self.artifact_path(bin))
+    }
+
+    /// In an elevated run, the path the unprivileged run resolved; otherwise
+    /// `artifact_path`.
+    pub fn elevated_artifact_path(&self, bin: &str) -> PathBuf {
+        match env::var_os(ENV_ARTIFACT_PATH) {
+            Some(path) if !path.is_empty() && env::var_os(ENV_ELEVATED_FLAG).is_some() => {
+                PathBuf::from(path)
+            }
+            _ => self.artifact_path(bin),
+        }
+    }
+
+    fn artifact_path_in(&self, target_dir: &Path, bin: &str) -> PathBuf {
+        let mut path = target_dir.to_path_buf();
+        if let Some(target) = &self.target {
+            path.push(target);
+        }
+        // The built-in dev and test profiles share target/debug
+        path.push(match self.profile() {
+            "dev" | "test" => "debug",
+            "bench" => "release",
+            profile => profile,
+        });
+        path.join(bin)
+    }
+}
+
+/// Cargo's target directory for the package in the current directory:
+/// $CARGO_TARGET_DIR, then build.target-dir from cargo's config, then ./target.
+pub fn target_dir() -> PathBuf {
+    if let Some(dir) = env::var_os(ENV_CARGO_TARGET_DIR).filter(|d| !d.is_empty()) {
+        return PathBuf::from(dir);
+    }
+    Command::new("cargo")
+        .args(["metadata", "--format-version", "1", "--no-deps", "--offline"])
+        .stderr(Stdio::null())
+        .output()
+        .ok()
+        .filter(|o| o.status.success())
+        .and_then(|o| json_string_field(&String::from_utf8_lossy(&o.stdout), "target_directory"))
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("target"))
+}
+
+/// The string value of the first `"key":"..."` in compact JSON.
+fn json_string_field(json: &str, key: &str) -> Option<String> {
+    let start = json.find(&format!("\"{}\":\"", key))? + key.len() + 4;
+    let mut value = String::new();
+    let mut chars = json[start..].chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => return Some(value),
+            '\\' => match chars.next()? {
+                'n' => value.push('\n'),
+                't' => value.push('\t'),
+                other => value.push(other),
+            },
+            c => value.push(c),
+        }
+    }
+    None
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Tests
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cargo_args() {
+        assert_eq!(BuildOptions::default().display(), "cargo build --release");
+        let options = BuildOptions {
+            features: vec!["tls".to_string(), "metrics".to_string()],
+            no_default_features: true,
+            profile: Some("dist".to_string()),
+            target: Some("x86_64-unknown-linux-musl".to_string()),
+            locked: true,
+            offline: false,
+        };
+        assert_eq!(
+            options.display(),
+            "cargo build --profile dist --features tls,metrics --no-default-features \
+             --target x86_64-unknown-linux-musl --locked"
+        );
+    }
+
+    #[test]
+    fn test_with_overrides() {
+        let config = BuildOptions {
+            features: vec!["tls".to_string()],
+            profile: Some("dist".to_string()),
+            locked: true,
+            ..Default::default()
+        };
+        let cli = BuildOptions {
+            features: vec!["tls".to_string(), "metrics".to_string()],
+            target: Some("aarch64-unknown-linux-musl".to_string()),
+            offline: true,
+            ..Default::default()
+        };
+        let merged = config.with_overrides(&cli);
+        assert_eq!(merged.features, ["tls", "metrics"]);
+        assert_eq!(merged.profile(), "dist");
+        assert_eq!(merged.target.as_deref(), Some("aarch64-unknown-linux-musl"));
+        assert!(merged.locked && merged.offline);
+    }
+
+    #[test]
+    fn test_artifact_path() {
+        let dir = Path::new("/build/target");
+        assert_eq!(
+            BuildOptions::default().artifact_path_in(dir, "svc"),
+            Path::new("/build/target/release/svc")
+        );
+        let options = BuildOptions {
+            profile: Some("dev".to_string()),
+            target: Some("x86_64-unknown-linux-musl".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            options.artifact_path_in(dir, "svc"),
+            Path::new("/build/target/x86_64-unknown-linux-musl/debug/svc")
+        );
+    }
+
+    #[test]
+    fn test_validate() {
+        let mut options = BuildOptions {
+            features: vec!["serde/derive".to_string(), "dep?/x".to_string()],
+            ..Default::default()
+        };
+        assert!(options.validate().is_ok());
+        options.profile = Some("../x".to_string());
+        assert!(options.validate().is_err());
+    }
+
+    #[test]
+    fn test_json_string_field() {
+        let json = r#"{"packages":[],"target_directory":"/srv/a \"b\"\\target","version":1}"#;
+        assert_eq!(
+            json_string_field(json, "target_directory").as_deref(),
+            Some("/srv/a \"b\"\\target")
+        );
+        assert_eq!(json_string_field(json, "missing"), None);
+    }
+}
@@ -0,0 +1,194 @@
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+use crate::init::{exit_error, run_cmd, section, CORKY_ETC_DIR, C_CYAN, C_GREEN, C_RESET, C_YELLOW};
+use crate::manifest::InstallManifest;
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Managed service directories
+// ─────────────────────────────────────────────────────────────────────────────
+pub const STATE_ROOT: &str = "/var/lib/corky";
+pub const LOGS_ROOT: &str = "/var/log/corky";
+
+/// Working directory and persistent data (systemd StateDirectory=).
+pub fn state_dir(service_name: &str) -> PathBuf {
+    PathBuf::from(STATE_ROOT).join(service_name)
+}
+
+/// Service configuration (systemd ConfigurationDirectory=).
+pub fn config_dir(service_name: &str) -> PathBuf {
+    PathBuf::from(CORKY_ETC_DIR).join(service_name)
+}
+
+/// Log files the service writes itself (systemd LogsDirectory=).
+pub fn logs_dir(service_name: &str) -> PathBuf {
+    PathBuf::from(LOGS_ROOT).join(service_name)
+}
+
+/// The three managed directories, as the environment variables systemd sets
+/// for them. Supervisor services get the same variables explicitly.
+pub fn managed_dir_env(service_name: &str) -> [(&'static str, PathBuf); 3] {
+    [
+        ("STATE_DIRECTORY", state_dir(service_name)),
+        ("CONFIGURATION_DIRECTORY", config_dir(service_name)),
+        ("LOGS_DIRECTORY", logs_dir(service_name)),
+    ]
+}
+
+/// Create the managed directories and hand them to the service user.
+/// With DynamicUser= systemd assigns ownership itself at start.
+pub fn prepare_service_dirs(manifest: &InstallManifest, dry_run: bool) {
+    section("Preparing service directories");
+    let owner = format!("{}:{}", manifest.user, manifest.group);
+    for (_, dir) in managed_dir_env(&manifest.service_name) {
+        if dry_run {
+            println!(
+                "{C_CYAN}[DRY-RUN]{C_RESET} Would create {} (owner {})",
+                dir.display(),
+                owner
+            );
+            continue;
+        }
+        if let Err(e) = fs::create_dir_all(&dir) {
+            exit_error(&format!("create {}: {}", dir.display(), e));
+        }
+        if !manifest.dynamic_user {
+            let _ = run_cmd("chown", &["-R", &owner, &dir.to_string_lossy()]);
+        }
+        println!("{C_GREEN}[INFO]{C_RESET} Ready: {}", dir.display());
+    }
+}
+
+/// Copy `[corky] assets` from the package checkout into the state directory,
+/// keeping their relative paths.
+pub fn install_assets(
+    assets: &[String],
+    source_root: &Path,
+    manifest: &InstallManifest,
+    dry_run: bool,
+) {
+    if assets.is_empty() {
+        return;
+    }
+    section("Installing assets");
+    let dest_root = state_dir(&manifest.service_name);
+    for asset in assets {
+        let Some(relative) = asset_relative_path(asset) else {
+            exit_error(&format!(
+                "[corky] assets: '{}' must be a relative path inside the package.",
+                asset
+            ));
+        };
+        let from = source_root.join(&relative);
+        let to = dest_root.join(&relative);
+        if !from.exists() {
+            exit_error(&format!("[corky] assets: {} does not exist.", from.display()));
+        }
+        if dry_run {
+            println!(
+                "{C_CYAN}[DRY-RUN]{C_RESET} Would copy {} -> {}",
+                from.display(),
+                to.display()
+            );
+            continue;
+        }
+        if let Err(e) = copy_recursive(&from, &to) {
+            exit_error(&format!("copy {} -> {}: {}", from.display(), to.display(), e));
+        }
+        println!("{C_GREEN}[INFO]{C_RESET} Copied {} -> {}", asset, to.display());
+    }
+    if !dry_run && !manifest.dynamic_user {
+        let owner = format!("{}:{}", manifest.user, manifest.group);
+        let _ = run_cmd("chown", &["-R", &owner, &dest_root.to_string_lossy()]);
+    }
+}
+
+/// Warn about settings that only make sense with managed directories.
+pub fn warn_unused_assets(assets: &[String]) {
+    if !assets.is_empty() {
+        eprintln!(
+            "{C_YELLOW}[WARN]{C_RESET} [corky] assets are ignored with managed_dirs = false."
+        );
+    }
+}
+
+/// A normalized relative path, or None if it is absolute or escapes the package.
+pub fn asset_relative_path(asset: &str) -> Option<PathBuf> {
+    let mut out = PathBuf::new();
+    for component in Path::new(asset).components() {
+        match component {
+            Component::Normal(part) => out.push(part),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+    if out.as_os_str().is_empty() { None } else { Some(out) }
+}
+
+/// Copy files and directories; a symlink is an error rather than followed,
+/// so an asset can't pull in files from elsewhere on the host.
+pub fn copy_recursive(from: &Path, to: &Path) -> io::Result<()> {
+    let metadata = fs::symlink_metadata(from)?;
+    if metadata.file_type().is_symlink() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} is a symlink", from.display()),
+        ));
+    }
+    if metadata.is_dir() {
+        fs::create_dir_all(to)?;
+        for entry in fs::read_dir(from)? {
+            let entry = entry?;
+            copy_recursive(&entry.path(), &to.join(entry.file_name()))?;
+        }
+    } else {
+        if let Some(parent) = to.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(from, to)?;
+    }
+    Ok(())
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Tests
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_asset_relative_path() {
+        assert_eq!(
+            asset_relative_path("./static/index.html"),
+            Some(PathBuf::from("static/index.html"))
+        );
+        assert_eq!(asset_relative_path("config"), Some(PathBuf::from("config")));
+        assert_eq!(asset_relative_path("/etc/passwd"), None);
+        assert_eq!(asset_relative_path("../secrets"), None);
+        assert_eq!(asset_relative_path("."), None);
+    }
+
+    #[test]
+    fn test_managed_dirs() {
+        let env = managed_dir_env("corky-api");
+        assert_eq!(env[0].1, PathBuf::from("/var/lib/corky/corky-api"));
+        assert_eq!(env[1].1, PathBuf::from("/etc/corky/corky-api"));
+        assert_eq!(env[2].1, PathBuf::from("/var/log/corky/corky-api"));
+    }
+
+    #[test]
+    fn test_copy_recursive_rejects_symlinks() {
+        let dir = std::env::temp_dir().join(format!("corky-deploy-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("assets")).unwrap();
+        fs::write(dir.join("assets").join("index.html"), "ok").unwrap();
+        std::os::unix::fs::symlink("/etc/hostname", dir.join("assets").join("x")).unwrap();
+        let err = copy_recursive(&dir.join("assets"), &dir.join("out")).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert!(!dir.join("out").join("x").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
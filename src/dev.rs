@@ -0,0 +1,271 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use crate::build::ENV_ARTIFACT_PATH;
+use crate::escalate::{elevation_env, escalator_or_exit, refresh_credentials};
+use crate::init::{
+    compute_file_checksum, corky_config, ensure_corky_prefix, exit_error, is_root,
+    pkg_name_and_description, run_cmd, section, validate_corky_package_or_exit, InitBackend,
+    C_BGREEN, C_GREEN, C_RESET, C_YELLOW, ENV_BINARY_CHECKSUM, ENV_INIT_BACKEND,
+    ENV_ORIGINAL_CWD,
+};
+use crate::instance::instance_name;
+use crate::manifest::{load_manifest, write_manifest, InstallManifest};
+
+/// How often the package sources are checked for changes.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Quiet period after a change so editors can finish writing.
+const SETTLE_DELAY: Duration = Duration::from_millis(300);
+
+// ─────────────────────────────────────────────────────────────────────────────
+// corky dev
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Watch the package, rebuild on change and swap in the new binary only when
+/// the build succeeds, streaming the service's logs meanwhile. `apply` is the
+/// privileged half, re-run as root for each swap.
+pub fn run_dev(backend: &InitBackend, apply: bool) -> ! {
+    if apply {
+        apply_build(backend, &env::var(ENV_BINARY_CHECKSUM).unwrap_or_default());
+        std::process::exit(0);
+    }
+    validate_corky_package_or_exit();
+    let (pkg_name, _) = pkg_name_and_description()
+        .unwrap_or_else(|| exit_error("Could not read the package name from Cargo.toml."));
+    let service_name = ensure_corky_prefix(&pkg_name);
+    let Some(manifest) = load_manifest(&service_name).filter(|m| m.backend == backend.to_string())
+    else {
+        exit_error(&format!(
+            "{} is not installed on {}; run `corky install` once first.",
+            service_name, backend
+        ));
+    };
+    // Ask for the password now, not in the middle of the first rebuild
+    refresh_credentials();
+
+    section("Streaming logs");
+    let mut logs = follow_logs(backend, &manifest);
+
+    section("Watching src/ and Cargo.toml (Ctrl-C to stop)");
+    let mut snapshot = source_snapshot();
+    rebuild_and_swap(backend, &pkg_name);
+    loop {
+        thread::sleep(POLL_INTERVAL);
+        if source_snapshot() == snapshot {
+            continue;
+        }
+        thread::sleep(SETTLE_DELAY);
+        snapshot = source_snapshot();
+        rebuild_and_swap(backend, &pkg_name);
+        if let Some(child) = logs.as_mut()
+            && child.try_wait().ok().flatten().is_some()
+        {
+            // The log follower exits when e.g. supervisor restarts the program
+            logs = follow_logs(backend, &manifest);
+        }
+    }
+}
+
+/// Modification times of everything that affects the build.
+fn source_snapshot() -> BTreeMap<PathBuf, SystemTime> {
+    let mut files = BTreeMap::new();
+    for path in ["Cargo.toml", "Cargo.lock", "build.rs"] {
+        record_mtime(Path::new(path), &mut files);
+    }
+    collect_mtimes(Path::new("src"), &mut files);
+    files
+}
+
+fn collect_mtimes(dir: &Path, files: &mut BTreeMap<PathBuf, SystemTime>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_mtimes(&path, files);
+        } else {
+            record_mtime(&path, files);
+        }
+    }
+}
+
+fn record_mtime(path: &Path, files: &mut BTreeMap<PathBuf, SystemTime>) {
+    if let Ok(modified) = fs::metadata(path).and_then(|m| m.modified()) {
+        files.insert(path.to_path_buf(), modified);
+    }
+}
+
+/// Build; on success hand the binary to the privileged half unless it is unchanged.
+fn rebuild_and_swap(backend: &InitBackend, pkg_name: &str) {
+    // Re-read each time so edits to [corky.build] apply to the next build
+    let build = corky_config().build;
+    if let Err(e) = build.validate() {
+        eprintln!("{C_YELLOW}[WARN]{C_RESET} [corky.build] {}; not building.", e);
+        return;
+    }
+    section(&format!("Building ({})", build.profile()));
+    let built = build.command().status().is_ok_and(|s| s.success());
+    if !built {
+        eprintln!("{C_YELLOW}[WARN]{C_RESET} Build failed; the running version is left in place.");
+        return;
+    }
+    let target_bin = build.artifact_path(pkg_name);
+    let Some(checksum) = compute_file_checksum(&target_bin) else {
+        eprintln!("{C_YELLOW}[WARN]{C_RESET} Could not read {}", target_bin.display());
+        return;
+    };
+    let service_name = ensure_corky_prefix(pkg_name);
+    if load_manifest(&service_name).is_some_and(|m| m.checksum == checksum) {
+        println!("{C_GREEN}[INFO]{C_RESET} Binary unchanged; nothing to restart.");
+        return;
+    }
+
+    if is_root() {
+        apply_build(backend, &checksum);
+        return;
+    }
+    let exe = env::current_exe().unwrap_or_else(|e| exit_error(&format!("current exe: {}", e)));
+    let cwd = env::current_dir().unwrap_or_default();
+    let mut vars = elevation_env();
+    vars.push((ENV_INIT_BACKEND, backend.to_string()));
+    vars.push((ENV_BINARY_CHECKSUM, checksum));
+    vars.push((
+        ENV_ARTIFACT_PATH,
+        build.absolute_artifact_path(pkg_name).to_string_lossy().into_owned(),
+    ));
+    let vars: Vec<(&str, &str)> = vars.iter().map(|(k, v)| (*k, v.as_str())).collect();
+    let args = ["dev".to_string(), "--apply".to_string()];
+    let status = escalator_or_exit()
+        .command(&exe, &args, &vars, Some(&cwd), true)
+        .status();
+    if !status.is_ok_and(|s| s.success()) {
+        eprintln!("{C_YELLOW}[WARN]{C_RESET} Could not swap in the new binary.");
+    }
+}
+
+/// Root: install the verified build, record it and restart the service.
+fn apply_build(backend: &InitBackend, expected_checksum: &str) {
+    if let Ok(original_cwd) = env::var(ENV_ORIGINAL_CWD) {
+        let _ = env::set_current_dir(&original_cwd);
+    }
+    let (pkg_name, _) = pkg_name_and_description()
+        .unwrap_or_else(|| exit_error("Could not read the package name from Cargo.toml."));
+    let service_name = ensure_corky_prefix(&pkg_name);
+    let Some(mut manifest) = load_manifest(&service_name) else {
+        exit_error(&format!("{} is not installed.", service_name));
+    };
+    let target_bin = corky_config().build.elevated_artifact_path(&pkg_name);
+
+    // Same TOCTOU check as install: swap exactly what was built
+    let actual = compute_file_checksum(&target_bin).unwrap_or_default();
+    if expected_checksum.is_empty() || actual != expected_checksum {
+        exit_error("Binary checksum mismatch; not swapping. Rebuild and try again.");
+    }
+
+    section("Swapping binary");
+    let install_bin = manifest.exec_path.clone();
+    if let Err(e) = swap_binary(&target_bin, &install_bin) {
+        exit_error(&format!("install {}: {}", install_bin.display(), e));
+    }
+    println!("{C_GREEN}[INFO]{C_RESET} Installed: {}", install_bin.display());
+    manifest.checksum = actual;
+    if let Err(e) = write_manifest(&manifest) {
+        eprintln!("{C_YELLOW}[WARN]{C_RESET} Could not write install manifest: {}", e);
+    }
+
+    if manifest.schedule.is_some() {
+        println!("{C_GREEN}[INFO]{C_RESET} Scheduled job; the next run uses the new binary.");
+        return;
+    }
+    for name in unit_names(&manifest) {
+        let ok = match backend {
+            InitBackend::Systemd { .. } => run_cmd("systemctl", &["restart", &name]),
+            InitBackend::Supervisor => run_cmd("supervisorctl", &["restart", &name]),
+        };
+        if ok {
+            println!("{C_BGREEN}[OK]{C_RESET} Service {} restarted", name);
+        } else {
+            eprintln!("{C_YELLOW}[WARN]{C_RESET} Could not restart {}", name);
+        }
+    }
+}
+
+/// Copy next to the destination and rename over it, so a running binary
+/// (ETXTBSY) is replaced instead of written into.
+fn swap_binary(from: &Path, to: &Path) -> std::io::Result<()> {
+    let staging = to.with_extension("corky-new");
+    fs::copy(from, &staging)?;
+    fs::set_permissions(&staging, fs::Permissions::from_mode(0o755))?;
+    fs::rename(&staging, to)
+}
+
+/// The units to restart and follow: the service, or each template instance.
+fn unit_names(manifest: &InstallManifest) -> Vec<String> {
+    if manifest.template {
+        manifest
+            .instances
+            .iter()
+            .map(|id| instance_name(&manifest.service_name, id))
+            .collect()
+    } else {
+        vec![manifest.service_name.clone()]
+    }
+}
+
+/// Follow the service's logs in the background, elevated when needed.
+fn follow_logs(backend: &InitBackend, manifest: &InstallManifest) -> Option<Child> {
+    let names = unit_names(manifest);
+    let mut words: Vec<String> = match backend {
+        InitBackend::Systemd { .. } => {
+            let mut words = vec!["journalctl".to_string(), "-f".into(), "-n".into(), "0".into()];
+            for name in &names {
+                words.extend(["-u".to_string(), format!("{}.service", name)]);
+            }
+            words
+        }
+        // supervisorctl can only tail one program
+        InitBackend::Supervisor => {
+            vec!["supervisorctl".into(), "tail".into(), "-f".into(), names.first()?.clone()]
+        }
+    };
+    let mut cmd = if is_root() {
+        let mut cmd = Command::new(&words[0]);
+        cmd.args(&words[1..]);
+        cmd
+    } else {
+        let program = words.remove(0);
+        escalator_or_exit().command(Path::new(&program), &words, &[], None, false)
+    };
+    cmd.stdin(Stdio::null()).spawn().ok()
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Tests
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_swap_binary() {
+        let dir = env::temp_dir().join(format!("corky-dev-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (built, installed) = (dir.join("built"), dir.join("corky-x"));
+        fs::write(&built, "new").unwrap();
+        fs::write(&installed, "old").unwrap();
+        swap_binary(&built, &installed).unwrap();
+        assert_eq!(fs::read_to_string(&installed).unwrap(), "new");
+        assert_eq!(fs::metadata(&installed).unwrap().permissions().mode() & 0o777, 0o755);
+        assert!(!dir.join("corky-x.corky-new").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,461 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::adopt::{has_other_sections, replace_program_section};
+use crate::envfile::{env_file_path, redact_config, write_config_file};
+use crate::init::{
+    elevate_if_needed, exit_error, generate_supervisor_conf, render_supervisor_conf, render_systemd_unit,
+    run_cmd_expect_ok, run_service_action, scoped_command, section, supervisor_conf_for, supervisor_conf_path,
+    systemd_unit_path, InitBackend, RenderHost, ServiceInfo,
+    C_BGREEN, C_GREEN, C_RED, C_RESET, C_YELLOW,
+};
+use crate::instance::template_unit_path;
+use crate::manifest::{load_manifest, manifest_path, InstallManifest};
+use crate::schedule::{cron_path, render_cron_entry_for};
+
+/// Lines of unchanged context around each hunk.
+const CONTEXT_LINES: usize = 3;
+
+// ─────────────────────────────────────────────────────────────────────────────
+// corky diff
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Compare a service's on-disk unit/conf with what its manifest renders to.
+/// With `fix`, restore the generated file. Exits 1 when drift remains.
+pub fn run_config_diff(service_info: &ServiceInfo, fix: bool) {
+    if fix {
+        elevate_if_needed(service_info);
+    }
+
+    let manifest = load_manifest(&service_info.name).unwrap_or_else(|| {
+        exit_error(&format!(
+            "No install manifest at {}. Reinstall with `corky install` to record one.",
+            manifest_path(&service_info.name).display()
+        ))
+    });
+    let (path, expected) = expected_config(&service_info.backend, &manifest);
+    let actual = fs::read_to_string(&path).unwrap_or_default();
+
+    if actual == expected {
+        println!(
+            "{C_BGREEN}[OK]{C_RESET} {} matches the generated config.",
+            path.display()
+        );
+        return;
+    }
+
+    let (shown_actual, shown_expected) = (
+        redact_config(&actual, &service_info.name),
+        redact_config(&expected, &service_info.name),
+    );
+    if shown_actual == shown_expected {
+        println!(
+            "{C_YELLOW}[WARN]{C_RESET} {} has stale env file values.",
+            path.display()
+        );
+    } else {
+        print_unified_diff(
+            &format!("{} (on disk)", path.display()),
+            &format!("{} (generated)", path.display()),
+            &shown_actual,
+            &shown_expected,
+        );
+    }
+
+    if !fix {
+        println!("\nRun `corky diff {} --fix` to restore it.", service_info.name);
+        std::process::exit(1);
+    }
+
+    section("Restoring generated config");
+    write_config_file(&service_info.backend, &path, &expected, &service_info.name).unwrap_or_else(|e| {
+        exit_error(&format!("write {}: {}", path.display(), e));
+    });
+    println!("{C_GREEN}[INFO]{C_RESET} Wrote {}", path.display());
+    match &service_info.backend {
+        InitBackend::Systemd { scope } => {
+            let _ = scoped_command("systemctl", scope).arg("daemon-reload").status();
+            println!(
+                "{C_BGREEN}[OK]{C_RESET} Restored. Restart to apply: corky restart {}",
+                service_info.name
+            );
+        }
+        InitBackend::Supervisor => {
+            // update restarts programs whose config changed
+            run_cmd_expect_ok("supervisorctl", &["reread"]);
+            run_cmd_expect_ok("supervisorctl", &["update"]);
+            println!("{C_BGREEN}[OK]{C_RESET} Restored and reloaded.");
+        }
+    }
+}
+
+/// Path of the init config for `backend` and the contents the manifest renders to.
+pub fn expected_config(backend: &InitBackend, manifest: &InstallManifest) -> (PathBuf, String) {
+    expected_config_for(backend, manifest, RenderHost::Local)
+}
+
+/// `expected_config` as rendered for `host`; only local renders look at
+/// the files already on disk.
+pub fn expected_config_for(
+    backend: &InitBackend,
+    manifest: &InstallManifest,
+    host: RenderHost,
+) -> (PathBuf, String) {
+    match backend {
+        InitBackend::Systemd { .. } if manifest.template => (
+            template_unit_path(&manifest.service_name),
+            render_systemd_unit(manifest),
+        ),
+        InitBackend::Systemd { .. } => (
+            systemd_unit_path(&manifest.service_name),
+            render_systemd_unit(manifest),
+        ),
+        InitBackend::Supervisor => match &manifest.schedule {
+            Some(schedule) => (
+                cron_path(&manifest.service_name),
+                render_cron_entry_for(manifest, schedule, host).unwrap_or_else(|e| exit_error(&e)),
+            ),
+            None if host != RenderHost::Local => (
+                supervisor_conf_path(&manifest.service_name),
+                render_supervisor_conf(manifest, host),
+            ),
+            None => {
+                let path = supervisor_conf_for(&manifest.service_name);
+                let contents = shared_conf_contents(&path, manifest)
+                    .unwrap_or_else(|| generate_supervisor_conf(manifest));
+                (path, contents)
+            }
+        },
+    }
+}
+
+/// For a .conf that also holds other programs (typically an adopted one),
+/// the file with only this program's section regenerated.
+fn shared_conf_contents(path: &Path, manifest: &InstallManifest) -> Option<String> {
+    let name = &manifest.service_name;
+    let current = fs::read_to_string(path).ok()?;
+    if manifest.template || !has_other_sections(&current, name) {
+        return None;
+    }
+    let generated = generate_supervisor_conf(manifest);
+    let block = &generated[generated.find("[program:")?..];
+    replace_program_section(&current, name, block)
+}
+
+/// Whether the file on disk already holds what `manifest` renders to.
+pub fn config_is_current(backend: &InitBackend, manifest: &InstallManifest) -> bool {
+    let (path, expected) = expected_config(backend, manifest);
+    fs::read_to_string(path).is_ok_and(|actual| actual == expected)
+}
+
+/// Before overwriting `path`, show what will change relative to the file on
+/// disk, with `service_name`'s env file values masked.
+pub fn show_config_changes(path: &Path, new_contents: &str, service_name: &str) {
+    let Ok(current) = fs::read_to_string(path) else {
+        return;
+    };
+    if current == new_contents {
+        println!(
+            "{C_GREEN}[INFO]{C_RESET} {} is unchanged.",
+            path.display()
+        );
+        return;
+    }
+    let (current, new_contents) = (
+        redact_config(&current, service_name),
+        redact_config(new_contents, service_name),
+    );
+    if current == new_contents {
+        println!(
+            "{C_GREEN}[INFO]{C_RESET} {} changes only in env file values.",
+            path.display()
+        );
+        return;
+    }
+    eprintln!(
+        "{C_YELLOW}[WARN]{C_RESET} {} differs from the generated config and will be overwritten:",
+        path.display()
+    );
+    print_unified_diff(
+        &format!("{} (on disk)", path.display()),
+        &format!("{} (new)", path.display()),
+        &current,
+        &new_contents,
+    );
+}
+
+fn print_unified_diff(old_label: &str, new_label: &str, old: &str, new: &str) {
+    for line in unified_diff(old_label, new_label, old, new).lines() {
+        if line.starts_with("---") || line.starts_with("+++") {
+            println!("{}", line);
+        } else if line.starts_with('-') {
+            println!("{C_RED}{}{C_RESET}", line);
+        } else if line.starts_with('+') {
+            println!("{C_GREEN}{}{C_RESET}", line);
+        } else {
+            println!("{}", line);
+        }
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// corky restart --if-changed
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Restart a service only if its binary, unit/conf or env file was modified
+/// after its running process started; start it if it isn't running.
+pub fn run_restart_if_changed(service_info: &ServiceInfo) {
+    elevate_if_needed(service_info);
+    let manifest = load_manifest(&service_info.name).unwrap_or_else(|| {
+        exit_error(&format!(
+            "No install manifest for {}; use `corky restart` instead.",
+            service_info.name
+        ))
+    });
+    if manifest.schedule.is_some() {
+        exit_error(&format!(
+            "{} is a scheduled job; each run already picks up changes.",
+            service_info.name
+        ));
+    }
+
+    let Some(started) = main_pid(service_info).and_then(process_start_time) else {
+        println!("{C_GREEN}[INFO]{C_RESET} {} is not running.", service_info.name);
+        run_service_action("start", service_info);
+        return;
+    };
+    let (config_path, _) = expected_config(&service_info.backend, &manifest);
+    let mut inputs = vec![
+        manifest.exec_path.clone(),
+        config_path.clone(),
+        env_file_path(&manifest.service_name),
+    ];
+    if service_info.name != manifest.service_name {
+        inputs.push(env_file_path(&service_info.name));
+    }
+    let changed: Vec<PathBuf> = inputs
+        .into_iter()
+        .filter(|path| {
+            fs::metadata(path)
+                .and_then(|m| m.modified())
+                .is_ok_and(|modified| modified > started)
+        })
+        .collect();
+    if changed.is_empty() {
+        println!(
+            "{C_BGREEN}[OK]{C_RESET} {} is up to date; not restarting.",
+            service_info.name
+        );
+        return;
+    }
+    for path in &changed {
+        println!("{C_GREEN}[INFO]{C_RESET} Changed since start: {}", path.display());
+    }
+
+    if changed.contains(&config_path) {
+        match &service_info.backend {
+            InitBackend::Systemd { scope } => {
+                let _ = scoped_command("systemctl", scope).arg("daemon-reload").status();
+            }
+            InitBackend::Supervisor => {
+                // update restarts programs whose config changed
+                run_cmd_expect_ok("supervisorctl", &["reread"]);
+                run_cmd_expect_ok("supervisorctl", &["update"]);
+                println!("{C_BGREEN}[OK]{C_RESET} Config reloaded.");
+                return;
+            }
+        }
+    }
+    run_service_action("restart", service_info);
+}
+
+/// PID of the service's main process, if it is running.
+fn main_pid(service_info: &ServiceInfo) -> Option<u32> {
+    let output = match &service_info.backend {
+        InitBackend::Systemd { scope } => scoped_command("systemctl", scope)
+            .args(["show", "-p", "MainPID", "--value", &service_info.name])
+            .output(),
+        InitBackend::Supervisor => Command::new("supervisorctl")
+            .args(["pid", &service_info.name])
+            .output(),
+    }
+    .ok()?;
+    String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .parse()
+        .ok()
+        .filter(|pid| *pid > 0)
+}
+
+/// When `pid` started, from its start tick in /proc and the boot time.
+fn process_start_time(pid: u32) -> Option<SystemTime> {
+    let stat = fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    let boot_secs: u64 = fs::read_to_string("/proc/stat")
+        .ok()?
+        .lines()
+        .find_map(|line| line.strip_prefix("btime ")?.trim().parse().ok())?;
+    let ticks_per_sec = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+    if ticks_per_sec <= 0 {
+        return None;
+    }
+    let ticks = start_ticks(&stat)?;
+    Some(
+        UNIX_EPOCH
+            + Duration::from_secs(boot_secs)
+            + Duration::from_secs_f64(ticks as f64 / ticks_per_sec as f64),
+    )
+}
+
+/// Field 22 (starttime) of /proc/<pid>/stat. The command name in field 2 may
+/// contain spaces and parentheses, so count from the last ')'.
+fn start_ticks(stat: &str) -> Option<u64> {
+    let (_, rest) = stat.rsplit_once(')')?;
+    rest.split_whitespace().nth(19)?.parse().ok()
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Unified diff
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Equal,
+    Delete,
+    Insert,
+}
+
+/// Line diff of two small texts in unified format (empty when identical).
+pub fn unified_diff(old_label: &str, new_label: &str, old: &str, new: &str) -> String {
+    let a: Vec<&str> = old.lines().collect();
+    let b: Vec<&str> = new.lines().collect();
+    let ops = diff_ops(&a, &b);
+    if ops.iter().all(|(op, _, _)| *op == Op::Equal) {
+        return String::new();
+    }
+
+    let mut out = format!("--- {}\n+++ {}\n", old_label, new_label);
+    let changed: Vec<usize> = ops
+        .iter()
+        .enumerate()
+        .filter(|(_, (op, _, _))| *op != Op::Equal)
+        .map(|(i, _)| i)
+        .collect();
+
+    // Group changes whose context windows overlap into hunks
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for &i in &changed {
+        let start = i.saturating_sub(CONTEXT_LINES);
+        let end = (i + CONTEXT_LINES + 1).min(ops.len());
+        match hunks.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    for (start, end) in hunks {
+        let slice = &ops[start..end];
+        let old_start = slice[0].1;
+        let new_start = slice[0].2;
+        let old_len = slice.iter().filter(|(op, _, _)| *op != Op::Insert).count();
+        let new_len = slice.iter().filter(|(op, _, _)| *op != Op::Delete).count();
+        out.push_str(&format!(
+            "@@ -{},{} +{},{} @@\n",
+            old_start + 1,
+            old_len,
+            new_start + 1,
+            new_len
+        ));
+        for &(op, ai, bi) in slice {
+            match op {
+                Op::Equal => out.push_str(&format!(" {}\n", a[ai])),
+                Op::Delete => out.push_str(&format!("-{}\n", a[ai])),
+                Op::Insert => out.push_str(&format!("+{}\n", b[bi])),
+            }
+        }
+    }
+    out
+}
+
+/// LCS edit script as (op, index into a, index into b).
+fn diff_ops(a: &[&str], b: &[&str]) -> Vec<(Op, usize, usize)> {
+    let (n, m) = (a.len(), b.len());
+    let mut lcs = vec![vec![0usize; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut ops = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && a[i] == b[j] {
+            ops.push((Op::Equal, i, j));
+            i += 1;
+            j += 1;
+        } else if i < n && (j == m || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            ops.push((Op::Delete, i, j));
+            i += 1;
+        } else {
+            ops.push((Op::Insert, i, j));
+            j += 1;
+        }
+    }
+    ops
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Tests
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_start_ticks() {
+        let stat = "4242 (my (odd) svc) S 1 4242 4242 0 -1 4194560 1 0 0 0 3 1 0 0 20 0 1 0 987654 1 2";
+        assert_eq!(start_ticks(stat), Some(987654));
+        assert_eq!(start_ticks("4242 (svc) S 1"), None);
+    }
+
+    #[test]
+    fn test_unified_diff_identical() {
+        assert_eq!(unified_diff("a", "b", "x\ny\n", "x\ny\n"), "");
+    }
+
+    #[test]
+    fn test_unified_diff_change() {
+        let old = "1\n2\n3\n4\n5\n6\n7\n8\n9\n";
+        let new = "1\n2\n3\n4\nfive\n6\n7\n8\n9\n";
+        let diff = unified_diff("old", "new", old, new);
+        assert_eq!(
+            diff,
+            "--- old\n+++ new\n@@ -2,7 +2,7 @@\n 2\n 3\n 4\n-5\n+five\n 6\n 7\n 8\n"
+        );
+    }
+
+    #[test]
+    fn test_unified_diff_separate_hunks() {
+        let lines: Vec<String> = (1..=20).map(|i| i.to_string()).collect();
+        let old = lines.join("\n");
+        let new = lines
+            .iter()
+            .map(|l| match l.as_str() {
+                "2" => "two",
+                "19" => "nineteen",
+                other => other,
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let diff = unified_diff("old", "new", &old, &new);
+        assert_eq!(diff.matches("@@ -").count(), 2);
+        assert!(diff.contains("-19\n+nineteen\n"));
+    }
+}
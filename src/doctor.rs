@@ -0,0 +1,317 @@
+use std::env;
+use std::ffi::CString;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use crate::escalate::{Escalator, ENV_ESCALATOR};
+use crate::init::{
+    find_in_path, installing_user, is_root, is_supervisor_available, is_systemd_available,
+    lookup_primary_group, orphaned_configs, section, supervisor_socket, InitBackend,
+    BIN_PATH_SYSTEM, C_BGREEN, C_RED, C_RESET, C_YELLOW, ENV_INIT_BACKEND,
+};
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Findings
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Severity {
+    Ok,
+    Warn,
+    Fail,
+}
+
+/// Result of a single diagnostic check.
+struct Finding {
+    severity: Severity,
+    check: &'static str,
+    detail: String,
+    remediation: Option<String>,
+}
+
+impl Finding {
+    fn ok(check: &'static str, detail: impl Into<String>) -> Self {
+        Finding {
+            severity: Severity::Ok,
+            check,
+            detail: detail.into(),
+            remediation: None,
+        }
+    }
+
+    fn warn(check: &'static str, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Finding {
+            severity: Severity::Warn,
+            check,
+            detail: detail.into(),
+            remediation: Some(fix.into()),
+        }
+    }
+
+    fn fail(check: &'static str, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Finding {
+            severity: Severity::Fail,
+            check,
+            detail: detail.into(),
+            remediation: Some(fix.into()),
+        }
+    }
+
+    fn print(&self) {
+        let tag = match self.severity {
+            Severity::Ok => format!("{C_BGREEN}[OK]{C_RESET}  "),
+            Severity::Warn => format!("{C_YELLOW}[WARN]{C_RESET}"),
+            Severity::Fail => format!("{C_RED}[FAIL]{C_RESET}"),
+        };
+        println!("{} {:<18} {}", tag, self.check, self.detail);
+        if let Some(fix) = &self.remediation {
+            println!("       {:<18} fix: {}", "", fix);
+        }
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// doctor
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Run every environment check, print findings and exit non-zero on failures.
+pub fn run_doctor() -> ! {
+    section("Corky doctor");
+
+    let mut findings = Vec::new();
+    let backend = check_init_system(&mut findings);
+    if backend == Some(InitBackend::Supervisor) || supervisor_socket().is_some() {
+        findings.push(check_supervisor_socket());
+    }
+    findings.push(check_escalation());
+    findings.push(check_user_group());
+    findings.push(check_bin_dir_on_path());
+    findings.push(check_cargo());
+    findings.push(check_selinux());
+    if let Some(backend) = &backend {
+        findings.push(check_orphans(backend));
+    }
+
+    for f in &findings {
+        f.print();
+    }
+
+    let failures = findings.iter().filter(|f| f.severity == Severity::Fail).count();
+    let warnings = findings.iter().filter(|f| f.severity == Severity::Warn).count();
+    println!();
+    if failures == 0 && warnings == 0 {
+        println!("{C_BGREEN}[SUCCESS]{C_RESET} No problems found.");
+    } else {
+        println!(
+            "{} failure(s), {} warning(s). See the fixes above.",
+            failures, warnings
+        );
+    }
+    std::process::exit(if failures > 0 { 1 } else { 0 });
+}
+
+fn check_init_system(findings: &mut Vec<Finding>) -> Option<InitBackend> {
+    let systemd = is_systemd_available();
+    let supervisor = is_supervisor_available();
+    let scope = if is_root() { "system" } else { "user" }.to_string();
+
+    let overridden = match env::var(ENV_INIT_BACKEND).map(|v| v.to_lowercase()) {
+        Ok(val) if val == "systemd" => Some(InitBackend::Systemd { scope: scope.clone() }),
+        Ok(val) if val == "supervisor" || val == "supervisord" => Some(InitBackend::Supervisor),
+        Ok(val) => {
+            findings.push(Finding::warn(
+                "init override",
+                format!("{}={} is not recognised", ENV_INIT_BACKEND, val),
+                format!("set {}=systemd|supervisor or unset it", ENV_INIT_BACKEND),
+            ));
+            None
+        }
+        Err(_) => None,
+    };
+    if let Some(backend) = overridden {
+        findings.push(Finding::ok(
+            "init override",
+            format!("{}={} (auto-detection bypassed)", ENV_INIT_BACKEND, backend),
+        ));
+        return Some(backend);
+    }
+
+    match (systemd, supervisor) {
+        (true, true) => {
+            findings.push(Finding::ok(
+                "init system",
+                "systemd and supervisor both available; systemd takes priority",
+            ));
+            Some(InitBackend::Systemd { scope })
+        }
+        (true, false) => {
+            findings.push(Finding::ok("init system", "systemd"));
+            Some(InitBackend::Systemd { scope })
+        }
+        (false, true) => {
+            findings.push(Finding::ok("init system", "supervisor"));
+            Some(InitBackend::Supervisor)
+        }
+        (false, false) => {
+            findings.push(Finding::fail(
+                "init system",
+                "neither systemd (/run/systemd/system) nor supervisord found",
+                format!(
+                    "install supervisor (apt install supervisor) or set {}=systemd|supervisor",
+                    ENV_INIT_BACKEND
+                ),
+            ));
+            None
+        }
+    }
+}
+
+fn check_supervisor_socket() -> Finding {
+    let Some(socket) = supervisor_socket() else {
+        return Finding::warn(
+            "supervisor socket",
+            "supervisorctl works but no socket at /var/run or /run",
+            "check [unix_http_server] file= in supervisord.conf",
+        );
+    };
+    if is_root() || can_access(&socket, libc::R_OK | libc::W_OK) {
+        Finding::ok("supervisor socket", format!("{} is accessible", socket.display()))
+    } else {
+        Finding::warn(
+            "supervisor socket",
+            format!("{} is not readable/writable by this user", socket.display()),
+            "corky will elevate to root; or set chown=/chmod= under [unix_http_server]",
+        )
+    }
+}
+
+fn check_escalation() -> Finding {
+    if is_root() {
+        return Finding::ok("privileges", "running as root");
+    }
+    match Escalator::current() {
+        Ok(escalator) => Finding::ok("privileges", format!("elevating with {}", escalator)),
+        Err(e) => Finding::fail(
+            "privileges",
+            format!("{}; install and elevation will fail", e),
+            format!(
+                "install sudo, doas, run0 or pkexec, set {}, or run corky as root",
+                ENV_ESCALATOR
+            ),
+        ),
+    }
+}
+
+fn check_user_group() -> Finding {
+    let user = installing_user();
+    match lookup_primary_group(&user) {
+        Some(group) => Finding::ok("service user", format!("{} (group {})", user, group)),
+        None => Finding::warn(
+            "service user",
+            format!("no primary group for '{}'; units would use Group={}", user, user),
+            format!("create it: groupadd {0} && usermod -g {0} {0}", user),
+        ),
+    }
+}
+
+fn check_bin_dir_on_path() -> Finding {
+    let path = env::var("PATH").unwrap_or_default();
+    if path_contains_dir(&path, BIN_PATH_SYSTEM) {
+        Finding::ok("PATH", format!("{} is on PATH", BIN_PATH_SYSTEM))
+    } else {
+        Finding::warn(
+            "PATH",
+            format!(
+                "{} is not on PATH; installed binaries won't be found by name",
+                BIN_PATH_SYSTEM
+            ),
+            format!(
+                "add `export PATH=\"{}:$PATH\"` to your shell profile",
+                BIN_PATH_SYSTEM
+            ),
+        )
+    }
+}
+
+fn check_cargo() -> Finding {
+    match find_in_path("cargo") {
+        Some(path) => Finding::ok("cargo", format!("{} found", path.display())),
+        None => Finding::warn(
+            "cargo",
+            "cargo not found on PATH; `corky install` cannot build",
+            "install Rust via https://rustup.rs",
+        ),
+    }
+}
+
+fn check_selinux() -> Finding {
+    let enforcing = fs::read_to_string("/sys/fs/selinux/enforce")
+        .map(|s| s.trim() == "1")
+        .unwrap_or(false);
+    if !enforcing {
+        return Finding::ok("SELinux", "not enforcing");
+    }
+    let denials = Command::new("ausearch")
+        .args(["-m", "AVC", "-ts", "recent"])
+        .output()
+        .map(|o| {
+            String::from_utf8_lossy(&o.stdout)
+                .lines()
+                .filter(|l| l.contains("corky"))
+                .count()
+        })
+        .unwrap_or(0);
+    if denials == 0 {
+        Finding::ok("SELinux", "enforcing, no recent corky denials")
+    } else {
+        Finding::warn(
+            "SELinux",
+            format!("{} recent AVC denial(s) mentioning corky", denials),
+            format!(
+                "restorecon -v {}/corky-* /etc/systemd/system/corky-*; see audit2allow -a",
+                BIN_PATH_SYSTEM
+            ),
+        )
+    }
+}
+
+fn check_orphans(backend: &InitBackend) -> Finding {
+    let orphans = orphaned_configs(backend);
+    if orphans.is_empty() {
+        return Finding::ok("orphaned configs", "none");
+    }
+    let list: Vec<String> = orphans.iter().map(|p| p.display().to_string()).collect();
+    Finding::warn(
+        "orphaned configs",
+        format!("{} config(s) for the unused init system", orphans.len()),
+        format!("rm {}", list.join(" ")),
+    )
+}
+
+fn can_access(path: &Path, mode: libc::c_int) -> bool {
+    let Ok(c_path) = CString::new(path.as_os_str().as_encoded_bytes()) else {
+        return false;
+    };
+    unsafe { libc::access(c_path.as_ptr(), mode) == 0 }
+}
+
+fn path_contains_dir(path_var: &str, dir: &str) -> bool {
+    env::split_paths(path_var).any(|p| p == Path::new(dir))
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Tests
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_path_contains_dir() {
+        assert!(path_contains_dir("/usr/bin:/usr/local/bin", "/usr/local/bin"));
+        assert!(!path_contains_dir("/usr/bin:/bin", "/usr/local/bin"));
+        assert!(!path_contains_dir("", "/usr/local/bin"));
+    }
+}
@@ -0,0 +1,373 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io::{self, Write};
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+
+use crate::diff::{expected_config, show_config_changes};
+use crate::init::{
+    ensure_root, exit_error, run_cmd, run_cmd_expect_ok, section, InitBackend, ServiceInfo,
+    CORKY_ETC_DIR, C_BGREEN, C_BOLD, C_GREEN, C_RESET, C_YELLOW,
+};
+use crate::instance::instance_name;
+use crate::manifest::load_manifest;
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Env file storage
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Root-only `KEY=VALUE` file holding a service's environment (and secrets).
+pub fn env_file_path(service_name: &str) -> PathBuf {
+    PathBuf::from(CORKY_ETC_DIR).join(format!("{}.env", service_name))
+}
+
+/// Variables from the service's env file; empty if it doesn't exist or can't be read.
+pub fn read_env_file(service_name: &str) -> BTreeMap<String, String> {
+    fs::read_to_string(env_file_path(service_name))
+        .map(|text| parse_env(&text))
+        .unwrap_or_default()
+}
+
+pub fn write_env_file(service_name: &str, vars: &BTreeMap<String, String>) -> io::Result<PathBuf> {
+    let path = env_file_path(service_name);
+    fs::create_dir_all(CORKY_ETC_DIR)?;
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(&path)?;
+    // mode() only applies on create; tighten files that already existed
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o600))?;
+    file.write_all(format_env(vars).as_bytes())?;
+    Ok(path)
+}
+
+/// Parse systemd EnvironmentFile= syntax (the subset corky writes).
+fn parse_env(text: &str) -> BTreeMap<String, String> {
+    let mut vars = BTreeMap::new();
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let value = value.trim();
+        let value = match value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+            Some(inner) => unescape_double_quoted(inner),
+            None => value.to_string(),
+        };
+        vars.insert(key.trim().to_string(), value);
+    }
+    vars
+}
+
+fn format_env(vars: &BTreeMap<String, String>) -> String {
+    let mut out = String::from("# Managed by corky CLI -- use `corky env` to edit\n");
+    for (key, value) in vars {
+        let plain = !value.is_empty()
+            && value
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "_-./:@+,".contains(c));
+        if plain {
+            out.push_str(&format!("{}={}\n", key, value));
+        } else {
+            out.push_str(&format!(
+                "{}=\"{}\"\n",
+                key,
+                value.replace('\\', "\\\\").replace('"', "\\\"")
+            ));
+        }
+    }
+    out
+}
+
+fn unescape_double_quoted(inner: &str) -> String {
+    let mut out = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            if let Some(next) = chars.next() {
+                out.push(next);
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+fn is_valid_env_key(key: &str) -> bool {
+    let mut chars = key.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// corky env set / unset / list
+// ─────────────────────────────────────────────────────────────────────────────
+
+pub fn run_env_set(service_info: &ServiceInfo, pairs: &[String], restart: bool) {
+    ensure_root(&service_info.backend);
+
+    let mut vars = read_env_file(&service_info.name);
+    vars.extend(parse_env_pairs(pairs));
+    save_and_apply(service_info, &vars, restart);
+}
+
+/// Validate `KEY=VALUE` command-line arguments, exiting on the first bad one.
+pub fn parse_env_pairs(pairs: &[String]) -> BTreeMap<String, String> {
+    let mut vars = BTreeMap::new();
+    for pair in pairs {
+        let Some((key, value)) = pair.split_once('=') else {
+            exit_error(&format!("Expected KEY=VALUE, got '{}'", pair));
+        };
+        if !is_valid_env_key(key) {
+            exit_error(&format!("Invalid variable name '{}'", key));
+        }
+        if value.contains('\n') {
+            exit_error(&format!("Value for {} must not contain newlines", key));
+        }
+        vars.insert(key.to_string(), value.to_string());
+    }
+    vars
+}
+
+pub fn run_env_unset(service_info: &ServiceInfo, keys: &[String], restart: bool) {
+    ensure_root(&service_info.backend);
+
+    let mut vars = read_env_file(&service_info.name);
+    for key in keys {
+        if vars.remove(key).is_none() {
+            eprintln!("{C_YELLOW}[WARN]{C_RESET} {} is not set", key);
+        }
+    }
+    save_and_apply(service_info, &vars, restart);
+}
+
+pub fn run_env_list(service_info: &ServiceInfo, reveal: bool) {
+    ensure_root(&service_info.backend);
+
+    let vars = read_env_file(&service_info.name);
+    println!(
+        "{}Environment for {}{} ({})",
+        C_BOLD,
+        service_info.name,
+        C_RESET,
+        env_file_path(&service_info.name).display()
+    );
+    if vars.is_empty() {
+        println!("  (none set)");
+    }
+    for (key, value) in &vars {
+        if reveal {
+            println!("  {}={}", key, value);
+        } else {
+            println!("  {}=****", key);
+        }
+    }
+}
+
+fn save_and_apply(service_info: &ServiceInfo, vars: &BTreeMap<String, String>, restart: bool) {
+    match write_env_file(&service_info.name, vars) {
+        Ok(path) => println!("{C_GREEN}[INFO]{C_RESET} Wrote {} (0600)", path.display()),
+        Err(e) => exit_error(&format!(
+            "write {}: {}",
+            env_file_path(&service_info.name).display(),
+            e
+        )),
+    }
+
+    let config_changed = refresh_config(service_info);
+
+    let restart = restart
+        || inquire::Confirm::new(&format!("Restart {} now to apply?", service_info.name))
+            .with_default(false)
+            .prompt()
+            .unwrap_or(false);
+
+    match &service_info.backend {
+        InitBackend::Systemd { .. } => {
+            if config_changed {
+                run_cmd_expect_ok("systemctl", &["daemon-reload"]);
+            }
+            if restart {
+                run_cmd_expect_ok("systemctl", &["restart", &service_info.name]);
+                println!("{C_BGREEN}[OK]{C_RESET} Service {} restarted", service_info.name);
+            } else {
+                println!("Apply later with: corky restart {}", service_info.name);
+            }
+        }
+        InitBackend::Supervisor => {
+            run_cmd_expect_ok("supervisorctl", &["reread"]);
+            if restart {
+                // update reloads the changed program config and restarts it
+                run_cmd_expect_ok("supervisorctl", &["update", &service_info.name]);
+                let _ = run_cmd("supervisorctl", &["restart", &service_info.name]);
+                println!("{C_BGREEN}[OK]{C_RESET} Service {} restarted", service_info.name);
+            } else {
+                println!("Apply later with: supervisorctl update {}", service_info.name);
+            }
+        }
+    }
+}
+
+/// Make sure the init config picks the env file up: systemd units must
+/// reference it, supervisor confs must be regenerated with the new values.
+/// Returns whether the config file was rewritten.
+fn refresh_config(service_info: &ServiceInfo) -> bool {
+    let Some(manifest) = load_manifest(&service_info.name) else {
+        eprintln!(
+            "{C_YELLOW}[WARN]{C_RESET} No install manifest for {}; its config was not regenerated.",
+            service_info.name
+        );
+        eprintln!(
+            "Reinstall with `corky install` so it loads {}.",
+            env_file_path(&service_info.name).display()
+        );
+        return false;
+    };
+    let (path, contents) = expected_config(&service_info.backend, &manifest);
+    let current = fs::read_to_string(&path).unwrap_or_default();
+    if current == contents {
+        return false;
+    }
+
+    section("Updating service config");
+    if let InitBackend::Systemd { .. } = service_info.backend {
+        show_config_changes(&path, &contents, &service_info.name);
+    }
+    if let Err(e) = write_config_file(&service_info.backend, &path, &contents, &service_info.name) {
+        exit_error(&format!("write {}: {}", path.display(), e));
+    }
+    println!("{C_GREEN}[INFO]{C_RESET} Wrote {}", path.display());
+    true
+}
+
+/// Env file keys whose values end up inline in the service's supervisor
+/// conf (a template's conf also inlines every instance's env file).
+fn secret_keys(service_name: &str) -> BTreeSet<String> {
+    let mut keys: BTreeSet<String> = read_env_file(service_name).into_keys().collect();
+    if let Some(manifest) = load_manifest(service_name) {
+        for id in &manifest.instances {
+            keys.extend(read_env_file(&instance_name(&manifest.service_name, id)).into_keys());
+        }
+    }
+    keys
+}
+
+/// Write an init config. Supervisor confs carry env values inline, so they
+/// are made root-only before any content lands once secrets exist.
+pub fn write_config_file(
+    backend: &InitBackend,
+    path: &Path,
+    contents: &str,
+    service_name: &str,
+) -> io::Result<()> {
+    let mode = if *backend == InitBackend::Supervisor && !secret_keys(service_name).is_empty() {
+        0o600
+    } else {
+        0o644
+    };
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(mode)
+        .open(path)?;
+    file.set_permissions(fs::Permissions::from_mode(mode))?;
+    file.write_all(contents.as_bytes())
+}
+
+/// `text` with env file values masked, for printing a supervisor conf.
+pub fn redact_config(text: &str, service_name: &str) -> String {
+    redact_env_values(text, &secret_keys(service_name))
+}
+
+fn redact_env_values(text: &str, keys: &BTreeSet<String>) -> String {
+    let mut out: String = text
+        .lines()
+        .map(|line| match line.strip_prefix("environment=") {
+            Some(items) if !keys.is_empty() => {
+                let items: Vec<String> = split_unquoted_commas(items)
+                    .into_iter()
+                    .map(|item| match item.split_once('=') {
+                        Some((key, _)) if keys.contains(key) => format!("{}=****", key),
+                        _ => item.to_string(),
+                    })
+                    .collect();
+                format!("environment={}", items.join(","))
+            }
+            _ => line.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    if text.ends_with('\n') {
+        out.push('\n');
+    }
+    out
+}
+
+/// Split supervisor's `environment=` on commas outside double quotes.
+fn split_unquoted_commas(items: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let (mut start, mut quoted, mut escaped) = (0, false, false);
+    for (i, c) in items.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => quoted = !quoted,
+            ',' if !quoted => {
+                parts.push(&items[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&items[start..]);
+    parts
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Tests
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_env_roundtrip() {
+        let vars = BTreeMap::from([
+            ("PLAIN".to_string(), "abc-123".to_string()),
+            ("SPACED".to_string(), "a b \"c\" \\d".to_string()),
+            ("EMPTY".to_string(), String::new()),
+        ]);
+        let text = format_env(&vars);
+        assert!(text.contains("PLAIN=abc-123\n"));
+        assert!(text.contains("SPACED=\"a b \\\"c\\\" \\\\d\"\n"));
+        assert_eq!(parse_env(&text), vars);
+    }
+
+    #[test]
+    fn test_redact_env_values() {
+        let keys = BTreeSet::from(["API_KEY".to_string(), "DB_URL".to_string()]);
+        let conf = "[program:api]\nenvironment=API_KEY=\"s3cr,et\",DB_URL=\"pg://u:\\\"p\\\"@h\",LOG=\"info\"\nautostart=true\n";
+        assert_eq!(
+            redact_env_values(conf, &keys),
+            "[program:api]\nenvironment=API_KEY=****,DB_URL=****,LOG=\"info\"\nautostart=true\n"
+        );
+        assert_eq!(redact_env_values(conf, &BTreeSet::new()), conf);
+    }
+
+    #[test]
+    fn test_is_valid_env_key() {
+        assert!(is_valid_env_key("DATABASE_URL"));
+        assert!(is_valid_env_key("_x1"));
+        assert!(!is_valid_env_key("1X"));
+        assert!(!is_valid_env_key("A-B"));
+        assert!(!is_valid_env_key(""));
+    }
+}
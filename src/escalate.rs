@@ -0,0 +1,385 @@
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::OnceLock;
+
+use crate::grant::Principal;
+use crate::init::{
+    exit_error, find_in_path, is_root, run_cmd_quiet, InitBackend, ServiceInfo, ENV_ELEVATED_FLAG,
+    ENV_ORIGINAL_CWD,
+};
+
+/// Names the escalator to use instead of the first one found on PATH.
+pub const ENV_ESCALATOR: &str = "CORKY_ESCALATOR";
+/// The user who ran corky, for escalators that don't set SUDO_USER.
+pub const ENV_ORIGINAL_USER: &str = "CORKY_ORIGINAL_USER";
+
+/// polkit action systemd checks for start/stop/restart of a unit.
+const POLKIT_MANAGE_UNITS: &str = "org.freedesktop.systemd1.manage-units";
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Escalators
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// A program that re-runs corky as root.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Escalator {
+    Sudo,
+    Doas,
+    Run0,
+    Pkexec,
+}
+
+/// Auto-detection order.
+const ESCALATORS: [Escalator; 4] = [
+    Escalator::Sudo,
+    Escalator::Doas,
+    Escalator::Run0,
+    Escalator::Pkexec,
+];
+
+impl std::str::FromStr for Escalator {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ESCALATORS
+            .into_iter()
+            .find(|e| e.program() == s.trim())
+            .ok_or_else(|| format!("unknown escalator '{}' (use sudo, doas, run0 or pkexec)", s))
+    }
+}
+
+impl std::fmt::Display for Escalator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.program())
+    }
+}
+
+impl Escalator {
+    pub fn program(self) -> &'static str {
+        match self {
+            Escalator::Sudo => "sudo",
+            Escalator::Doas => "doas",
+            Escalator::Run0 => "run0",
+            Escalator::Pkexec => "pkexec",
+        }
+    }
+
+    /// $CORKY_ESCALATOR if set, else the first escalator `available` reports.
+    pub fn detect(
+        override_name: Option<&str>,
+        available: impl Fn(&str) -> bool,
+    ) -> Result<Escalator, String> {
+        if let Some(name) = override_name.filter(|n| !n.is_empty()) {
+            let escalator: Escalator = name.parse().map_err(|e| format!("{}: {}", ENV_ESCALATOR, e))?;
+            if !available(escalator.program()) {
+                return Err(format!("{}={} but {} is not on PATH", ENV_ESCALATOR, name, name));
+            }
+            return Ok(escalator);
+        }
+        ESCALATORS
+            .into_iter()
+            .find(|e| available(e.program()))
+            .ok_or_else(|| "no sudo, doas, run0 or pkexec on PATH".to_string())
+    }
+
+    /// The escalator for this process, detected once.
+    pub fn current() -> Result<Escalator, String> {
+        static CURRENT: OnceLock<Result<Escalator, String>> = OnceLock::new();
+        CURRENT
+            .get_or_init(|| {
+                let name = env::var(ENV_ESCALATOR).ok();
+                Escalator::detect(name.as_deref(), |p| find_in_path(p).is_some())
+            })
+            .clone()
+    }
+
+    /// Arguments that run `program args` as root with `vars` set and `cwd`
+    /// as the working directory. sudo takes `KEY=VALUE` words itself; doas
+    /// and pkexec reset the environment, so the command goes through env(1);
+    /// run0 has --setenv= and --chdir=. pkexec always prompts through the
+    /// polkit agent, so `interactive` has no effect on it.
+    pub fn argv(
+        self,
+        program: &Path,
+        args: &[String],
+        vars: &[(&str, &str)],
+        cwd: Option<&Path>,
+        interactive: bool,
+    ) -> Vec<OsString> {
+        let mut argv: Vec<OsString> = vec![self.program().into()];
+        let assignments = vars.iter().map(|(k, v)| OsString::from(format!("{}={}", k, v)));
+        match self {
+            Escalator::Sudo => {
+                if !interactive {
+                    argv.push("-n".into());
+                }
+                argv.extend(assignments);
+            }
+            Escalator::Doas => {
+                if !interactive {
+                    argv.push("-n".into());
+                }
+                argv.push("env".into());
+                argv.extend(assignments);
+            }
+            Escalator::Run0 => {
+                if !interactive {
+                    argv.push("--no-ask-password".into());
+                }
+                if let Some(dir) = cwd {
+                    let mut chdir = OsString::from("--chdir=");
+                    chdir.push(dir);
+                    argv.push(chdir);
+                }
+                argv.extend(vars.iter().map(|(k, v)| OsString::from(format!("--setenv={}={}", k, v))));
+            }
+            Escalator::Pkexec => {
+                argv.push("env".into());
+                argv.extend(assignments);
+            }
+        }
+        argv.push(program.into());
+        argv.extend(args.iter().map(OsString::from));
+        argv
+    }
+
+    /// `argv` as a Command with inherited stdio.
+    pub fn command(
+        self,
+        program: &Path,
+        args: &[String],
+        vars: &[(&str, &str)],
+        cwd: Option<&Path>,
+        interactive: bool,
+    ) -> Command {
+        let argv = self.argv(program, args, vars, cwd, interactive);
+        let mut cmd = Command::new(&argv[0]);
+        cmd.args(&argv[1..])
+            .stdin(Stdio::inherit())
+            .stdout(Stdio::inherit())
+            .stderr(Stdio::inherit());
+        if let Some(dir) = cwd {
+            cmd.current_dir(dir);
+        }
+        cmd
+    }
+
+    /// Ask for the password up front where the escalator caches it (sudo);
+    /// the others authenticate on each invocation.
+    pub fn refresh_credentials(self) {
+        if self == Escalator::Sudo && !is_root() {
+            let _ = Command::new(self.program())
+                .arg("-v")
+                .stdin(Stdio::inherit())
+                .stdout(Stdio::inherit())
+                .stderr(Stdio::inherit())
+                .status();
+        }
+    }
+}
+
+/// The current escalator, or exit explaining how to pick one.
+pub fn escalator_or_exit() -> Escalator {
+    Escalator::current().unwrap_or_else(|e| {
+        exit_error(&format!(
+            "Cannot elevate privileges: {}. Run corky as root or set {}.",
+            e, ENV_ESCALATOR
+        ))
+    })
+}
+
+/// Ask for credentials now rather than halfway through a command.
+pub fn refresh_credentials() {
+    if !is_root()
+        && let Ok(escalator) = Escalator::current()
+    {
+        escalator.refresh_credentials();
+    }
+}
+
+/// Variables every elevated run gets: the loop guard, where corky was run
+/// from and by whom.
+pub fn elevation_env() -> Vec<(&'static str, String)> {
+    let mut vars = vec![(ENV_ELEVATED_FLAG, "1".to_string())];
+    if let Ok(cwd) = env::current_dir() {
+        vars.push((ENV_ORIGINAL_CWD, cwd.to_string_lossy().into_owned()));
+    }
+    if let Some(user) = current_user_name() {
+        vars.push((ENV_ORIGINAL_USER, user));
+    }
+    vars
+}
+
+/// In an elevated run, go back to the directory corky was started from;
+/// pkexec starts commands in root's home.
+pub fn restore_original_cwd() {
+    if env::var_os(ENV_ELEVATED_FLAG).is_some()
+        && let Some(cwd) = env::var_os(ENV_ORIGINAL_CWD)
+    {
+        let _ = env::set_current_dir(cwd);
+    }
+}
+
+fn current_user_name() -> Option<String> {
+    let output = Command::new("id").arg("-un").output().ok()?;
+    let name = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (output.status.success() && !name.is_empty()).then_some(name)
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Narrow grants (polkit, sudoers)
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// How a non-root user can run `program args` for a service without corky
+/// elevating itself: directly when polkit authorizes the unit action, or
+/// through `sudo -n` when a `corky grant` sudoers rule allows exactly that
+/// command. The returned command still needs `args`.
+pub fn granted_command(service_info: &ServiceInfo, program: &str, args: &[&str]) -> Option<Command> {
+    if is_root() {
+        return None;
+    }
+    match &service_info.backend {
+        InitBackend::Systemd { scope } if scope != "system" => return None,
+        // The socket is accessible; nothing to grant
+        InitBackend::Supervisor if run_cmd_quiet("supervisorctl", &["pid"]) => return None,
+        _ => {}
+    }
+    if program == "systemctl"
+        && let [verb, _] = args
+        && polkit_permits(service_info, verb)
+    {
+        return Some(Command::new(program));
+    }
+    if find_in_path("sudo").is_none()
+        || !may_have_sudoers_grant()
+        || !run_cmd_quiet("sudo", &[&["-n", "-l", program], args].concat())
+    {
+        return None;
+    }
+    let mut cmd = Command::new("sudo");
+    cmd.args(["-n", program]);
+    Some(cmd)
+}
+
+/// Whether `corky grant` left a sudoers drop-in for this user or one of their
+/// groups; sudo logs every `-l` probe, so corky only asks when one exists. A
+/// sudoers.d that isn't searchable (0750 on some distributions) can't be
+/// checked, so sudo is asked then.
+fn may_have_sudoers_grant() -> bool {
+    let Some(user) = current_user_name() else {
+        return false;
+    };
+    let groups = Command::new("id")
+        .arg("-Gn")
+        .output()
+        .map(|o| String::from_utf8_lossy(&o.stdout).into_owned())
+        .unwrap_or_default();
+    grant_drop_in_paths(&user, &groups).iter().any(|path| match fs::symlink_metadata(path) {
+        Ok(_) => true,
+        Err(e) => e.kind() == io::ErrorKind::PermissionDenied,
+    })
+}
+
+/// The drop-ins `corky grant` would write for `user` and `groups` (`id -Gn`).
+fn grant_drop_in_paths(user: &str, groups: &str) -> Vec<PathBuf> {
+    let mut paths = vec![Principal::User(user.to_string()).sudoers_path()];
+    for group in groups.split_whitespace() {
+        paths.push(Principal::Group(group.to_string()).sudoers_path());
+    }
+    paths
+}
+
+/// Whether polkit lets this (non-root) process `verb` the service's system
+/// unit, in which case systemctl can do it without an escalator.
+pub fn polkit_permits(service_info: &ServiceInfo, verb: &str) -> bool {
+    let InitBackend::Systemd { scope } = &service_info.backend else {
+        return false;
+    };
+    if is_root()
+        || scope != "system"
+        || !matches!(verb, "start" | "stop" | "restart" | "reload")
+        || find_in_path("pkcheck").is_none()
+    {
+        return false;
+    }
+    Command::new("pkcheck")
+        .args(["--action-id", POLKIT_MANAGE_UNITS, "--process"])
+        .arg(std::process::id().to_string())
+        .args(["--detail", "unit", &format!("{}.service", service_info.name)])
+        .args(["--detail", "verb", verb])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|s| s.success())
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Tests
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(argv: Vec<OsString>) -> Vec<String> {
+        argv.into_iter().map(|a| a.to_string_lossy().into_owned()).collect()
+    }
+
+    #[test]
+    fn test_detect() {
+        let only = |names: &'static [&'static str]| move |p: &str| names.contains(&p);
+        assert_eq!(Escalator::detect(None, only(&["doas", "pkexec"])), Ok(Escalator::Doas));
+        assert_eq!(Escalator::detect(Some("run0"), only(&["sudo", "run0"])), Ok(Escalator::Run0));
+        assert!(Escalator::detect(Some("run0"), only(&["sudo"])).is_err());
+        assert!(Escalator::detect(Some("su"), only(&["su"])).is_err());
+        assert!(Escalator::detect(None, only(&[])).is_err());
+    }
+
+    #[test]
+    fn test_argv() {
+        let exe = Path::new("/usr/local/bin/corky");
+        let args = ["install".to_string()];
+        let vars = [("CORKY_ELEVATED", "1"), ("CORKY_ORIGINAL_CWD", "/src/api")];
+        let cwd = Some(Path::new("/src/api"));
+        assert_eq!(
+            words(Escalator::Sudo.argv(exe, &args, &vars, cwd, true)),
+            ["sudo", "CORKY_ELEVATED=1", "CORKY_ORIGINAL_CWD=/src/api", "/usr/local/bin/corky", "install"]
+        );
+        assert_eq!(
+            words(Escalator::Doas.argv(exe, &args, &vars, cwd, false)),
+            ["doas", "-n", "env", "CORKY_ELEVATED=1", "CORKY_ORIGINAL_CWD=/src/api", "/usr/local/bin/corky", "install"]
+        );
+        assert_eq!(
+            words(Escalator::Run0.argv(exe, &args, &vars, cwd, true)),
+            [
+                "run0",
+                "--chdir=/src/api",
+                "--setenv=CORKY_ELEVATED=1",
+                "--setenv=CORKY_ORIGINAL_CWD=/src/api",
+                "/usr/local/bin/corky",
+                "install"
+            ]
+        );
+        assert_eq!(
+            words(Escalator::Pkexec.argv(exe, &args, &vars[..1], None, false)),
+            ["pkexec", "env", "CORKY_ELEVATED=1", "/usr/local/bin/corky", "install"]
+        );
+    }
+
+    #[test]
+    fn test_grant_drop_in_paths() {
+        assert_eq!(
+            grant_drop_in_paths("alice", "alice oncall\n"),
+            [
+                Path::new("/etc/sudoers.d/corky-user-alice"),
+                Path::new("/etc/sudoers.d/corky-group-alice"),
+                Path::new("/etc/sudoers.d/corky-group-oncall")
+            ]
+        );
+    }
+}
@@ -0,0 +1,514 @@
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+use crate::init::{
+    ensure_corky_prefix, ensure_root, exit_error, find_in_path, list_corky_services, run_cmd,
+    section, InitBackend, C_BGREEN, C_CYAN, C_GREEN, C_RESET, C_YELLOW,
+};
+use crate::users::is_valid_user_name;
+
+pub const SUDOERS_DIR: &str = "/etc/sudoers.d";
+pub const POLKIT_RULES_DIR: &str = "/etc/polkit-1/rules.d";
+
+/// First-line marker in every file `corky grant` writes; `--list` reads it back.
+const GRANT_MARKER: &str = "corky grant:";
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Grants
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Something `corky grant` can allow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GrantAction {
+    Start,
+    Stop,
+    Restart,
+    Logs,
+}
+
+impl GrantAction {
+    pub fn name(self) -> &'static str {
+        match self {
+            GrantAction::Start => "start",
+            GrantAction::Stop => "stop",
+            GrantAction::Restart => "restart",
+            GrantAction::Logs => "logs",
+        }
+    }
+}
+
+/// Who a grant is for: a user, or a group written sudoers-style as `%group`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Principal {
+    User(String),
+    Group(String),
+}
+
+impl std::str::FromStr for Principal {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (principal, name): (fn(String) -> Principal, &str) = match s.strip_prefix('%') {
+            Some(group) => (Principal::Group, group),
+            None => (Principal::User, s),
+        };
+        if !is_valid_user_name(name) {
+            return Err(format!("invalid user or group name '{}'", s));
+        }
+        Ok(principal(name.to_string()))
+    }
+}
+
+impl std::fmt::Display for Principal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Principal::User(name) => write!(f, "user {}", name),
+            Principal::Group(name) => write!(f, "group {}", name),
+        }
+    }
+}
+
+impl Principal {
+    /// "corky-user-alice" / "corky-group-oncall"; sudoers.d skips names with dots.
+    fn file_stem(&self) -> String {
+        match self {
+            Principal::User(name) => format!("corky-user-{}", name),
+            Principal::Group(name) => format!("corky-group-{}", name),
+        }
+    }
+
+    pub fn sudoers_path(&self) -> PathBuf {
+        Path::new(SUDOERS_DIR).join(self.file_stem())
+    }
+
+    pub fn polkit_rule_path(&self) -> PathBuf {
+        Path::new(POLKIT_RULES_DIR).join(format!("50-{}.rules", self.file_stem()))
+    }
+
+    fn sudoers_name(&self) -> String {
+        match self {
+            Principal::User(name) => name.clone(),
+            Principal::Group(name) => format!("%{}", name),
+        }
+    }
+
+    fn polkit_subject_test(&self) -> String {
+        match self {
+            Principal::User(name) => format!("subject.user == \"{}\"", name),
+            Principal::Group(name) => format!("subject.isInGroup(\"{}\")", name),
+        }
+    }
+}
+
+/// The exact command line corky runs for `action` on `service`; corky
+/// uses a sudoers grant only when it matches word for word.
+fn action_command(backend: &InitBackend, service: &str, action: GrantAction) -> String {
+    match (backend, action) {
+        (InitBackend::Systemd { .. }, GrantAction::Logs) => {
+            format!("journalctl -u {}.service -f", service)
+        }
+        (InitBackend::Systemd { .. }, action) => {
+            format!("systemctl {} {}.service", action.name(), service)
+        }
+        (InitBackend::Supervisor, GrantAction::Logs) => format!("supervisorctl tail -f {}", service),
+        (InitBackend::Supervisor, action) => format!("supervisorctl {} {}", action.name(), service),
+    }
+}
+
+/// One-line summary recorded in each file.
+fn grant_summary(principal: &Principal, services: &[String], actions: &[GrantAction]) -> String {
+    let actions: Vec<&str> = actions.iter().map(|a| a.name()).collect();
+    format!(
+        "{} {} may {} {}",
+        GRANT_MARKER,
+        principal,
+        actions.join(","),
+        services.join(",")
+    )
+}
+
+/// A sudoers drop-in allowing exactly the commands for `actions`, with
+/// absolute program paths as sudoers requires.
+pub fn render_sudoers(
+    principal: &Principal,
+    backend: &InitBackend,
+    services: &[String],
+    actions: &[GrantAction],
+    resolve: impl Fn(&str) -> String,
+) -> String {
+    let mut commands = Vec::new();
+    for service in services {
+        for action in actions {
+            let command = action_command(backend, service, *action);
+            let (program, args) = command.split_once(' ').unwrap_or((&command, ""));
+            commands.push(format!("{} {}", resolve(program), args));
+        }
+    }
+    format!(
+        "# {}\n# Managed by corky; remove with `corky revoke`.\n{} ALL=(root) NOPASSWD: {}\n",
+        grant_summary(principal, services, actions),
+        principal.sudoers_name(),
+        commands.join(", \\\n    ")
+    )
+}
+
+/// A polkit rule letting systemd start/stop/restart exactly these units.
+pub fn render_polkit_rule(
+    principal: &Principal,
+    services: &[String],
+    actions: &[GrantAction],
+) -> String {
+    let quoted = |items: Vec<String>| {
+        items
+            .iter()
+            .map(|i| format!("\"{}\"", i))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let units = quoted(services.iter().map(|s| format!("{}.service", s)).collect());
+    let verbs = quoted(actions.iter().map(|a| a.name().to_string()).collect());
+    format!(
+        "// {summary}\n\
+         // Managed by corky; remove with `corky revoke`.\n\
+         polkit.addRule(function(action, subject) {{\n\
+         \x20   if (action.id == \"org.freedesktop.systemd1.manage-units\" &&\n\
+         \x20       {subject} &&\n\
+         \x20       [{units}].indexOf(action.lookup(\"unit\")) >= 0 &&\n\
+         \x20       [{verbs}].indexOf(action.lookup(\"verb\")) >= 0) {{\n\
+         \x20       return polkit.Result.YES;\n\
+         \x20   }}\n\
+         }});\n",
+        summary = grant_summary(principal, services, actions),
+        subject = principal.polkit_subject_test(),
+    )
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// corky grant / revoke
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Allow `principal` to run `actions` on `services` (all installed corky
+/// services when empty) without full root. On systemd hosts with polkit,
+/// unit actions go into a polkit rule; logs, and everything on supervisor
+/// hosts, go into a sudoers drop-in checked with `visudo -c`. Replaces any
+/// earlier grant for `principal`.
+pub fn run_grant(
+    backend: &InitBackend,
+    principal: &str,
+    services: &[String],
+    actions_requested: &[GrantAction],
+    dry_run: bool,
+) {
+    let principal: Principal = principal.parse().unwrap_or_else(|e: String| exit_error(&e));
+    if !dry_run {
+        ensure_root(backend);
+    }
+    let services = resolve_services(backend, services);
+    let mut actions: Vec<GrantAction> = Vec::new();
+    for action in actions_requested {
+        if !actions.contains(action) {
+            actions.push(*action);
+        }
+    }
+
+    let use_polkit = matches!(backend, InitBackend::Systemd { .. })
+        && Path::new(POLKIT_RULES_DIR).is_dir();
+    let (unit_actions, sudo_actions): (Vec<GrantAction>, Vec<GrantAction>) = actions
+        .iter()
+        .partition(|a| use_polkit && **a != GrantAction::Logs);
+
+    section(&format!("Granting {}", principal));
+    let polkit_path = principal.polkit_rule_path();
+    let sudoers_path = principal.sudoers_path();
+    warn_replaced_grant(&[&polkit_path, &sudoers_path], &services, &actions);
+    let polkit_rule = (!unit_actions.is_empty())
+        .then(|| render_polkit_rule(&principal, &services, &unit_actions));
+    let sudoers = (!sudo_actions.is_empty()).then(|| {
+        render_sudoers(&principal, backend, &services, &sudo_actions, |program| {
+            find_in_path(program)
+                .map(|p| p.to_string_lossy().into_owned())
+                .unwrap_or_else(|| format!("/usr/bin/{}", program))
+        })
+    });
+
+    if dry_run {
+        for (path, contents) in [(&polkit_path, &polkit_rule), (&sudoers_path, &sudoers)] {
+            match contents {
+                Some(contents) => {
+                    println!("{C_CYAN}[DRY-RUN]{C_RESET} Would write: {}", path.display());
+                    println!("---------- {} ----------\n{}", path.display(), contents);
+                }
+                None if path.exists() => {
+                    println!("{C_CYAN}[DRY-RUN]{C_RESET} Would remove: {}", path.display())
+                }
+                None => {}
+            }
+        }
+        return;
+    }
+
+    // Validate before anything is written: a broken sudoers file locks out sudo
+    if let Some(sudoers) = &sudoers {
+        if find_in_path("visudo").is_none() {
+            exit_error("visudo not found; refusing to write an unvalidated sudoers file.");
+        }
+        let tmp = Path::new(SUDOERS_DIR).join(format!(".{}.tmp", principal.file_stem()));
+        let written = fs::create_dir_all(SUDOERS_DIR)
+            .and_then(|()| fs::write(&tmp, sudoers))
+            .and_then(|()| fs::set_permissions(&tmp, fs::Permissions::from_mode(0o440)));
+        if let Err(e) = written {
+            let _ = fs::remove_file(&tmp);
+            exit_error(&format!("write {}: {}", tmp.display(), e));
+        }
+        if !run_cmd("visudo", &["-c", "-q", "-f", &tmp.to_string_lossy()]) {
+            let _ = fs::remove_file(&tmp);
+            exit_error("visudo rejected the generated sudoers file; nothing was changed.");
+        }
+        fs::rename(&tmp, &sudoers_path)
+            .unwrap_or_else(|e| exit_error(&format!("write {}: {}", sudoers_path.display(), e)));
+        println!("{C_GREEN}[INFO]{C_RESET} Wrote {}", sudoers_path.display());
+    } else {
+        remove_if_present(&sudoers_path);
+    }
+    if let Some(rule) = &polkit_rule {
+        fs::write(&polkit_path, rule)
+            .and_then(|()| fs::set_permissions(&polkit_path, fs::Permissions::from_mode(0o644)))
+            .unwrap_or_else(|e| exit_error(&format!("write {}: {}", polkit_path.display(), e)));
+        println!("{C_GREEN}[INFO]{C_RESET} Wrote {}", polkit_path.display());
+    } else {
+        remove_if_present(&polkit_path);
+    }
+
+    let names: Vec<&str> = actions.iter().map(|a| a.name()).collect();
+    println!(
+        "{C_BGREEN}[OK]{C_RESET} {} may now {} {}",
+        principal,
+        names.join(", "),
+        services.join(", ")
+    );
+    println!(
+        "  e.g. corky {} {}",
+        names[0],
+        services[0].trim_start_matches("corky-")
+    );
+}
+
+/// `--services` as installed corky service names; every service when empty.
+fn resolve_services(backend: &InitBackend, requested: &[String]) -> Vec<String> {
+    let installed: Vec<String> = list_corky_services(backend).into_iter().map(|s| s.name).collect();
+    if requested.is_empty() {
+        if installed.is_empty() {
+            exit_error("No corky services are installed.");
+        }
+        println!(
+            "{C_YELLOW}[WARN]{C_RESET} No --services given; granting the {} installed service(s). Services installed later are not covered.",
+            installed.len()
+        );
+        return installed;
+    }
+    let mut services = Vec::new();
+    for name in requested {
+        let name = ensure_corky_prefix(name);
+        if !installed.contains(&name) {
+            exit_error(&format!("{} is not an installed corky service.", name));
+        }
+        if !services.contains(&name) {
+            services.push(name);
+        }
+    }
+    services
+}
+
+/// Remove every grant for `principal`.
+pub fn run_revoke(backend: &InitBackend, principal: &str, dry_run: bool) {
+    let principal: Principal = principal.parse().unwrap_or_else(|e: String| exit_error(&e));
+    if !dry_run {
+        ensure_root(backend);
+    }
+    section(&format!("Revoking {}", principal));
+    let paths = [principal.sudoers_path(), principal.polkit_rule_path()];
+    let existing: Vec<&PathBuf> = paths.iter().filter(|p| p.exists()).collect();
+    if existing.is_empty() {
+        exit_error(&format!("{} has no corky grants.", principal));
+    }
+    for path in existing {
+        if dry_run {
+            println!("{C_CYAN}[DRY-RUN]{C_RESET} Would remove: {}", path.display());
+        } else {
+            remove_if_present(path);
+        }
+    }
+    if !dry_run {
+        println!("{C_BGREEN}[OK]{C_RESET} Revoked all corky grants for {}", principal);
+    }
+}
+
+/// Print the summary line of every grant file.
+pub fn run_grant_list(backend: &InitBackend) {
+    ensure_root(backend);
+    let mut grants: Vec<(PathBuf, String)> = Vec::new();
+    for dir in [SUDOERS_DIR, POLKIT_RULES_DIR] {
+        let Ok(entries) = fs::read_dir(dir) else {
+            continue;
+        };
+        for path in entries.flatten().map(|e| e.path()) {
+            let name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+            if !name.starts_with("corky-") && !name.starts_with("50-corky-") {
+                continue;
+            }
+            if let Some(summary) = fs::read_to_string(&path)
+                .ok()
+                .and_then(|text| read_summary(&text))
+            {
+                grants.push((path, summary));
+            }
+        }
+    }
+    if grants.is_empty() {
+        println!("No corky grants.");
+        return;
+    }
+    grants.sort();
+    for (path, summary) in grants {
+        println!("{}  ({})", summary, path.display());
+    }
+}
+
+/// "user alice may restart corky-api" from a file's marker line.
+fn read_summary(text: &str) -> Option<String> {
+    let first = text.lines().next()?;
+    let (_, summary) = first.split_once(GRANT_MARKER)?;
+    Some(summary.trim().to_string())
+}
+
+/// A grant replaces the principal's previous one; say what it no longer allows.
+fn warn_replaced_grant(paths: &[&Path], services: &[String], actions: &[GrantAction]) {
+    let previous: Vec<String> = paths
+        .iter()
+        .filter_map(|path| fs::read_to_string(path).ok())
+        .filter_map(|text| read_summary(&text))
+        .collect();
+    let (dropped_services, dropped_actions) = dropped_by_regrant(&previous, services, actions);
+    if !dropped_services.is_empty() {
+        println!(
+            "{C_YELLOW}[WARN]{C_RESET} Replacing the existing grant; no longer covered: {}",
+            dropped_services.join(", ")
+        );
+    }
+    if !dropped_actions.is_empty() {
+        println!(
+            "{C_YELLOW}[WARN]{C_RESET} Replacing the existing grant; no longer allowed: {}",
+            dropped_actions.join(", ")
+        );
+    }
+}
+
+/// Services and actions in the `previous` summaries that the new grant leaves out.
+fn dropped_by_regrant(
+    previous: &[String],
+    services: &[String],
+    actions: &[GrantAction],
+) -> (Vec<String>, Vec<String>) {
+    let (mut dropped_services, mut dropped_actions) = (Vec::<String>::new(), Vec::<String>::new());
+    for summary in previous {
+        // "<principal> may <actions> <services>"
+        let mut words = summary.rsplitn(3, ' ');
+        let (Some(old_services), Some(old_actions)) = (words.next(), words.next()) else {
+            continue;
+        };
+        for service in old_services.split(',') {
+            if !services.iter().any(|s| s == service) && !dropped_services.iter().any(|s| s == service) {
+                dropped_services.push(service.to_string());
+            }
+        }
+        for action in old_actions.split(',') {
+            if !actions.iter().any(|a| a.name() == action) && !dropped_actions.iter().any(|a| a == action) {
+                dropped_actions.push(action.to_string());
+            }
+        }
+    }
+    (dropped_services, dropped_actions)
+}
+
+fn remove_if_present(path: &Path) {
+    if path.exists() {
+        match fs::remove_file(path) {
+            Ok(()) => println!("{C_GREEN}[INFO]{C_RESET} Removed {}", path.display()),
+            Err(e) => exit_error(&format!("remove {}: {}", path.display(), e)),
+        }
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Tests
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_principal() {
+        assert_eq!("alice".parse(), Ok(Principal::User("alice".to_string())));
+        assert_eq!("%oncall".parse(), Ok(Principal::Group("oncall".to_string())));
+        assert!("bob ALL=(ALL) ALL".parse::<Principal>().is_err());
+        assert!("%".parse::<Principal>().is_err());
+        assert_eq!(
+            Principal::Group("oncall".to_string()).sudoers_path(),
+            Path::new("/etc/sudoers.d/corky-group-oncall")
+        );
+    }
+
+    #[test]
+    fn test_render_sudoers() {
+        let text = render_sudoers(
+            &Principal::Group("oncall".to_string()),
+            &InitBackend::Supervisor,
+            &["corky-api".to_string()],
+            &[GrantAction::Restart, GrantAction::Logs],
+            |program| format!("/usr/bin/{}", program),
+        );
+        assert_eq!(
+            text,
+            "# corky grant: group oncall may restart,logs corky-api\n\
+             # Managed by corky; remove with `corky revoke`.\n\
+             %oncall ALL=(root) NOPASSWD: /usr/bin/supervisorctl restart corky-api, \\\n    \
+             /usr/bin/supervisorctl tail -f corky-api\n"
+        );
+        assert_eq!(
+            read_summary(&text).as_deref(),
+            Some("group oncall may restart,logs corky-api")
+        );
+    }
+
+    #[test]
+    fn test_render_polkit_rule() {
+        let rule = render_polkit_rule(
+            &Principal::User("alice".to_string()),
+            &["corky-api".to_string(), "corky-web".to_string()],
+            &[GrantAction::Start, GrantAction::Restart],
+        );
+        assert!(rule.starts_with("// corky grant: user alice may start,restart corky-api,corky-web\n"));
+        assert!(rule.contains("        subject.user == \"alice\" &&\n"));
+        assert!(rule.contains("[\"corky-api.service\", \"corky-web.service\"].indexOf(action.lookup(\"unit\"))"));
+        assert!(rule.contains("[\"start\", \"restart\"].indexOf(action.lookup(\"verb\"))"));
+    }
+
+    #[test]
+    fn test_dropped_by_regrant() {
+        let previous = [
+            "user alice may start,stop corky-api,corky-web".to_string(),
+            "user alice may logs corky-api,corky-web".to_string(),
+        ];
+        let (services, actions) = dropped_by_regrant(
+            &previous,
+            &["corky-api".to_string()],
+            &[GrantAction::Stop, GrantAction::Logs],
+        );
+        assert_eq!(services, ["corky-web"]);
+        assert_eq!(actions, ["start"]);
+        let (services, actions) = dropped_by_regrant(&[], &["corky-api".to_string()], &[GrantAction::Logs]);
+        assert!(services.is_empty() && actions.is_empty());
+    }
+}
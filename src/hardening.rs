@@ -0,0 +1,205 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use serde::{Deserialize, Serialize};
+
+use crate::init::{exit_error, InitBackend, ServiceInfo, C_BOLD, C_RESET};
+use crate::manifest::{load_manifest, InstallManifest};
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Hardening presets
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// systemd sandboxing level for generated units (`[corky] hardening = "..."`).
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum HardeningPreset {
+    /// No sandboxing (the historical default).
+    #[default]
+    None,
+    /// Safe for almost any service: no privilege gain, read-only /usr and /etc.
+    Basic,
+    /// Full sandbox; only the working directory stays writable.
+    Strict,
+}
+
+impl std::fmt::Display for HardeningPreset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HardeningPreset::None => write!(f, "none"),
+            HardeningPreset::Basic => write!(f, "basic"),
+            HardeningPreset::Strict => write!(f, "strict"),
+        }
+    }
+}
+
+const BASIC: &[(&str, &str)] = &[
+    ("NoNewPrivileges", "yes"),
+    ("PrivateTmp", "yes"),
+    ("ProtectSystem", "full"),
+    ("ProtectHome", "no"),
+    ("ProtectKernelTunables", "yes"),
+    ("ProtectKernelModules", "yes"),
+    ("ProtectControlGroups", "yes"),
+    ("RestrictSUIDSGID", "yes"),
+];
+
+const STRICT: &[(&str, &str)] = &[
+    ("NoNewPrivileges", "yes"),
+    ("PrivateTmp", "yes"),
+    ("PrivateDevices", "yes"),
+    ("ProtectSystem", "strict"),
+    ("ProtectHome", "read-only"),
+    ("ProtectKernelTunables", "yes"),
+    ("ProtectKernelModules", "yes"),
+    ("ProtectKernelLogs", "yes"),
+    ("ProtectControlGroups", "yes"),
+    ("ProtectClock", "yes"),
+    ("ProtectHostname", "yes"),
+    ("RestrictAddressFamilies", "AF_UNIX AF_INET AF_INET6"),
+    ("RestrictNamespaces", "yes"),
+    ("RestrictRealtime", "yes"),
+    ("RestrictSUIDSGID", "yes"),
+    ("LockPersonality", "yes"),
+    ("MemoryDenyWriteExecute", "yes"),
+    ("SystemCallArchitectures", "native"),
+    ("CapabilityBoundingSet", ""),
+    ("AmbientCapabilities", ""),
+];
+
+/// The [Service] directives for a preset, with `overrides` applied: an
+/// override replaces the preset's value, adds a new directive, or (when
+/// empty) drops the directive entirely.
+pub fn hardening_directives(
+    preset: HardeningPreset,
+    overrides: &BTreeMap<String, String>,
+    working_dir: &Path,
+) -> Vec<(String, String)> {
+    let base: &[(&str, &str)] = match preset {
+        HardeningPreset::None => &[("ProtectHome", "no")],
+        HardeningPreset::Basic => BASIC,
+        HardeningPreset::Strict => STRICT,
+    };
+    let mut directives: Vec<(String, String)> = base
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+    if preset == HardeningPreset::Strict {
+        // ProtectSystem=strict makes everything read-only; keep the service's own dir writable
+        directives.push((
+            "ReadWritePaths".to_string(),
+            working_dir.display().to_string(),
+        ));
+    }
+
+    for (key, value) in overrides {
+        match directives.iter().position(|(k, _)| k == key) {
+            Some(i) if value.is_empty() => {
+                directives.remove(i);
+            }
+            Some(i) => directives[i].1 = value.clone(),
+            None if value.is_empty() => {}
+            None => directives.push((key.clone(), value.clone())),
+        }
+    }
+    directives
+}
+
+/// Render the hardening block of a unit, one `Key=value` line each.
+pub fn render_hardening(manifest: &InstallManifest) -> String {
+    hardening_directives(
+        manifest.hardening,
+        &manifest.hardening_overrides,
+        &manifest.working_dir,
+    )
+    .iter()
+    .map(|(k, v)| format!("{}={}\n", k, v))
+    .collect()
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// corky security
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Print the service's hardening settings and `systemd-analyze security` exposure report.
+pub fn run_security_report(service_info: &ServiceInfo) -> ! {
+    let InitBackend::Systemd { scope } = &service_info.backend else {
+        exit_error("Security reports need systemd; supervisor programs run without a sandbox.");
+    };
+
+    if let Some(manifest) = load_manifest(&service_info.name) {
+        println!(
+            "{C_BOLD}{}{C_RESET}: hardening = \"{}\"",
+            service_info.name, manifest.hardening
+        );
+        for (key, value) in &manifest.hardening_overrides {
+            println!("  override {}={}", key, value);
+        }
+        println!();
+    }
+
+    let mut cmd = Command::new("systemd-analyze");
+    if scope == "user" {
+        cmd.arg("--user");
+    }
+    let status = cmd
+        .arg("security")
+        .arg(format!("{}.service", service_info.name))
+        .stdin(Stdio::inherit())
+        .stdout(Stdio::inherit())
+        .stderr(Stdio::inherit())
+        .status()
+        .unwrap_or_else(|e| exit_error(&format!("Failed to run systemd-analyze: {}", e)));
+    std::process::exit(status.code().unwrap_or(1));
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Tests
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(directives: &[(String, String)]) -> Vec<&str> {
+        directives.iter().map(|(k, _)| k.as_str()).collect()
+    }
+
+    #[test]
+    fn test_none_preset_keeps_legacy_output() {
+        let d = hardening_directives(HardeningPreset::None, &BTreeMap::new(), Path::new("/srv"));
+        assert_eq!(d, vec![("ProtectHome".to_string(), "no".to_string())]);
+    }
+
+    #[test]
+    fn test_strict_preset_keeps_workdir_writable() {
+        let d = hardening_directives(HardeningPreset::Strict, &BTreeMap::new(), Path::new("/srv/a"));
+        assert!(d.contains(&("ProtectSystem".to_string(), "strict".to_string())));
+        assert!(d.contains(&("ReadWritePaths".to_string(), "/srv/a".to_string())));
+    }
+
+    #[test]
+    fn test_overrides_replace_add_and_drop() {
+        let overrides = BTreeMap::from([
+            ("ProtectSystem".to_string(), "strict".to_string()),
+            ("PrivateTmp".to_string(), String::new()),
+            ("PrivateNetwork".to_string(), "yes".to_string()),
+        ]);
+        let d = hardening_directives(HardeningPreset::Basic, &overrides, Path::new("/srv"));
+        assert!(d.contains(&("ProtectSystem".to_string(), "strict".to_string())));
+        assert!(!keys(&d).contains(&"PrivateTmp"));
+        assert_eq!(keys(&d).last(), Some(&"PrivateNetwork"));
+    }
+
+    #[test]
+    fn test_preset_deserialize() {
+        #[derive(Deserialize)]
+        struct T {
+            hardening: HardeningPreset,
+        }
+        let t: T = toml::from_str("hardening = \"strict\"").unwrap();
+        assert_eq!(t.hardening, HardeningPreset::Strict);
+        assert!(toml::from_str::<T>("hardening = \"paranoid\"").is_err());
+    }
+}
@@ -1,21 +1,63 @@
 use serde::Deserialize;
+use std::collections::BTreeMap;
 use std::env;
 use std::fs;
-use std::os::unix::fs::PermissionsExt;
+use std::io::{self, Read};
+use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
 use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
 
+use corky_sdk::READY_FILE_ENV;
+
+use crate::archive::{ExtractedArchive, InstallSource};
+use crate::build::{BuildOptions, ENV_ARTIFACT_PATH};
+use crate::deploy::{
+    install_assets, managed_dir_env, prepare_service_dirs, state_dir, warn_unused_assets,
+};
+use crate::diff::{config_is_current, show_config_changes};
+use crate::escalate::{elevation_env, escalator_or_exit, granted_command, ENV_ORIGINAL_USER};
+use crate::envfile::{env_file_path, read_env_file, redact_config, write_config_file};
+use crate::hardening::{render_hardening, HardeningPreset};
+use crate::instance::{
+    install_template, instance_name, is_valid_instance_id, split_instance, stop_instances,
+    template_unit_path, validate_template,
+};
+use crate::limits::{
+    render_limit_directives, supervisor_limit_wrapper, warn_unenforced_limits, ResourceLimits,
+    WrapperTools,
+};
+use crate::logs::print_failure_context;
+use crate::manifest::{
+    list_manifests, load_manifest, manifest_path, remove_manifest, write_manifest,
+    InstallManifest,
+};
+use crate::readiness::{
+    can_wait_for_ready, is_notify_service, ready_dir, ready_file_path, render_notify_directives,
+    wait_until_ready, ReadyMode, DEFAULT_WATCHDOG_SEC,
+};
+use crate::schedule::{
+    cron_path, install_cron_job, install_systemd_timer, remove_schedule, scheduled_manifest,
+    set_schedule_enabled, validate_schedule,
+};
+use crate::signal::{normalize_signal, render_exec_reload, DEFAULT_RELOAD_SIGNAL};
+use crate::socket::{
+    install_systemd_socket, is_socket_activated, remove_socket, validate_listen,
+};
+use crate::users::{remove_system_user, resolve_service_user, ServiceUser, UserSpec};
+
 // ─────────────────────────────────────────────────────────────────────────────
 // Constants & colors
 // ─────────────────────────────────────────────────────────────────────────────
-pub const SUDO_BIN: &str = "sudo";
 pub const ENV_ELEVATED_FLAG: &str = "CORKY_ELEVATED";
 pub const ENV_BINARY_CHECKSUM: &str = "CORKY_BINARY_CHECKSUM";
 pub const ENV_ORIGINAL_CWD: &str = "CORKY_ORIGINAL_CWD";
 pub const ENV_INIT_BACKEND: &str = "CORKY_INIT_BACKEND";
 pub const BIN_PATH_SYSTEM: &str = "/usr/local/bin";
-const UNIT_DIR_SYSTEM: &str = "/etc/systemd/system";
-const SUPERVISOR_CONF_DIR: &str = "/etc/supervisor/conf.d";
+pub const UNIT_DIR_SYSTEM: &str = "/etc/systemd/system";
+pub const SUPERVISOR_CONF_DIR: &str = "/etc/supervisor/conf.d";
+pub const SUPERVISOR_LOG_DIR: &str = "/var/log/supervisor";
+pub const CORKY_ETC_DIR: &str = "/etc/corky";
+const SUPERVISOR_SOCKETS: [&str; 2] = ["/var/run/supervisor.sock", "/run/supervisor.sock"];
 
 pub const C_RESET: &str = "\x1b[0m";
 pub const C_BOLD: &str = "\x1b[1m";
@@ -118,70 +160,70 @@ fn detect_systemd_scope() -> String {
     if is_root() { "system".to_string() } else { "user".to_string() }
 }
 
-fn is_systemd_available() -> bool {
+pub fn is_systemd_available() -> bool {
     Path::new("/run/systemd/system").exists()
 }
 
-fn is_supervisor_available() -> bool {
+pub fn is_supervisor_available() -> bool {
     // Check for supervisord socket
-    if Path::new("/var/run/supervisor.sock").exists() {
-        return true;
-    }
-    if Path::new("/run/supervisor.sock").exists() {
+    if supervisor_socket().is_some() {
         return true;
     }
     // Fallback: try supervisorctl
     run_cmd_quiet("supervisorctl", &["version"])
 }
 
+/// Path of the supervisord control socket, if one exists.
+pub fn supervisor_socket() -> Option<PathBuf> {
+    SUPERVISOR_SOCKETS
+        .iter()
+        .map(PathBuf::from)
+        .find(|p| p.exists())
+}
+
+/// Config files left behind by the init system that is *not* in use.
+pub fn orphaned_configs(backend: &InitBackend) -> Vec<PathBuf> {
+    let (dir, suffix) = match backend {
+        InitBackend::Supervisor => (UNIT_DIR_SYSTEM, ".service"),
+        InitBackend::Systemd { .. } => (SUPERVISOR_CONF_DIR, ".conf"),
+    };
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter(|e| {
+            let n = e.file_name();
+            let s = n.to_string_lossy();
+            s.starts_with("corky-") && s.ends_with(suffix)
+        })
+        .map(|e| e.path())
+        .collect()
+}
+
 /// Warn about orphaned configs from a different init system.
 pub fn check_migration_warning(backend: &InitBackend) {
+    let orphans = orphaned_configs(backend);
+    if orphans.is_empty() {
+        return;
+    }
     match backend {
         InitBackend::Supervisor => {
-            // Check for orphaned systemd units
-            if let Ok(entries) = fs::read_dir(UNIT_DIR_SYSTEM) {
-                let orphans: Vec<_> = entries
-                    .flatten()
-                    .filter(|e| {
-                        let n = e.file_name();
-                        let s = n.to_string_lossy();
-                        s.starts_with("corky-") && s.ends_with(".service")
-                    })
-                    .collect();
-                if !orphans.is_empty() {
-                    eprintln!(
-                        "{C_YELLOW}[WARN]{C_RESET} Found {} orphaned systemd unit file(s) but running under supervisor.",
-                        orphans.len()
-                    );
-                    for o in &orphans {
-                        eprintln!("  rm {}", o.path().display());
-                    }
-                }
-            }
+            eprintln!(
+                "{C_YELLOW}[WARN]{C_RESET} Found {} orphaned systemd unit file(s) but running under supervisor.",
+                orphans.len()
+            );
         }
         InitBackend::Systemd { .. } => {
-            // Check for orphaned supervisor configs
-            if let Ok(entries) = fs::read_dir(SUPERVISOR_CONF_DIR) {
-                let orphans: Vec<_> = entries
-                    .flatten()
-                    .filter(|e| {
-                        let n = e.file_name();
-                        let s = n.to_string_lossy();
-                        s.starts_with("corky-") && s.ends_with(".conf")
-                    })
-                    .collect();
-                if !orphans.is_empty() {
-                    eprintln!(
-                        "{C_YELLOW}[WARN]{C_RESET} Found {} orphaned supervisor config(s) but running under systemd.",
-                        orphans.len()
-                    );
-                    for o in &orphans {
-                        eprintln!("  rm {}", o.path().display());
-                    }
-                }
-            }
+            eprintln!(
+                "{C_YELLOW}[WARN]{C_RESET} Found {} orphaned supervisor config(s) but running under systemd.",
+                orphans.len()
+            );
         }
     }
+    for o in &orphans {
+        eprintln!("  rm {}", o.display());
+    }
 }
 
 // ─────────────────────────────────────────────────────────────────────────────
@@ -199,9 +241,43 @@ struct Package {
     description: Option<String>,
 }
 
-#[derive(Deserialize)]
-struct CorkyConfig {
+#[derive(Deserialize, Default)]
+pub struct CorkyConfig {
     is_corky_package: Option<bool>,
+    /// systemd sandboxing preset for the generated unit
+    #[serde(default)]
+    pub hardening: HardeningPreset,
+    /// Per-directive tweaks on top of the preset (empty value drops it)
+    #[serde(default)]
+    pub hardening_overrides: BTreeMap<String, String>,
+    /// "dynamic", "create:<name>" or an existing user name
+    pub user: Option<String>,
+    /// Run from /var/lib/corky/<name> instead of the checkout (default true)
+    pub managed_dirs: Option<bool>,
+    /// Package files copied into the state directory at install
+    #[serde(default)]
+    pub assets: Vec<String>,
+    /// `[corky.limits]` resource caps
+    #[serde(default)]
+    pub limits: ResourceLimits,
+    /// OnCalendar expression; makes the service a timer-driven job
+    pub schedule: Option<String>,
+    /// Addresses for systemd socket activation
+    #[serde(default)]
+    pub listen: Vec<String>,
+    /// Install as a template run once per `corky instance add`
+    #[serde(default)]
+    pub template: bool,
+    /// "notify": the service reports readiness through corky-sdk
+    #[serde(default)]
+    pub ready: ReadyMode,
+    /// WatchdogSec= for notify services (default 30, 0 disables)
+    pub watchdog_sec: Option<u64>,
+    /// Signal `corky reload` sends (default SIGHUP)
+    pub reload_signal: Option<String>,
+    /// `[corky.build]` cargo build options
+    #[serde(default)]
+    pub build: BuildOptions,
 }
 
 // ─────────────────────────────────────────────────────────────────────────────
@@ -209,12 +285,50 @@ struct CorkyConfig {
 // ─────────────────────────────────────────────────────────────────────────────
 
 pub fn list_corky_services(backend: &InitBackend) -> Vec<ServiceInfo> {
-    match backend {
+    let mut services = match backend {
         InitBackend::Systemd { .. } => list_corky_services_systemd(),
         InitBackend::Supervisor => list_corky_services_supervisor(),
+    };
+
+    // Services adopted under their original (unprefixed) name, cron-driven
+    // jobs and template instances live only in the registry
+    let registry_backend = match backend {
+        InitBackend::Systemd { .. } => InitBackend::Systemd {
+            scope: "system".to_string(),
+        },
+        InitBackend::Supervisor => InitBackend::Supervisor,
+    };
+    for manifest in list_manifests() {
+        if manifest.backend != backend.to_string() {
+            continue;
+        }
+        let names = if manifest.template {
+            // The template itself (corky-x@.service / corky-x.conf) isn't runnable
+            let template = &manifest.service_name;
+            services.retain(|s| s.name != *template && s.name != format!("{}@", template));
+            manifest
+                .instances
+                .iter()
+                .map(|id| instance_name(template, id))
+                .collect()
+        } else if manifest.service_name.starts_with("corky-") && manifest.schedule.is_none() {
+            continue;
+        } else {
+            vec![manifest.service_name]
+        };
+        for name in names {
+            if !services.iter().any(|s| s.name == name) {
+                services.push(ServiceInfo {
+                    name,
+                    backend: registry_backend.clone(),
+                });
+            }
+        }
     }
+    services
 }
 
+#[allow(clippy::collapsible_if)]
 fn list_corky_services_systemd() -> Vec<ServiceInfo> {
     let mut services = Vec::new();
 
@@ -313,11 +427,22 @@ fn list_corky_services_supervisor() -> Vec<ServiceInfo> {
 // ─────────────────────────────────────────────────────────────────────────────
 
 pub fn run_service_action(action: &str, service_info: &ServiceInfo) {
-    elevate_if_needed(service_info);
+    // A polkit rule or `corky grant` may cover this exact command
+    let unit = format!("{}.service", service_info.name);
+    let granted = match &service_info.backend {
+        _ if action == "status" => None,
+        InitBackend::Systemd { .. } => granted_command(service_info, "systemctl", &[action, &unit]),
+        InitBackend::Supervisor => {
+            granted_command(service_info, "supervisorctl", &[action, &service_info.name])
+        }
+    };
+    if granted.is_none() {
+        elevate_if_needed(service_info);
+    }
 
     match &service_info.backend {
         InitBackend::Systemd { scope } => {
-            let mut cmd = Command::new("systemctl");
+            let mut cmd = granted.unwrap_or_else(|| Command::new("systemctl"));
             if scope == "user" {
                 cmd.arg("--user");
             }
@@ -330,7 +455,7 @@ pub fn run_service_action(action: &str, service_info: &ServiceInfo) {
             if action == "status" {
                 let status = cmd
                     .arg(action)
-                    .arg(format!("{}.service", service_info.name))
+                    .arg(&unit)
                     .stdin(Stdio::inherit())
                     .stdout(Stdio::inherit())
                     .stderr(Stdio::inherit())
@@ -340,7 +465,7 @@ pub fn run_service_action(action: &str, service_info: &ServiceInfo) {
             } else {
                 match cmd
                     .arg(action)
-                    .arg(format!("{}.service", service_info.name))
+                    .arg(&unit)
                     .output()
                 {
                     Ok(output) => {
@@ -362,6 +487,7 @@ pub fn run_service_action(action: &str, service_info: &ServiceInfo) {
                                 "\n{C_RED}[ERROR]{C_RESET} Failed to {} service {}. Exit code: {}",
                                 action, service_info.name, exit_code
                             );
+                            print_failure_context(service_info);
                         }
                         std::process::exit(exit_code);
                     }
@@ -376,12 +502,27 @@ pub fn run_service_action(action: &str, service_info: &ServiceInfo) {
             }
         }
         InitBackend::Supervisor => {
+            if scheduled_manifest(&service_info.name).is_some() {
+                if action == "status" {
+                    std::process::exit(0);
+                }
+                exit_error(&format!(
+                    "{} is a scheduled job run by cron ({}); it can't be {} directly.",
+                    service_info.name,
+                    cron_path(&service_info.name).display(),
+                    past_tense(action)
+                ));
+            }
+            let wait_ready = matches!(action, "start" | "restart")
+                && is_notify_service(&service_info.name)
+                && can_wait_for_ready(&service_info.name);
             println!(
                 "{C_GREEN}[INFO]{C_RESET} Running: supervisorctl {} {}",
                 action, service_info.name
             );
 
-            let status = Command::new("supervisorctl")
+            let status = granted
+                .unwrap_or_else(|| Command::new("supervisorctl"))
                 .args([action, &service_info.name])
                 .stdin(Stdio::inherit())
                 .stdout(Stdio::inherit())
@@ -389,7 +530,10 @@ pub fn run_service_action(action: &str, service_info: &ServiceInfo) {
                 .status()
                 .expect("Failed to run supervisorctl");
 
-            let exit_code = status.code().unwrap_or(1);
+            let mut exit_code = status.code().unwrap_or(1);
+            if exit_code == 0 && wait_ready && !wait_until_ready(&service_info.name) {
+                exit_code = 1;
+            }
             if action != "status" {
                 if exit_code == 0 {
                     println!(
@@ -402,6 +546,7 @@ pub fn run_service_action(action: &str, service_info: &ServiceInfo) {
                         "\n{C_RED}[ERROR]{C_RESET} Failed to {} service {}. Exit code: {}",
                         action, service_info.name, exit_code
                     );
+                    print_failure_context(service_info);
                 }
             }
             std::process::exit(exit_code);
@@ -412,6 +557,10 @@ pub fn run_service_action(action: &str, service_info: &ServiceInfo) {
 /// Enable a service (auto-start).
 pub fn run_service_enable(service_info: &ServiceInfo) {
     elevate_if_needed(service_info);
+    if scheduled_manifest(&service_info.name).is_some() {
+        set_schedule_enabled(service_info, true);
+        return;
+    }
 
     match &service_info.backend {
         InitBackend::Systemd { scope } => {
@@ -419,13 +568,16 @@ pub fn run_service_enable(service_info: &ServiceInfo) {
             if scope == "user" {
                 cmd.arg("--user");
             }
-            println!(
-                "{C_GREEN}[INFO]{C_RESET} Running: systemctl enable {}.service",
-                service_info.name
-            );
+            // Socket-activated services are enabled through their .socket
+            let unit = if is_socket_activated(&service_info.name) {
+                format!("{}.socket", service_info.name)
+            } else {
+                format!("{}.service", service_info.name)
+            };
+            println!("{C_GREEN}[INFO]{C_RESET} Running: systemctl enable {}", unit);
             match cmd
                 .arg("enable")
-                .arg(format!("{}.service", service_info.name))
+                .arg(&unit)
                 .output()
             {
                 Ok(output) => {
@@ -455,14 +607,14 @@ pub fn run_service_enable(service_info: &ServiceInfo) {
             }
         }
         InitBackend::Supervisor => {
-            let conf_path = supervisor_conf_path(&service_info.name);
+            let conf_path = supervisor_conf_for(&service_info.name);
             if !conf_path.exists() {
                 exit_error(&format!(
                     "Supervisor config not found: {}. Is the service installed?",
                     conf_path.display()
                 ));
             }
-            set_supervisor_autostart(&conf_path, true);
+            set_supervisor_autostart(&conf_path, &service_info.name, true);
             run_cmd_expect_ok("supervisorctl", &["reread"]);
             run_cmd_expect_ok("supervisorctl", &["update"]);
             println!(
@@ -476,6 +628,10 @@ pub fn run_service_enable(service_info: &ServiceInfo) {
 /// Disable a service (no auto-start).
 pub fn run_service_disable(service_info: &ServiceInfo) {
     elevate_if_needed(service_info);
+    if scheduled_manifest(&service_info.name).is_some() {
+        set_schedule_enabled(service_info, false);
+        return;
+    }
 
     match &service_info.backend {
         InitBackend::Systemd { scope } => {
@@ -483,13 +639,16 @@ pub fn run_service_disable(service_info: &ServiceInfo) {
             if scope == "user" {
                 cmd.arg("--user");
             }
-            println!(
-                "{C_GREEN}[INFO]{C_RESET} Running: systemctl disable {}.service",
-                service_info.name
-            );
+            // Socket-activated services are enabled through their .socket
+            let unit = if is_socket_activated(&service_info.name) {
+                format!("{}.socket", service_info.name)
+            } else {
+                format!("{}.service", service_info.name)
+            };
+            println!("{C_GREEN}[INFO]{C_RESET} Running: systemctl disable {}", unit);
             match cmd
                 .arg("disable")
-                .arg(format!("{}.service", service_info.name))
+                .arg(&unit)
                 .output()
             {
                 Ok(output) => {
@@ -519,14 +678,14 @@ pub fn run_service_disable(service_info: &ServiceInfo) {
             }
         }
         InitBackend::Supervisor => {
-            let conf_path = supervisor_conf_path(&service_info.name);
+            let conf_path = supervisor_conf_for(&service_info.name);
             if !conf_path.exists() {
                 exit_error(&format!(
                     "Supervisor config not found: {}. Is the service installed?",
                     conf_path.display()
                 ));
             }
-            set_supervisor_autostart(&conf_path, false);
+            set_supervisor_autostart(&conf_path, &service_info.name, false);
             run_cmd_expect_ok("supervisorctl", &["reread"]);
             run_cmd_expect_ok("supervisorctl", &["update"]);
             println!(
@@ -539,18 +698,25 @@ pub fn run_service_disable(service_info: &ServiceInfo) {
 
 /// Follow/stream logs for a service.
 pub fn run_service_logs(service_info: &ServiceInfo) -> ! {
-    elevate_if_needed(service_info);
+    let unit = format!("{}.service", service_info.name);
+    let granted = match &service_info.backend {
+        InitBackend::Systemd { .. } => granted_command(service_info, "journalctl", &["-u", &unit, "-f"]),
+        InitBackend::Supervisor => {
+            granted_command(service_info, "supervisorctl", &["tail", "-f", &service_info.name])
+        }
+    };
+    if granted.is_none() {
+        elevate_if_needed(service_info);
+    }
 
     match &service_info.backend {
         InitBackend::Systemd { scope } => {
-            let mut cmd = Command::new("journalctl");
+            let mut cmd = granted.unwrap_or_else(|| Command::new("journalctl"));
             if scope == "user" {
                 cmd.arg("--user");
             }
             let status = cmd
-                .arg("-u")
-                .arg(format!("{}.service", service_info.name))
-                .arg("-f")
+                .args(["-u", &unit, "-f"])
                 .stdin(Stdio::inherit())
                 .stdout(Stdio::inherit())
                 .stderr(Stdio::inherit())
@@ -559,7 +725,8 @@ pub fn run_service_logs(service_info: &ServiceInfo) -> ! {
             std::process::exit(status.code().unwrap_or(1));
         }
         InitBackend::Supervisor => {
-            let status = Command::new("supervisorctl")
+            let status = granted
+                .unwrap_or_else(|| Command::new("supervisorctl"))
                 .args(["tail", "-f", &service_info.name])
                 .stdin(Stdio::inherit())
                 .stdout(Stdio::inherit())
@@ -575,38 +742,89 @@ pub fn run_service_logs(service_info: &ServiceInfo) -> ! {
 // Install / Uninstall
 // ─────────────────────────────────────────────────────────────────────────────
 
-pub fn install_service(backend: &InitBackend, dry_run: bool, skip_init: bool) {
+/// Build and install the package as a service. Unless `force` is set, a build
+/// whose binary and rendered config match what is installed changes nothing.
+/// `build_flags` are command-line build options applied over `[corky.build]`;
+/// they don't apply when `source` is a prebuilt artifact or archive.
+#[allow(clippy::collapsible_if)]
+pub fn install_service(
+    backend: &InitBackend,
+    dry_run: bool,
+    skip_init: bool,
+    force: bool,
+    build_flags: &BuildOptions,
+    source: &InstallSource,
+) {
     // Step 1: Validate
     section("Validating Corky package");
-    validate_corky_package_or_exit();
+    let archive = match source {
+        InstallSource::Archive(path) => {
+            Some(ExtractedArchive::open(path).unwrap_or_else(|e: String| exit_error(&e)))
+        }
+        _ => None,
+    };
+    let package_toml = match &archive {
+        Some(archive) => {
+            validate_corky_package_at(&archive.manifest_path());
+            archive.manifest_path()
+        }
+        None => {
+            validate_corky_package_or_exit();
+            PathBuf::from("Cargo.toml")
+        }
+    };
     println!(
-        "{C_BGREEN}[OK]{C_RESET} {C_WHITE}Found Cargo.toml with [corky] is_corky_package=true{C_RESET}"
+        "{C_BGREEN}[OK]{C_RESET} {C_WHITE}Found {} with [corky] is_corky_package=true{C_RESET}",
+        package_toml.file_name().unwrap_or_default().to_string_lossy()
     );
+    let config = corky_config_at(&package_toml);
+    let user_spec: UserSpec = match &config.user {
+        Some(value) => value
+            .parse()
+            .unwrap_or_else(|e| exit_error(&format!("[corky] user: {}", e))),
+        None => UserSpec::Invoker,
+    };
+    if let Err(e) = config.limits.validate() {
+        exit_error(&format!("[corky.limits] {}", e));
+    }
+    if let Some(schedule) = &config.schedule {
+        validate_schedule(backend, schedule);
+    }
+    validate_listen(backend, &config.listen, config.schedule.is_some());
+    validate_template(&config);
+    if let Err(e) = normalize_signal(config.reload_signal.as_deref().unwrap_or(DEFAULT_RELOAD_SIGNAL)) {
+        exit_error(&format!("[corky] reload_signal: {}", e));
+    }
+    let build = config.build.with_overrides(build_flags);
+    if let Err(e) = build.validate() {
+        exit_error(&format!("[corky.build] {}", e));
+    }
 
     // Step 2: Build
-    section("Building (release)");
-    if dry_run {
-        println!("{C_CYAN}[DRY-RUN]{C_RESET} Would run: cargo build --release");
-    } else {
-        println!("{C_GREEN}[INFO]{C_RESET} Running: cargo build --release");
-        let status = Command::new("cargo")
-            .arg("build")
-            .arg("--release")
-            .stdin(Stdio::inherit())
-            .stdout(Stdio::inherit())
-            .stderr(Stdio::inherit())
-            .status()
-            .expect("Failed to run cargo build --release");
-        if !status.success() {
-            eprintln!("{C_RED}[ERROR]{C_RESET} cargo build --release failed.");
-            std::process::exit(status.code().unwrap_or(1));
+    match source {
+        InstallSource::Build => {
+            section(&format!("Building ({})", build.profile()));
+            if dry_run {
+                println!("{C_CYAN}[DRY-RUN]{C_RESET} Would run: {}", build.display());
+            } else {
+                build.build_or_exit();
+            }
+        }
+        InstallSource::Artifact(path) | InstallSource::Archive(path) => {
+            section("Using prebuilt binary");
+            println!("{C_GREEN}[INFO]{C_RESET} Source: {}", path.display());
+            if *build_flags != BuildOptions::default() {
+                eprintln!(
+                    "{C_YELLOW}[WARN]{C_RESET} Build options are ignored when installing a prebuilt binary."
+                );
+            }
         }
     }
 
     // Step 3: Compute checksum
-    let (raw_pkg_name, _) = pkg_name_and_description()
+    let (raw_pkg_name, _) = pkg_name_and_description_at(&package_toml)
         .unwrap_or_else(|| ("corky".to_string(), "corky service".to_string()));
-    let target_bin = Path::new("target").join("release").join(&raw_pkg_name);
+    let target_bin = source_binary(source, archive.as_ref(), &build, &raw_pkg_name);
     let checksum = if !dry_run {
         compute_file_checksum(&target_bin).unwrap_or_else(|| {
             eprintln!(
@@ -618,19 +836,35 @@ pub fn install_service(backend: &InitBackend, dry_run: bool, skip_init: bool) {
     } else {
         String::new()
     };
+    if !dry_run
+        && let Some(archive) = &archive
+        && archive.checksum().as_deref() != Some(checksum.as_str())
+    {
+        exit_error("The archive's binary does not match its recorded checksum; it may be corrupt.");
+    }
 
     // Step 4: Elevate if needed, passing backend and checksum through env
     if !is_root() {
-        println!("{C_GREEN}[INFO]{C_RESET} Elevating with sudo to install service...");
+        // The elevated run extracts its own copy
+        drop(archive);
+        println!(
+            "{C_GREEN}[INFO]{C_RESET} Elevating with {} to install service...",
+            escalator_or_exit()
+        );
         let args: Vec<String> = env::args().skip(1).collect();
         let backend_str = backend.to_string();
-        elevate_privileges(
-            &args,
-            &[
-                (ENV_BINARY_CHECKSUM, &checksum),
-                (ENV_INIT_BACKEND, &backend_str),
-            ],
-        );
+        let mut extra_env = vec![
+            (ENV_BINARY_CHECKSUM, checksum.clone()),
+            (ENV_INIT_BACKEND, backend_str),
+        ];
+        // Root's cargo config may resolve a different target dir; reuse ours
+        if *source == InstallSource::Build {
+            let artifact = build.absolute_artifact_path(&raw_pkg_name);
+            extra_env.push((ENV_ARTIFACT_PATH, artifact.to_string_lossy().into_owned()));
+        }
+        let extra_env: Vec<(&str, &str)> =
+            extra_env.iter().map(|(k, v)| (*k, v.as_str())).collect();
+        elevate_privileges(&args, &extra_env);
     }
 
     // Phase 2 (root): install binary and config
@@ -647,13 +881,16 @@ pub fn install_service(backend: &InitBackend, dry_run: bool, skip_init: bool) {
         );
     }
 
-    let (raw_pkg_name, description) = pkg_name_and_description().unwrap_or_else(|| {
-        eprintln!("{C_YELLOW}[WARN]{C_RESET} Using fallback metadata (could not read Cargo.toml).");
+    let (raw_pkg_name, description) = pkg_name_and_description_at(&package_toml).unwrap_or_else(|| {
+        eprintln!(
+            "{C_YELLOW}[WARN]{C_RESET} Using fallback metadata (could not read {}).",
+            package_toml.display()
+        );
         ("corky".to_string(), "corky service".to_string())
     });
 
     let service_name = ensure_corky_prefix(&raw_pkg_name);
-    let target_bin = Path::new("target").join("release").join(&raw_pkg_name);
+    let target_bin = source_binary(source, archive.as_ref(), &build, &raw_pkg_name);
     let install_bin = Path::new(BIN_PATH_SYSTEM).join(&raw_pkg_name);
 
     // Verify binary integrity (TOCTOU protection)
@@ -673,6 +910,10 @@ pub fn install_service(backend: &InitBackend, dry_run: bool, skip_init: bool) {
         }
     }
 
+    // Leave an identical running binary alone so the service isn't stopped
+    let previous = load_manifest(&service_name);
+    let binary_current = !dry_run && same_file_contents(&target_bin, &install_bin);
+
     // Install binary
     section("Installing binary");
     if dry_run {
@@ -681,6 +922,11 @@ pub fn install_service(backend: &InitBackend, dry_run: bool, skip_init: bool) {
             target_bin.display(),
             install_bin.display()
         );
+    } else if binary_current && !force {
+        println!(
+            "{C_GREEN}[INFO]{C_RESET} {} is unchanged.",
+            install_bin.display()
+        );
     } else {
         let data = fs::read(&target_bin).unwrap_or_else(|e| {
             eprintln!(
@@ -758,74 +1004,268 @@ pub fn install_service(backend: &InitBackend, dry_run: bool, skip_init: bool) {
         std::process::exit(1);
     }
 
-    let user = installing_user();
-    let group = primary_group_for_user(&user);
+    let identity = resolve_service_user(&user_spec, backend, &service_name, dry_run);
+    let managed_dirs = config.managed_dirs.unwrap_or(true);
+    let working_dir = if managed_dirs {
+        state_dir(&service_name)
+    } else {
+        warn_unused_assets(&config.assets);
+        cwd.clone()
+    };
 
-    match backend {
-        InitBackend::Systemd { .. } => {
-            install_systemd_config(
-                dry_run,
-                skip_init,
-                &service_name,
-                &description,
-                &install_bin,
-                &cwd,
-                &user,
-                &group,
-            );
+    if *backend == InitBackend::Supervisor && config.hardening != HardeningPreset::None {
+        eprintln!(
+            "{C_YELLOW}[WARN]{C_RESET} hardening = \"{}\" is ignored: supervisor has no sandboxing.",
+            config.hardening
+        );
+    }
+    if *backend == InitBackend::Supervisor {
+        let (_, unenforced) = supervisor_limit_wrapper(
+            &config.limits,
+            &identity.user,
+            &identity.group,
+            WrapperTools::probe(),
+        );
+        warn_unenforced_limits(&unenforced);
+    }
+
+    let mut manifest = manifest_from_config(
+        &config,
+        backend,
+        &service_name,
+        &description,
+        install_bin.clone(),
+        working_dir,
+        identity,
+    );
+    if *backend == InitBackend::Supervisor && manifest.watchdog_sec > 0 {
+        eprintln!(
+            "{C_YELLOW}[WARN]{C_RESET} Supervisor has no watchdog; watchdog_sec is only enforced under systemd."
+        );
+    }
+    // Reinstalling a template keeps the instances added since
+    if let Some(previous) = load_manifest(&service_name).filter(|m| m.template && config.template) {
+        manifest.instances = previous.instances;
+        manifest.instance_autostart = previous.instance_autostart;
+    }
+    manifest.checksum = checksum.clone();
+    // Assets aren't part of the up-to-date check, so always deploy them
+    if managed_dirs {
+        prepare_service_dirs(&manifest, dry_run);
+        let assets_root = archive.as_ref().map_or_else(|| cwd.clone(), |a| a.assets_dir());
+        install_assets(&config.assets, &assets_root, &manifest, dry_run);
+    }
+    if binary_current
+        && !force
+        && previous.as_ref() == Some(&manifest)
+        && config_is_current(backend, &manifest)
+    {
+        println!(
+            "{C_BGREEN}[OK]{C_RESET} {} is up to date; nothing else to do (use --force to reinstall).",
+            service_name
+        );
+        return;
+    }
+    record_manifest(dry_run, &manifest);
+
+    let previous_mode = previous
+        .as_ref()
+        .filter(|m| m.backend == manifest.backend)
+        .map(InstallMode::of);
+    for stale in stale_modes(backend, previous_mode, InstallMode::of(&manifest)) {
+        remove_stale_mode(stale, dry_run, skip_init, &service_name);
+    }
+
+    match (backend, &manifest.schedule) {
+        _ if manifest.template => install_template(backend, dry_run, skip_init, &manifest),
+        (InitBackend::Systemd { .. }, Some(schedule)) => {
+            install_systemd_timer(dry_run, skip_init, &manifest, schedule)
         }
-        InitBackend::Supervisor => {
-            install_supervisor_config(
-                dry_run,
-                skip_init,
-                &service_name,
-                &description,
-                &install_bin,
-                &cwd,
-                &user,
-            );
+        (InitBackend::Supervisor, Some(schedule)) => install_cron_job(dry_run, &manifest, schedule),
+        (InitBackend::Systemd { .. }, None) if !manifest.listen.is_empty() => {
+            install_systemd_socket(dry_run, skip_init, &manifest)
         }
+        (InitBackend::Systemd { .. }, None) => install_systemd_config(dry_run, skip_init, &manifest),
+        (InitBackend::Supervisor, None) => install_supervisor_config(dry_run, skip_init, &manifest),
+    }
+
+    // systemctl start leaves a running service on its old unit
+    if binary_current
+        && !force
+        && !skip_init
+        && !manifest.template
+        && manifest.schedule.is_none()
+        && matches!(backend, InitBackend::Systemd { .. })
+    {
+        println!(
+            "{C_GREEN}[INFO]{C_RESET} Binary unchanged, so {} was not restarted. Apply the new config with: corky restart --if-changed {}",
+            service_name, service_name
+        );
     }
 }
 
-fn install_systemd_config(
-    dry_run: bool,
-    skip_init: bool,
+/// How a manifest gets installed; mirrors the dispatch in `install_service`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InstallMode {
+    Service,
+    Scheduled,
+    SocketActivated,
+    Template,
+}
+
+impl InstallMode {
+    pub fn of(manifest: &InstallManifest) -> InstallMode {
+        if manifest.template {
+            InstallMode::Template
+        } else if manifest.schedule.is_some() {
+            InstallMode::Scheduled
+        } else if !manifest.listen.is_empty() {
+            InstallMode::SocketActivated
+        } else {
+            InstallMode::Service
+        }
+    }
+}
+
+/// Something a previous install left behind that the new mode doesn't use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum StaleMode {
+    /// The .timer or cron.d entry
+    Schedule,
+    /// The .socket, which would keep holding the port
+    Socket,
+    /// The supervisor program, which would keep running next to cron
+    SupervisorProgram,
+    /// The enabled, running systemd service, now started by its timer
+    ServiceEnablement,
+}
+
+/// What to tear down when switching to `new`. Schedules and sockets are
+/// checked on disk whatever `previous` was (their removal is a no-op when
+/// absent); the long-running service is only known from the old manifest.
+fn stale_modes(backend: &InitBackend, previous: Option<InstallMode>, new: InstallMode) -> Vec<StaleMode> {
+    let mut stale = Vec::new();
+    if new != InstallMode::Scheduled {
+        stale.push(StaleMode::Schedule);
+    }
+    if new != InstallMode::SocketActivated {
+        stale.push(StaleMode::Socket);
+    }
+    if new == InstallMode::Scheduled && previous == Some(InstallMode::Service) {
+        stale.push(match backend {
+            InitBackend::Systemd { .. } => StaleMode::ServiceEnablement,
+            InitBackend::Supervisor => StaleMode::SupervisorProgram,
+        });
+    }
+    stale
+}
+
+fn remove_stale_mode(stale: StaleMode, dry_run: bool, skip_init: bool, service_name: &str) {
+    match stale {
+        StaleMode::Schedule => remove_schedule(dry_run, skip_init, service_name),
+        StaleMode::Socket => remove_socket(dry_run, skip_init, service_name),
+        StaleMode::SupervisorProgram => remove_supervisor_program(dry_run, skip_init, service_name),
+        StaleMode::ServiceEnablement => {
+            let unit = format!("{}.service", service_name);
+            if dry_run {
+                println!("{C_CYAN}[DRY-RUN]{C_RESET} Would run: systemctl disable --now {}", unit);
+            } else if skip_init {
+                println!("{C_YELLOW}[SKIP]{C_RESET} systemctl disable --now {}", unit);
+            } else {
+                section("Disabling long-running service");
+                let _ = run_cmd("systemctl", &["disable", "--now", &unit]);
+            }
+        }
+    }
+}
+
+/// What `[corky]` describes for a service running `exec_path` as `identity`,
+/// before instances and the binary checksum are filled in.
+pub fn manifest_from_config(
+    config: &CorkyConfig,
+    backend: &InitBackend,
     service_name: &str,
     description: &str,
-    install_bin: &Path,
-    cwd: &Path,
-    user: &str,
-    group: &str,
-) {
-    let unit_path = Path::new(UNIT_DIR_SYSTEM).join(format!("{}.service", service_name));
+    exec_path: PathBuf,
+    working_dir: PathBuf,
+    identity: ServiceUser,
+) -> InstallManifest {
+    let watchdog_sec = match config.ready {
+        ReadyMode::Notify => config.watchdog_sec.unwrap_or(DEFAULT_WATCHDOG_SEC),
+        ReadyMode::Simple => 0,
+    };
+    let reload_signal =
+        normalize_signal(config.reload_signal.as_deref().unwrap_or(DEFAULT_RELOAD_SIGNAL))
+            .unwrap_or_else(|e| exit_error(&format!("[corky] reload_signal: {}", e)));
+    InstallManifest {
+        service_name: service_name.to_string(),
+        description: description.to_string(),
+        backend: backend.to_string(),
+        exec_path,
+        args: Vec::new(),
+        working_dir,
+        managed_dirs: config.managed_dirs.unwrap_or(true),
+        user: identity.user,
+        group: identity.group,
+        dynamic_user: identity.dynamic,
+        created_user: identity.created,
+        environment: BTreeMap::new(),
+        autostart: true,
+        hardening: config.hardening,
+        hardening_overrides: config.hardening_overrides.clone(),
+        limits: config.limits.clone(),
+        schedule: config.schedule.clone(),
+        listen: config.listen.clone(),
+        template: config.template,
+        instances: Vec::new(),
+        instance_autostart: BTreeMap::new(),
+        ready: config.ready,
+        watchdog_sec,
+        reload_signal,
+        checksum: String::new(),
+    }
+}
 
-    section("Writing systemd unit");
-    let unit_contents = format!(
-        r#"[Unit]
-Description={description}
-Wants=network-online.target
-After=network-online.target
+/// The file `install_service` installs for `source`.
+fn source_binary(
+    source: &InstallSource,
+    archive: Option<&ExtractedArchive>,
+    build: &BuildOptions,
+    pkg_name: &str,
+) -> PathBuf {
+    match (source, archive) {
+        (InstallSource::Artifact(path), _) => path.clone(),
+        (_, Some(archive)) => archive.binary_path(pkg_name),
+        _ => build.elevated_artifact_path(pkg_name),
+    }
+}
 
-[Service]
-User={user}
-Group={group}
-WorkingDirectory={workdir}
-ExecStart={exec_path}
-ExecStartPre=/usr/bin/test -x {exec_path}
-Restart=on-failure
-RestartSec=1
-ProtectHome=no
+fn record_manifest(dry_run: bool, manifest: &InstallManifest) {
+    if dry_run {
+        println!(
+            "{C_CYAN}[DRY-RUN]{C_RESET} Would write manifest: {}",
+            manifest_path(&manifest.service_name).display()
+        );
+        return;
+    }
+    match write_manifest(manifest) {
+        Ok(path) => println!("{C_GREEN}[INFO]{C_RESET} Wrote manifest: {}", path.display()),
+        Err(e) => eprintln!(
+            "{C_YELLOW}[WARN]{C_RESET} Could not write install manifest: {}",
+            e
+        ),
+    }
+}
 
-[Install]
-WantedBy=multi-user.target
-"#,
-        description = description,
-        user = user,
-        group = group,
-        workdir = cwd.display(),
-        exec_path = install_bin.display(),
-    );
+fn install_systemd_config(dry_run: bool, skip_init: bool, manifest: &InstallManifest) {
+    let service_name = manifest.service_name.as_str();
+    let install_bin = manifest.exec_path.as_path();
+    let cwd = manifest.working_dir.as_path();
+    let unit_path = systemd_unit_path(service_name);
+
+    section("Writing systemd unit");
+    let unit_contents = render_systemd_unit(manifest);
+    show_config_changes(&unit_path, &unit_contents, service_name);
 
     if dry_run {
         println!(
@@ -882,12 +1322,22 @@ WantedBy=multi-user.target
     } else {
         run_cmd_expect_ok("systemctl", &["daemon-reload"]);
         run_cmd_expect_ok("systemctl", &["enable", service_name]);
+        if manifest.ready == ReadyMode::Notify {
+            // Type=notify: systemctl start returns once the service sends READY=1
+            println!("{C_GREEN}[INFO]{C_RESET} Waiting for {} to report READY=1...", service_name);
+        }
         let ok = run_cmd("systemctl", &["start", service_name]);
         if !ok {
             eprintln!(
                 "{C_RED}[ERROR]{C_RESET} Failed to start service. Try: systemctl status {}",
                 service_name
             );
+            print_failure_context(&ServiceInfo {
+                name: service_name.to_string(),
+                backend: InitBackend::Systemd {
+                    scope: "system".to_string(),
+                },
+            });
             std::process::exit(1);
         }
     }
@@ -917,19 +1367,14 @@ WantedBy=multi-user.target
     }
 }
 
-fn install_supervisor_config(
-    dry_run: bool,
-    skip_init: bool,
-    service_name: &str,
-    description: &str,
-    install_bin: &Path,
-    cwd: &Path,
-    user: &str,
-) {
+fn install_supervisor_config(dry_run: bool, skip_init: bool, manifest: &InstallManifest) {
+    let service_name = manifest.service_name.as_str();
+    let install_bin = manifest.exec_path.as_path();
     let conf_path = supervisor_conf_path(service_name);
 
     section("Writing supervisor config");
-    let conf_contents = generate_supervisor_conf(service_name, description, install_bin, cwd, user);
+    let conf_contents = generate_supervisor_conf(manifest);
+    show_config_changes(&conf_path, &conf_contents, service_name);
 
     if dry_run {
         println!(
@@ -938,7 +1383,7 @@ fn install_supervisor_config(
         );
         println!(
             "---------- supervisor config ----------\n{}\n---------------------------------------",
-            conf_contents
+            redact_config(&conf_contents, service_name)
         );
     } else {
         fs::create_dir_all(SUPERVISOR_CONF_DIR).unwrap_or_else(|e| {
@@ -948,7 +1393,7 @@ fn install_supervisor_config(
             );
             std::process::exit(1);
         });
-        fs::write(&conf_path, conf_contents).unwrap_or_else(|e| {
+        write_config_file(&InitBackend::Supervisor, &conf_path, &conf_contents, service_name).unwrap_or_else(|e| {
             eprintln!(
                 "{C_RED}[ERROR]{C_RESET} write {}: {}",
                 conf_path.display(),
@@ -984,6 +1429,8 @@ fn install_supervisor_config(
             service_name
         );
     } else {
+        let wait_ready =
+            manifest.ready == ReadyMode::Notify && can_wait_for_ready(service_name);
         run_cmd_expect_ok("supervisorctl", &["reread"]);
         run_cmd_expect_ok("supervisorctl", &["update"]);
         // After update, supervisor may auto-start the service (autostart=true).
@@ -995,6 +1442,13 @@ fn install_supervisor_config(
                 "{C_YELLOW}[WARN]{C_RESET} supervisorctl start returned non-zero (may already be running)"
             );
         }
+        if wait_ready && !wait_until_ready(service_name) {
+            print_failure_context(&ServiceInfo {
+                name: service_name.to_string(),
+                backend: InitBackend::Supervisor,
+            });
+            std::process::exit(1);
+        }
     }
 
     section("Done");
@@ -1022,10 +1476,13 @@ fn install_supervisor_config(
     }
 }
 
-pub fn uninstall_service(backend: &InitBackend, dry_run: bool, skip_init: bool) {
+pub fn uninstall_service(backend: &InitBackend, dry_run: bool, skip_init: bool, remove_user: bool) {
     // Elevate if needed
     if !is_root() {
-        println!("{C_GREEN}[INFO]{C_RESET} Elevating with sudo to uninstall service...");
+        println!(
+            "{C_GREEN}[INFO]{C_RESET} Elevating with {} to uninstall service...",
+            escalator_or_exit()
+        );
         let args: Vec<String> = env::args().skip(1).collect();
         let backend_str = backend.to_string();
         elevate_privileges(&args, &[(ENV_INIT_BACKEND, &backend_str)]);
@@ -1040,7 +1497,13 @@ pub fn uninstall_service(backend: &InitBackend, dry_run: bool, skip_init: bool)
         .unwrap_or_else(|| ("corky".to_string(), "corky service".to_string()));
     let service_name = ensure_corky_prefix(&raw_pkg_name);
     let bin_path = Path::new(BIN_PATH_SYSTEM).join(&raw_pkg_name);
+    let manifest = load_manifest(&service_name);
 
+    remove_schedule(dry_run, skip_init, &service_name);
+    remove_socket(dry_run, skip_init, &service_name);
+    if let Some(m) = manifest.as_ref().filter(|m| m.template) {
+        stop_instances(backend, dry_run, skip_init, m);
+    }
     match backend {
         InitBackend::Systemd { .. } => {
             uninstall_systemd(dry_run, skip_init, &service_name, &bin_path);
@@ -1049,10 +1512,40 @@ pub fn uninstall_service(backend: &InitBackend, dry_run: bool, skip_init: bool)
             uninstall_supervisor(dry_run, skip_init, &service_name, &bin_path);
         }
     }
+
+    // Only accounts corky created itself are ever removed
+    match &manifest {
+        Some(m) if m.created_user && remove_user => remove_system_user(&m.user, dry_run),
+        Some(m) if m.created_user => println!(
+            "{C_GREEN}[INFO]{C_RESET} Kept service user {}; remove it with `corky uninstall --remove-user`.",
+            m.user
+        ),
+        _ if remove_user => eprintln!(
+            "{C_YELLOW}[WARN]{C_RESET} No corky-created user recorded for {}; nothing removed.",
+            service_name
+        ),
+        _ => {}
+    }
+    if manifest.is_some_and(|m| m.managed_dirs) {
+        let dirs: Vec<String> = managed_dir_env(&service_name)
+            .iter()
+            .map(|(_, dir)| dir.display().to_string())
+            .collect();
+        println!("{C_GREEN}[INFO]{C_RESET} Kept service data in {}", dirs.join(", "));
+    }
+
+    section("Done");
+    println!(
+        "{C_BGREEN}[SUCCESS]{C_RESET} {} uninstalled.",
+        service_name
+    );
 }
 
 fn uninstall_systemd(dry_run: bool, skip_init: bool, service_name: &str, bin_path: &Path) {
-    let unit_path = Path::new(UNIT_DIR_SYSTEM).join(format!("{}.service", service_name));
+    let unit_path = match load_manifest(service_name) {
+        Some(m) if m.template => template_unit_path(service_name),
+        _ => systemd_unit_path(service_name),
+    };
 
     // Stop & disable
     section("Stopping & disabling");
@@ -1129,12 +1622,7 @@ fn uninstall_systemd(dry_run: bool, skip_init: bool, service_name: &str, bin_pat
 
     // Remove binary
     remove_binary(dry_run, bin_path);
-
-    section("Done");
-    println!(
-        "{C_BGREEN}[SUCCESS]{C_RESET} {} uninstalled.",
-        service_name
-    );
+    forget_manifest(dry_run, service_name);
 }
 
 fn uninstall_supervisor(dry_run: bool, skip_init: bool, service_name: &str, bin_path: &Path) {
@@ -1203,12 +1691,7 @@ fn uninstall_supervisor(dry_run: bool, skip_init: bool, service_name: &str, bin_
 
     // Remove binary
     remove_binary(dry_run, bin_path);
-
-    section("Done");
-    println!(
-        "{C_BGREEN}[SUCCESS]{C_RESET} {} uninstalled.",
-        service_name
-    );
+    forget_manifest(dry_run, service_name);
 }
 
 fn remove_binary(dry_run: bool, bin_path: &Path) {
@@ -1238,35 +1721,325 @@ fn remove_binary(dry_run: bool, bin_path: &Path) {
     }
 }
 
+fn forget_manifest(dry_run: bool, service_name: &str) {
+    if dry_run {
+        println!(
+            "{C_CYAN}[DRY-RUN]{C_RESET} Would remove: {}",
+            manifest_path(service_name).display()
+        );
+        return;
+    }
+    // Owned by the service user, who may be removed next
+    let _ = fs::remove_dir_all(ready_dir(service_name));
+    if let Err(e) = remove_manifest(service_name) {
+        eprintln!(
+            "{C_YELLOW}[WARN]{C_RESET} remove {}: {}",
+            manifest_path(service_name).display(),
+            e
+        );
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// systemd helpers
+// ─────────────────────────────────────────────────────────────────────────────
+
+pub fn systemd_unit_path(service_name: &str) -> PathBuf {
+    PathBuf::from(UNIT_DIR_SYSTEM).join(format!("{}.service", service_name))
+}
+
+/// Where systemd loaded a unit from (may be outside /etc for vendor units).
+pub fn systemd_fragment_path(service_name: &str) -> Option<PathBuf> {
+    let output = Command::new("systemctl")
+        .args(["show", "-p", "FragmentPath", "--value"])
+        .arg(format!("{}.service", service_name))
+        .output()
+        .ok()?;
+    let path = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if path.is_empty() { None } else { Some(PathBuf::from(path)) }
+}
+
+pub fn render_systemd_unit(manifest: &InstallManifest) -> String {
+    let mut environment = String::new();
+    for (key, value) in &manifest.environment {
+        environment.push_str(&format!(
+            "Environment={}\n",
+            systemd_quote(&format!("{}={}", key, value))
+        ));
+    }
+    let identity = if manifest.dynamic_user {
+        format!("User={}\nDynamicUser=yes\n", manifest.user)
+    } else {
+        format!("User={}\nGroup={}\n", manifest.user, manifest.group)
+    };
+    let directories = if manifest.managed_dirs {
+        // Relative to /var/lib, /etc and /var/log; systemd creates and chowns them
+        let dir = format!("corky/{}", manifest.service_name);
+        format!(
+            "StateDirectory={dir}\nConfigurationDirectory={dir}\nLogsDirectory={dir}\n"
+        )
+    } else {
+        String::new()
+    };
+    // Scheduled jobs run to completion and are started by their .timer;
+    // socket-activated services are started by their .socket
+    let restart = if manifest.schedule.is_some() {
+        "Type=oneshot\n".to_string()
+    } else {
+        render_exec_reload(&manifest.reload_signal)
+            + &render_notify_directives(manifest)
+            + "Restart=on-failure\nRestartSec=1\n"
+    };
+    let install = if manifest.schedule.is_some() || !manifest.listen.is_empty() {
+        ""
+    } else {
+        "\n[Install]\nWantedBy=multi-user.target\n"
+    };
+    // Instances add their own env file on top of the shared one
+    let (description, instance) = if manifest.template {
+        (
+            format!("{} (%i)", manifest.description),
+            format!(
+                "EnvironmentFile=-{}\nEnvironment=CORKY_INSTANCE=%i\n",
+                env_file_path(&instance_name(&manifest.service_name, "%i")).display()
+            ),
+        )
+    } else {
+        (manifest.description.clone(), String::new())
+    };
+    let mut exec_start = systemd_escape(&manifest.exec_path.display().to_string());
+    for arg in &manifest.args {
+        exec_start.push(' ');
+        exec_start.push_str(&systemd_quote(arg));
+    }
+
+    format!(
+        r#"[Unit]
+Description={description}
+Wants=network-online.target
+After=network-online.target
+
+[Service]
+{identity}WorkingDirectory={workdir}
+{directories}EnvironmentFile=-{env_file}
+{instance}{environment}ExecStart={exec_start}
+ExecStartPre=/usr/bin/test -x {exec_path}
+{restart}{limits}{hardening}{install}"#,
+        description = description,
+        identity = identity,
+        workdir = manifest.working_dir.display(),
+        directories = directories,
+        env_file = env_file_path(&manifest.service_name).display(),
+        instance = instance,
+        environment = environment,
+        exec_start = exec_start,
+        exec_path = manifest.exec_path.display(),
+        restart = restart,
+        install = install,
+        limits = render_limit_directives(&manifest.limits),
+        hardening = render_hardening(manifest),
+    )
+}
+
+/// Escape systemd specifier (`%`) and variable (`$`) expansion.
+fn systemd_escape(value: &str) -> String {
+    value.replace('%', "%%").replace('$', "$$")
+}
+
+/// Quote a single word for an ExecStart= or Environment= line.
+fn systemd_quote(value: &str) -> String {
+    let escaped = systemd_escape(value);
+    if !value.is_empty()
+        && !value
+            .chars()
+            .any(|c| c.is_whitespace() || c == '"' || c == '\'' || c == '\\')
+    {
+        return escaped;
+    }
+    format!("\"{}\"", escaped.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
 // ─────────────────────────────────────────────────────────────────────────────
 // Supervisor helpers
 // ─────────────────────────────────────────────────────────────────────────────
 
-fn supervisor_conf_path(service_name: &str) -> PathBuf {
+pub fn supervisor_conf_path(service_name: &str) -> PathBuf {
     PathBuf::from(SUPERVISOR_CONF_DIR).join(format!("{}.conf", service_name))
 }
 
-fn generate_supervisor_conf(
-    service_name: &str,
-    description: &str,
-    exec_path: &Path,
-    working_dir: &Path,
-    user: &str,
+/// The .conf holding `[program:<name>]`: corky's own path, or for adopted
+/// programs whichever file in the conf dir declares it.
+pub fn supervisor_conf_for(service_name: &str) -> PathBuf {
+    let own = supervisor_conf_path(service_name);
+    if own.exists() {
+        return own;
+    }
+    let header = format!("[program:{}]", service_name);
+    fs::read_dir(SUPERVISOR_CONF_DIR)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|e| e.path())
+        .find(|p| {
+            fs::read_to_string(p)
+                .map(|c| c.lines().any(|l| l.trim() == header))
+                .unwrap_or(false)
+        })
+        .unwrap_or(own)
+}
+
+/// Stop a program and drop its corky conf, e.g. when it becomes a cron job.
+fn remove_supervisor_program(dry_run: bool, skip_init: bool, service_name: &str) {
+    let conf_path = supervisor_conf_path(service_name);
+    if !conf_path.exists() {
+        return;
+    }
+    section("Removing supervisor program");
+    if dry_run {
+        println!("{C_CYAN}[DRY-RUN]{C_RESET} Would run: supervisorctl stop {}", service_name);
+        println!("{C_CYAN}[DRY-RUN]{C_RESET} Would remove: {}", conf_path.display());
+        return;
+    }
+    if !skip_init {
+        let _ = run_cmd("supervisorctl", &["stop", service_name]);
+    }
+    match fs::remove_file(&conf_path) {
+        Ok(()) => println!("{C_GREEN}[INFO]{C_RESET} Removed {}", conf_path.display()),
+        Err(e) => eprintln!("{C_YELLOW}[WARN]{C_RESET} remove {}: {}", conf_path.display(), e),
+    }
+    if !skip_init {
+        let _ = run_cmd("supervisorctl", &["reread"]);
+        let _ = run_cmd("supervisorctl", &["update"]);
+    }
+}
+
+pub fn supervisor_log_path(service_name: &str) -> PathBuf {
+    PathBuf::from(SUPERVISOR_LOG_DIR).join(format!("{}.log", service_name))
+}
+
+/// Where a supervisor conf or cron entry will run, which decides the
+/// host-specific parts: inlined env file values and limit wrapper tools.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenderHost {
+    /// This machine: inline its env files and probe its tools.
+    Local,
+    /// Another machine (a package): no env values, which `corky env set`
+    /// fills in there, and these tools assumed present.
+    Target(WrapperTools),
+}
+
+impl RenderHost {
+    pub fn env_file(self, service_name: &str) -> BTreeMap<String, String> {
+        match self {
+            RenderHost::Local => read_env_file(service_name),
+            RenderHost::Target(_) => BTreeMap::new(),
+        }
+    }
+
+    pub fn wrapper_tools(self) -> WrapperTools {
+        match self {
+            RenderHost::Local => WrapperTools::probe(),
+            RenderHost::Target(tools) => tools,
+        }
+    }
+}
+
+pub fn generate_supervisor_conf(manifest: &InstallManifest) -> String {
+    render_supervisor_conf(manifest, RenderHost::Local)
+}
+
+pub fn render_supervisor_conf(manifest: &InstallManifest, host: RenderHost) -> String {
+    let header = format!(
+        "; {}\n; Managed by corky CLI -- do not edit manually\n",
+        manifest.description
+    );
+    if !manifest.template {
+        return header
+            + &render_supervisor_program(manifest, &manifest.service_name, BTreeMap::new(), host);
+    }
+    // One program block per instance, each with its id and env file on top
+    if manifest.instances.is_empty() {
+        return header + "; No instances yet -- add one with `corky instance add`\n";
+    }
+    let programs: Vec<String> = manifest
+        .instances
+        .iter()
+        .map(|id| {
+            let program = instance_name(&manifest.service_name, id);
+            let mut extra = BTreeMap::from([("CORKY_INSTANCE".to_string(), id.clone())]);
+            extra.extend(host.env_file(&program));
+            render_supervisor_program(manifest, &program, extra, host)
+        })
+        .collect();
+    header + &programs.join("\n")
+}
+
+/// A `[program:<program>]` block; `extra_env` is applied last.
+fn render_supervisor_program(
+    manifest: &InstallManifest,
+    program: &str,
+    extra_env: BTreeMap<String, String>,
+    host: RenderHost,
 ) -> String {
+    let mut env = BTreeMap::from([("RUST_LOG_STYLE".to_string(), "always".to_string())]);
+    if manifest.managed_dirs {
+        for (key, dir) in managed_dir_env(&manifest.service_name) {
+            env.insert(key.to_string(), dir.display().to_string());
+        }
+    }
+    env.extend(manifest.environment.clone());
+    // supervisor has no env file support; inline it (it wins, as with systemd)
+    env.extend(host.env_file(&manifest.service_name));
+    env.extend(extra_env);
+    if manifest.ready == ReadyMode::Notify {
+        env.insert(
+            READY_FILE_ENV.to_string(),
+            ready_file_path(program).display().to_string(),
+        );
+    }
+    let environment = env
+        .iter()
+        .map(|(k, v)| format!("{}={}", k, supervisor_quote(v)))
+        .collect::<Vec<_>>()
+        .join(",");
+    // Limits are applied by a root wrapper chain that drops to the user itself
+    let (wrapper, _) = if manifest.limits.is_empty() {
+        (Vec::new(), Vec::new())
+    } else {
+        supervisor_limit_wrapper(
+            &manifest.limits,
+            &manifest.user,
+            &manifest.group,
+            host.wrapper_tools(),
+        )
+    };
+    let user_line = if wrapper.is_empty() {
+        format!("user={}\n", manifest.user)
+    } else {
+        String::new()
+    };
+    let mut command = wrapper
+        .iter()
+        .map(|w| supervisor_word(w))
+        .chain([supervisor_escape(&manifest.exec_path.display().to_string())])
+        .collect::<Vec<_>>()
+        .join(" ");
+    for arg in &manifest.args {
+        command.push(' ');
+        command.push_str(&supervisor_word(arg));
+    }
+
     format!(
-        r#"; {description}
-; Managed by corky CLI -- do not edit manually
-[program:{service_name}]
-command={exec_path}
+        r#"[program:{service_name}]
+command={command}
 directory={working_dir}
-user={user}
-environment=RUST_LOG_STYLE="always"
-autostart=true
+{user_line}environment={environment}
+autostart={autostart}
 autorestart=true
 startsecs=1
 startretries=3
 redirect_stderr=true
-stdout_logfile=/var/log/supervisor/{service_name}.log
+stdout_logfile={log_dir}/{service_name}.log
 stdout_logfile_maxbytes=10MB
 stdout_logfile_backups=5
 stopsignal=TERM
@@ -1274,45 +2047,118 @@ stopwaitsecs=10
 stopasgroup=true
 killasgroup=true
 "#,
-        description = description,
-        service_name = service_name,
-        exec_path = exec_path.display(),
-        working_dir = working_dir.display(),
-        user = user,
+        service_name = program,
+        command = command,
+        working_dir = manifest.working_dir.display(),
+        user_line = user_line,
+        environment = environment,
+        autostart = program_autostart(manifest, program),
+        log_dir = SUPERVISOR_LOG_DIR,
+    )
+}
+
+/// autostart= for `program`: an instance's own setting, else the service's.
+fn program_autostart(manifest: &InstallManifest, program: &str) -> bool {
+    split_instance(program)
+        .and_then(|(_, id)| manifest.instance_autostart.get(id).copied())
+        .unwrap_or(manifest.autostart)
+}
+
+/// Escape supervisor's `%(name)s` interpolation.
+fn supervisor_escape(value: &str) -> String {
+    value.replace('%', "%%")
+}
+
+/// Double-quote a value for supervisor's `environment=` (parsed with shlex).
+fn supervisor_quote(value: &str) -> String {
+    format!(
+        "\"{}\"",
+        supervisor_escape(value).replace('\\', "\\\\").replace('"', "\\\"")
     )
 }
 
-/// Toggle autostart= in a supervisor .conf file.
-fn set_supervisor_autostart(conf_path: &Path, enabled: bool) {
+/// Quote a `command=` word only when it needs it.
+fn supervisor_word(value: &str) -> String {
+    if !value.is_empty()
+        && !value
+            .chars()
+            .any(|c| c.is_whitespace() || c == '"' || c == '\'' || c == '\\' || c == ',')
+    {
+        supervisor_escape(value)
+    } else {
+        supervisor_quote(value)
+    }
+}
+
+/// Toggle autostart= for `[program:<service_name>]` in a supervisor .conf
+/// file, leaving other programs in a shared file alone, and record it in the
+/// manifest so `corky diff` and reinstalls keep it.
+fn set_supervisor_autostart(conf_path: &Path, service_name: &str, enabled: bool) {
     let content = fs::read_to_string(conf_path).unwrap_or_else(|e| {
         exit_error(&format!("Read {}: {}", conf_path.display(), e));
     });
 
-    let value = if enabled { "true" } else { "false" };
+    write_config_file(
+        &InitBackend::Supervisor,
+        conf_path,
+        &toggle_autostart(&content, service_name, enabled),
+        service_name,
+    )
+    .unwrap_or_else(|e| {
+        exit_error(&format!("Write {}: {}", conf_path.display(), e));
+    });
+
+    let Some(mut manifest) = load_manifest(service_name) else {
+        return;
+    };
+    let previous = manifest.clone();
+    match split_instance(service_name) {
+        // Instances share their template's manifest; keep the template's default
+        Some((_, id)) if manifest.service_name != service_name => {
+            manifest.instance_autostart.insert(id.to_string(), enabled);
+        }
+        _ => manifest.autostart = enabled,
+    }
+    if manifest != previous
+        && let Err(e) = write_manifest(&manifest)
+    {
+        eprintln!("{C_YELLOW}[WARN]{C_RESET} Could not write install manifest: {}", e);
+    }
+}
+
+fn toggle_autostart(content: &str, service_name: &str, enabled: bool) -> String {
+    let header = format!("[program:{}]", service_name);
+    let line = format!("autostart={}", enabled);
+    let mut out: Vec<String> = Vec::new();
+    let mut in_program = false;
     let mut found = false;
-    let new_content: String = content
-        .lines()
-        .map(|line| {
-            let trimmed = line.trim();
-            if trimmed.starts_with("autostart") && trimmed.contains('=') {
+    for current in content.lines() {
+        let trimmed = current.trim();
+        if trimmed.starts_with('[') {
+            if in_program && !found {
+                insert_before_blank_lines(&mut out, &line);
                 found = true;
-                format!("autostart={}", value)
-            } else {
-                line.to_string()
             }
-        })
-        .collect::<Vec<_>>()
-        .join("\n");
-
-    let final_content = if found {
-        format!("{}\n", new_content)
-    } else {
-        format!("{}\nautostart={}\n", new_content.trim_end(), value)
-    };
+            in_program = trimmed == header;
+        } else if in_program && trimmed.starts_with("autostart") && trimmed.contains('=') {
+            out.push(line.clone());
+            found = true;
+            continue;
+        }
+        out.push(current.to_string());
+    }
+    if in_program && !found {
+        insert_before_blank_lines(&mut out, &line);
+    }
+    let mut result = out.join("\n");
+    result.push('\n');
+    result
+}
 
-    fs::write(conf_path, final_content).unwrap_or_else(|e| {
-        exit_error(&format!("Write {}: {}", conf_path.display(), e));
-    });
+/// Add `line` at the end of the last section, before trailing blank lines.
+fn insert_before_blank_lines(out: &mut Vec<String>, line: &str) {
+    let at = out.iter().rposition(|l| !l.trim().is_empty()).map_or(0, |i| i + 1);
+    out.insert(at, line.to_string());
 }
 
 // ─────────────────────────────────────────────────────────────────────────────
@@ -1338,7 +2184,7 @@ impl std::str::FromStr for ServiceName {
             _ => {
                 if !is_valid_service_name(s) {
                     return Err(format!(
-                        "Invalid service name '{}'. Only alphanumeric characters, underscores, and hyphens are allowed.",
+                        "Invalid service name '{}'. Only alphanumeric characters, underscores, and hyphens are allowed, plus an optional @instance.",
                         s
                     ));
                 }
@@ -1359,8 +2205,15 @@ impl std::fmt::Display for ServiceName {
     }
 }
 
+/// `name` or `name@instance`.
 fn is_valid_service_name(name: &str) -> bool {
-    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    let valid = |part: &str| {
+        !part.is_empty() && part.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    };
+    match split_instance(name) {
+        Some((base, id)) => valid(base) && is_valid_instance_id(id),
+        None => valid(name),
+    }
 }
 
 pub fn resolve_service(backend: &InitBackend, arg: Option<ServiceName>) -> ServiceInfo {
@@ -1390,10 +2243,16 @@ pub fn resolve_service(backend: &InitBackend, arg: Option<ServiceName>) -> Servi
         Some(ServiceName::Interactive) => interactive_select_service(&services),
         Some(ServiceName::Custom(name)) => {
             let name_with_prefix = ensure_corky_prefix(&name);
-            let matches: Vec<_> = services
-                .iter()
-                .filter(|s| s.name == name_with_prefix)
-                .collect();
+            // Adopted services keep their original name, so accept an exact match too
+            let exact: Vec<_> = services.iter().filter(|s| s.name == name).collect();
+            let matches: Vec<_> = if exact.is_empty() {
+                services
+                    .iter()
+                    .filter(|s| s.name == name_with_prefix)
+                    .collect()
+            } else {
+                exact
+            };
 
             if matches.is_empty() {
                 eprintln!(
@@ -1470,64 +2329,36 @@ pub fn is_root() -> bool {
     }
 }
 
-pub fn ensure_sudo_timestamp() {
-    if is_root() {
-        return;
-    }
-    let _ = Command::new(SUDO_BIN)
-        .arg("-v")
-        .stdin(Stdio::inherit())
-        .stdout(Stdio::inherit())
-        .stderr(Stdio::inherit())
-        .status();
-}
-
 pub fn elevate_privileges(args: &[String], extra_env: &[(&str, &str)]) -> ! {
     if env::var_os(ENV_ELEVATED_FLAG).is_some() {
         eprintln!("{C_RED}Elevation loop detected; aborting.{C_RESET}");
         std::process::exit(1);
     }
-    ensure_sudo_timestamp();
+    let escalator = escalator_or_exit();
+    escalator.refresh_credentials();
     let exe = env::current_exe().expect("Failed to get current executable path");
-
     let cwd = env::current_dir().ok();
-    let cwd_str = cwd.as_ref().map(|p| p.to_string_lossy().to_string());
-
-    // Pass env vars as KEY=VALUE arguments to sudo (before the command).
-    // This is more reliable than cmd.env() because sudo's default env_reset
-    // policy strips inherited environment variables.
-    let mut cmd = Command::new(SUDO_BIN);
-
-    cmd.arg(format!("{}=1", ENV_ELEVATED_FLAG));
-
-    if let Some(ref dir) = cwd_str {
-        cmd.arg(format!("{}={}", ENV_ORIGINAL_CWD, dir));
-    }
-
-    for (key, value) in extra_env {
-        cmd.arg(format!("{}={}", key, value));
-    }
-
-    cmd.arg(exe.as_os_str())
-        .args(args)
-        .stdin(Stdio::inherit())
-        .stdout(Stdio::inherit())
-        .stderr(Stdio::inherit());
-
-    if let Some(dir) = cwd {
-        let _ = cmd.current_dir(dir);
-    }
 
-    let status = cmd.status().expect("Failed to execute sudo re-exec");
+    // Escalators reset the environment, so variables are handed over the way
+    // each one accepts them (sudo's KEY=VALUE words, env(1), run0 --setenv=)
+    let base_env = elevation_env();
+    let vars: Vec<(&str, &str)> = base_env
+        .iter()
+        .map(|(k, v)| (*k, v.as_str()))
+        .chain(extra_env.iter().copied())
+        .collect();
+    let status = escalator
+        .command(&exe, args, &vars, cwd.as_deref(), true)
+        .status()
+        .unwrap_or_else(|e| exit_error(&format!("Failed to run {}: {}", escalator, e)));
     std::process::exit(status.code().unwrap_or(1));
 }
 
-fn elevate_if_needed(service_info: &ServiceInfo) {
+pub fn elevate_if_needed(service_info: &ServiceInfo) {
     let backend_str = service_info.backend.to_string();
     match &service_info.backend {
         InitBackend::Systemd { scope } => {
             if scope == "system" && !is_root() {
-                ensure_sudo_timestamp();
                 let args: Vec<String> = env::args().skip(1).collect();
                 elevate_privileges(&args, &[(ENV_INIT_BACKEND, &backend_str)]);
             }
@@ -1535,7 +2366,6 @@ fn elevate_if_needed(service_info: &ServiceInfo) {
         InitBackend::Supervisor => {
             // Supervisor: check if we can access the socket
             if !is_root() && !run_cmd_quiet("supervisorctl", &["pid"]) {
-                ensure_sudo_timestamp();
                 let args: Vec<String> = env::args().skip(1).collect();
                 elevate_privileges(&args, &[(ENV_INIT_BACKEND, &backend_str)]);
             }
@@ -1543,6 +2373,15 @@ fn elevate_if_needed(service_info: &ServiceInfo) {
     }
 }
 
+/// Re-run the current command as root unless it already is.
+pub fn ensure_root(backend: &InitBackend) {
+    if !is_root() {
+        let args: Vec<String> = env::args().skip(1).collect();
+        let backend_str = backend.to_string();
+        elevate_privileges(&args, &[(ENV_INIT_BACKEND, &backend_str)]);
+    }
+}
+
 // ─────────────────────────────────────────────────────────────────────────────
 // Generic helpers
 // ─────────────────────────────────────────────────────────────────────────────
@@ -1552,6 +2391,25 @@ pub fn exit_error(msg: &str) -> ! {
     std::process::exit(1);
 }
 
+/// A fresh, randomly named 0700 directory under the temp dir. A single
+/// mkdir creates it, so an existing path or planted symlink is never reused.
+pub fn private_temp_dir(prefix: &str) -> io::Result<PathBuf> {
+    for _ in 0..16 {
+        let mut bytes = [0u8; 8];
+        fs::File::open("/dev/urandom")?.read_exact(&mut bytes)?;
+        let suffix: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+        let dir = env::temp_dir().join(format!("{}-{}", prefix, suffix));
+        match fs::DirBuilder::new().mode(0o700).create(&dir) {
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            result => return result.map(|()| dir),
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::AlreadyExists,
+        format!("no free {}-* name in {}", prefix, env::temp_dir().display()),
+    ))
+}
+
 pub fn ensure_corky_prefix(name: &str) -> String {
     if name.starts_with("corky-") {
         name.to_string()
@@ -1573,7 +2431,16 @@ pub fn section(title: &str) {
     );
 }
 
-fn run_cmd(cmd: &str, args: &[&str]) -> bool {
+/// `systemctl`/`journalctl` with `--user` added for user-scope services.
+pub fn scoped_command(program: &str, scope: &str) -> Command {
+    let mut cmd = Command::new(program);
+    if scope == "user" {
+        cmd.arg("--user");
+    }
+    cmd
+}
+
+pub fn run_cmd(cmd: &str, args: &[&str]) -> bool {
     let status = Command::new(cmd)
         .args(args)
         .stdin(Stdio::inherit())
@@ -1592,7 +2459,7 @@ fn run_cmd(cmd: &str, args: &[&str]) -> bool {
     }
 }
 
-fn run_cmd_quiet(cmd: &str, args: &[&str]) -> bool {
+pub fn run_cmd_quiet(cmd: &str, args: &[&str]) -> bool {
     let status = Command::new(cmd)
         .args(args)
         .stdin(Stdio::null())
@@ -1602,7 +2469,7 @@ fn run_cmd_quiet(cmd: &str, args: &[&str]) -> bool {
     status.map(|s| s.success()).unwrap_or(false)
 }
 
-fn run_cmd_expect_ok(cmd: &str, args: &[&str]) {
+pub fn run_cmd_expect_ok(cmd: &str, args: &[&str]) {
     if !run_cmd(cmd, args) {
         eprintln!(
             "{C_RED}[ERROR]{C_RESET} Command failed: {} {:?}",
@@ -1623,20 +2490,25 @@ fn past_tense(action: &str) -> &str {
     }
 }
 
-fn validate_corky_package_or_exit() {
+pub fn validate_corky_package_or_exit() {
     let cargo_toml = Path::new("Cargo.toml");
     if !cargo_toml.exists() {
         exit_error("No Cargo.toml found in the current directory.");
     }
+    validate_corky_package_at(cargo_toml);
+}
 
+/// Like `validate_corky_package_or_exit`, for a Cargo.toml-shaped file
+/// elsewhere (e.g. the corky.toml inside a release archive).
+pub fn validate_corky_package_at(cargo_toml: &Path) {
     let content = match fs::read_to_string(cargo_toml) {
         Ok(c) => c,
-        Err(e) => exit_error(&format!("Failed to read Cargo.toml: {}", e)),
+        Err(e) => exit_error(&format!("Failed to read {}: {}", cargo_toml.display(), e)),
     };
 
     let parsed: CargoToml = match toml::from_str(&content) {
         Ok(p) => p,
-        Err(e) => exit_error(&format!("Failed to parse Cargo.toml: {}", e)),
+        Err(e) => exit_error(&format!("Failed to parse {}: {}", cargo_toml.display(), e)),
     };
 
     let is_corky = parsed
@@ -1653,8 +2525,24 @@ fn validate_corky_package_or_exit() {
     }
 }
 
+/// The `[corky]` table of ./Cargo.toml, or defaults if it can't be read.
+pub fn corky_config() -> CorkyConfig {
+    corky_config_at(Path::new("Cargo.toml"))
+}
+
+pub fn corky_config_at(cargo_toml: &Path) -> CorkyConfig {
+    fs::read_to_string(cargo_toml)
+        .ok()
+        .and_then(|content| toml::from_str::<CargoToml>(&content).ok())
+        .and_then(|parsed| parsed.corky)
+        .unwrap_or_default()
+}
+
 pub fn pkg_name_and_description() -> Option<(String, String)> {
-    let cargo_toml = Path::new("Cargo.toml");
+    pkg_name_and_description_at(Path::new("Cargo.toml"))
+}
+
+pub fn pkg_name_and_description_at(cargo_toml: &Path) -> Option<(String, String)> {
     let content = fs::read_to_string(cargo_toml).ok()?;
     let parsed: CargoToml = toml::from_str(&content).ok()?;
 
@@ -1673,29 +2561,53 @@ pub fn pkg_name_and_description() -> Option<(String, String)> {
     Some((name, description))
 }
 
-fn installing_user() -> String {
+pub fn installing_user() -> String {
     if is_root() {
-        if let Ok(sudo_user) = env::var("SUDO_USER") {
-            if !sudo_user.is_empty() {
-                return sudo_user;
-            }
-        }
-        "root".to_string()
+        // CORKY_ORIGINAL_USER covers run0 and pkexec; doas sets DOAS_USER
+        [ENV_ORIGINAL_USER, "SUDO_USER", "DOAS_USER"]
+            .iter()
+            .filter_map(|var| env::var(var).ok())
+            .find(|user| !user.is_empty() && user != "root")
+            .unwrap_or_else(|| "root".to_string())
     } else {
         env::var("USER").unwrap_or_else(|_| "user".to_string())
     }
 }
 
-fn primary_group_for_user(username: &str) -> String {
-    if let Ok(output) = Command::new("id").args(["-gn", username]).output() {
-        if output.status.success() {
-            let group = String::from_utf8_lossy(&output.stdout).trim().to_string();
-            if !group.is_empty() {
-                return group;
-            }
+pub fn primary_group_for_user(username: &str) -> String {
+    lookup_primary_group(username).unwrap_or_else(|| username.to_string())
+}
+
+/// The user's primary group per `id -gn`, or None if it cannot be resolved.
+pub fn lookup_primary_group(username: &str) -> Option<String> {
+    let output = Command::new("id").args(["-gn", username]).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let group = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if group.is_empty() { None } else { Some(group) }
+}
+
+/// Locate an executable on PATH.
+pub fn find_in_path(program: &str) -> Option<PathBuf> {
+    let path = env::var_os("PATH")?;
+    env::split_paths(&path)
+        .map(|dir| dir.join(program))
+        .find(|candidate| {
+            fs::metadata(candidate)
+                .map(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+                .unwrap_or(false)
+        })
+}
+
+/// Whether both files exist and hold the same bytes.
+fn same_file_contents(a: &Path, b: &Path) -> bool {
+    match (fs::metadata(a), fs::metadata(b)) {
+        (Ok(ma), Ok(mb)) if ma.len() == mb.len() => {
+            matches!((fs::read(a), fs::read(b)), (Ok(x), Ok(y)) if x == y)
         }
+        _ => false,
     }
-    username.to_string()
 }
 
 pub fn compute_file_checksum(path: &Path) -> Option<String> {
@@ -1808,15 +2720,39 @@ mod tests {
         );
     }
 
+    fn test_manifest() -> InstallManifest {
+        InstallManifest {
+            service_name: "corky-test".to_string(),
+            description: "Test service".to_string(),
+            backend: "systemd".to_string(),
+            exec_path: PathBuf::from("/usr/local/bin/corky-test"),
+            args: Vec::new(),
+            working_dir: PathBuf::from("/opt/corky"),
+            managed_dirs: false,
+            user: "appuser".to_string(),
+            group: "appgroup".to_string(),
+            dynamic_user: false,
+            created_user: false,
+            environment: BTreeMap::new(),
+            autostart: true,
+            hardening: HardeningPreset::None,
+            hardening_overrides: BTreeMap::new(),
+            limits: ResourceLimits::default(),
+            schedule: None,
+            listen: Vec::new(),
+            template: false,
+            instances: Vec::new(),
+            instance_autostart: BTreeMap::new(),
+            ready: ReadyMode::Simple,
+            watchdog_sec: 0,
+            reload_signal: DEFAULT_RELOAD_SIGNAL.to_string(),
+            checksum: String::new(),
+        }
+    }
+
     #[test]
     fn test_generate_supervisor_conf() {
-        let conf = generate_supervisor_conf(
-            "corky-test",
-            "Test service",
-            Path::new("/usr/local/bin/corky-test"),
-            Path::new("/opt/corky"),
-            "appuser",
-        );
+        let conf = generate_supervisor_conf(&test_manifest());
         assert!(conf.contains("[program:corky-test]"));
         assert!(conf.contains("command=/usr/local/bin/corky-test"));
         assert!(conf.contains("directory=/opt/corky"));
@@ -1825,4 +2761,198 @@ mod tests {
         assert!(conf.contains("autorestart=true"));
         assert!(conf.contains("stdout_logfile=/var/log/supervisor/corky-test.log"));
     }
+
+    #[test]
+    fn test_generate_supervisor_conf_env_and_args() {
+        let mut manifest = test_manifest();
+        manifest.args = vec!["--port".to_string(), "80 80".to_string()];
+        manifest.environment.insert("TOKEN".to_string(), "a\"b%c".to_string());
+        manifest.autostart = false;
+        let conf = generate_supervisor_conf(&manifest);
+        assert!(conf.contains("command=/usr/local/bin/corky-test --port \"80 80\""));
+        assert!(conf.contains(r#"environment=RUST_LOG_STYLE="always",TOKEN="a\"b%%c""#));
+        assert!(conf.contains("autostart=false"));
+    }
+
+    #[test]
+    fn test_render_systemd_unit() {
+        let mut manifest = test_manifest();
+        let unit = render_systemd_unit(&manifest);
+        assert!(unit.contains("User=appuser\nGroup=appgroup\n"));
+        assert!(unit.contains(
+            "WorkingDirectory=/opt/corky\nEnvironmentFile=-/etc/corky/corky-test.env\nExecStart=/usr/local/bin/corky-test\n"
+        ));
+        assert!(!unit.contains("\nEnvironment="));
+        assert!(unit.contains("ExecReload=/bin/kill -HUP $MAINPID\n"));
+
+        manifest.args = vec!["--name".to_string(), "a b".to_string()];
+        manifest.environment.insert("PCT".to_string(), "50%".to_string());
+        let unit = render_systemd_unit(&manifest);
+        assert!(unit.contains("Environment=PCT=50%%\n"));
+        assert!(unit.contains("ExecStart=/usr/local/bin/corky-test --name \"a b\"\n"));
+    }
+
+    #[test]
+    fn test_managed_dirs_rendering() {
+        let mut manifest = test_manifest();
+        manifest.managed_dirs = true;
+        let unit = render_systemd_unit(&manifest);
+        assert!(unit.contains(
+            "StateDirectory=corky/corky-test\nConfigurationDirectory=corky/corky-test\nLogsDirectory=corky/corky-test\n"
+        ));
+        let conf = generate_supervisor_conf(&manifest);
+        assert!(conf.contains("STATE_DIRECTORY=\"/var/lib/corky/corky-test\""));
+    }
+
+    #[test]
+    fn test_render_systemd_unit_dynamic_user() {
+        let mut manifest = test_manifest();
+        manifest.user = "corky-test".to_string();
+        manifest.dynamic_user = true;
+        let unit = render_systemd_unit(&manifest);
+        assert!(unit.contains("User=corky-test\nDynamicUser=yes\nWorkingDirectory="));
+        assert!(!unit.contains("Group="));
+    }
+
+    #[test]
+    fn test_template_rendering() {
+        let mut manifest = test_manifest();
+        manifest.template = true;
+        let unit = render_systemd_unit(&manifest);
+        assert!(unit.contains("Description=Test service (%i)\n"));
+        assert!(unit.contains(
+            "EnvironmentFile=-/etc/corky/corky-test.env\n\
+             EnvironmentFile=-/etc/corky/corky-test@%i.env\n\
+             Environment=CORKY_INSTANCE=%i\n"
+        ));
+
+        assert!(!generate_supervisor_conf(&manifest).contains("[program:"));
+        manifest.instances = vec!["eu".to_string(), "us".to_string()];
+        let conf = generate_supervisor_conf(&manifest);
+        assert!(conf.contains("[program:corky-test@eu]"));
+        assert!(conf.contains("[program:corky-test@us]"));
+        assert!(conf.contains("CORKY_INSTANCE=\"eu\""));
+        assert!(conf.contains("stdout_logfile=/var/log/supervisor/corky-test@us.log"));
+
+        // Disabling one instance leaves the others on the template's default
+        manifest.instance_autostart.insert("us".to_string(), false);
+        let conf = generate_supervisor_conf(&manifest);
+        let (eu, us) = conf.split_once("[program:corky-test@us]").unwrap();
+        assert!(eu.contains("autostart=true"));
+        assert!(us.contains("autostart=false"));
+    }
+
+    #[test]
+    fn test_notify_rendering() {
+        let mut manifest = test_manifest();
+        manifest.ready = ReadyMode::Notify;
+        manifest.watchdog_sec = 30;
+        let unit = render_systemd_unit(&manifest);
+        assert!(unit.contains("Type=notify\nWatchdogSec=30\nRestart=on-failure\n"));
+        let conf = generate_supervisor_conf(&manifest);
+        assert!(conf.contains("CORKY_READY_FILE=\"/run/corky/corky-test/corky-test.ready\""));
+    }
+
+    #[test]
+    fn test_toggle_autostart() {
+        let conf = "[program:a]\ncommand=/bin/a\nautostart=true\n\n[program:b]\ncommand=/bin/b\nautostart=true\n";
+        assert_eq!(
+            toggle_autostart(conf, "b", false),
+            "[program:a]\ncommand=/bin/a\nautostart=true\n\n[program:b]\ncommand=/bin/b\nautostart=false\n"
+        );
+        let conf = "[program:a]\ncommand=/bin/a\n\n[program:b]\ncommand=/bin/b\n";
+        assert_eq!(
+            toggle_autostart(conf, "a", false),
+            "[program:a]\ncommand=/bin/a\nautostart=false\n\n[program:b]\ncommand=/bin/b\n"
+        );
+    }
+
+    #[test]
+    fn test_same_file_contents() {
+        let dir = private_temp_dir("corky-test").unwrap();
+        let (a, b, c) = (dir.join("a"), dir.join("b"), dir.join("c"));
+        // Same length and XOR fold, different bytes
+        fs::write(&a, [1u8, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0]).unwrap();
+        fs::write(&b, [0u8; 16]).unwrap();
+        fs::write(&c, [1u8, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0]).unwrap();
+        assert_eq!(compute_file_checksum(&a), compute_file_checksum(&b));
+        assert!(!same_file_contents(&a, &b));
+        assert!(same_file_contents(&a, &c));
+        assert!(!same_file_contents(&a, &dir.join("missing")));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_private_temp_dir() {
+        let a = private_temp_dir("corky-test").unwrap();
+        let b = private_temp_dir("corky-test").unwrap();
+        assert_ne!(a, b);
+        assert_eq!(fs::metadata(&a).unwrap().permissions().mode() & 0o777, 0o700);
+        let _ = fs::remove_dir(&a);
+        let _ = fs::remove_dir(&b);
+    }
+
+    #[test]
+    fn test_install_mode() {
+        let mut manifest = test_manifest();
+        assert_eq!(InstallMode::of(&manifest), InstallMode::Service);
+        manifest.listen = vec!["0.0.0.0:8080".to_string()];
+        assert_eq!(InstallMode::of(&manifest), InstallMode::SocketActivated);
+        manifest.listen.clear();
+        manifest.schedule = Some("daily".to_string());
+        assert_eq!(InstallMode::of(&manifest), InstallMode::Scheduled);
+        manifest.template = true;
+        assert_eq!(InstallMode::of(&manifest), InstallMode::Template);
+    }
+
+    #[test]
+    fn test_stale_modes() {
+        use InstallMode::*;
+        let systemd = InitBackend::Systemd {
+            scope: "system".to_string(),
+        };
+        let supervisor = InitBackend::Supervisor;
+        // Dropping schedule removes the timer / cron entry
+        assert_eq!(
+            stale_modes(&systemd, Some(Scheduled), Service),
+            [StaleMode::Schedule, StaleMode::Socket]
+        );
+        assert_eq!(
+            stale_modes(&supervisor, Some(Scheduled), Service),
+            [StaleMode::Schedule, StaleMode::Socket]
+        );
+        // Adding schedule stops the long-running service
+        assert_eq!(
+            stale_modes(&supervisor, Some(Service), Scheduled),
+            [StaleMode::Socket, StaleMode::SupervisorProgram]
+        );
+        assert_eq!(
+            stale_modes(&systemd, Some(Service), Scheduled),
+            [StaleMode::Socket, StaleMode::ServiceEnablement]
+        );
+        // Dropping listen removes the socket
+        assert_eq!(
+            stale_modes(&systemd, Some(SocketActivated), Service),
+            [StaleMode::Schedule, StaleMode::Socket]
+        );
+        assert_eq!(
+            stale_modes(&systemd, Some(SocketActivated), Scheduled),
+            [StaleMode::Socket]
+        );
+        // Staying in a mode keeps its own artifacts
+        assert_eq!(stale_modes(&systemd, Some(Scheduled), Scheduled), [StaleMode::Socket]);
+        assert_eq!(
+            stale_modes(&systemd, Some(SocketActivated), SocketActivated),
+            [StaleMode::Schedule]
+        );
+        assert_eq!(stale_modes(&supervisor, None, Scheduled), [StaleMode::Socket]);
+    }
+
+    #[test]
+    fn test_instance_service_names() {
+        assert!("worker@eu".parse::<ServiceName>().is_ok());
+        assert!("worker@".parse::<ServiceName>().is_err());
+        assert!("@eu".parse::<ServiceName>().is_err());
+        assert!("worker@eu@us".parse::<ServiceName>().is_err());
+    }
 }
@@ -0,0 +1,335 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::diff::{expected_config, show_config_changes};
+use crate::envfile::{
+    env_file_path, parse_env_pairs, redact_config, write_config_file, write_env_file,
+};
+use crate::init::{
+    ensure_corky_prefix, ensure_root, exit_error, run_cmd, run_cmd_expect_ok, run_cmd_quiet,
+    section, systemd_unit_path, CorkyConfig, InitBackend, ServiceInfo, ServiceName, C_BGREEN,
+    C_CYAN, C_GREEN, C_RESET, C_YELLOW,
+};
+use crate::logs::print_failure_context;
+use crate::manifest::{load_manifest, write_manifest, InstallManifest};
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Instance naming (`name@instance`)
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// `corky-worker@eu` -> (`corky-worker`, `eu`).
+pub fn split_instance(name: &str) -> Option<(&str, &str)> {
+    name.split_once('@')
+}
+
+pub fn instance_name(template: &str, id: &str) -> String {
+    format!("{}@{}", template, id)
+}
+
+/// Ids usable verbatim as a systemd instance and a supervisor program suffix.
+pub fn is_valid_instance_id(id: &str) -> bool {
+    !id.is_empty()
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.')
+}
+
+/// The `<name>@.service` template unit instances are started from.
+pub fn template_unit_path(service_name: &str) -> PathBuf {
+    systemd_unit_path(&format!("{}@", service_name))
+}
+
+/// Reject settings a template can't be combined with.
+pub fn validate_template(config: &CorkyConfig) {
+    if !config.template {
+        return;
+    }
+    if config.schedule.is_some() {
+        exit_error("[corky] template and schedule can't be combined.");
+    }
+    if !config.listen.is_empty() {
+        exit_error("[corky] template and listen can't be combined.");
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Install / uninstall
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Write the template unit (or the conf with one program per instance) and
+/// restart the instances that already exist so they pick up the new binary.
+pub fn install_template(
+    backend: &InitBackend,
+    dry_run: bool,
+    skip_init: bool,
+    manifest: &InstallManifest,
+) {
+    let (path, contents) = expected_config(backend, manifest);
+    let names: Vec<String> = manifest
+        .instances
+        .iter()
+        .map(|id| instance_name(&manifest.service_name, id))
+        .collect();
+
+    section("Writing service template");
+    show_config_changes(&path, &contents, &manifest.service_name);
+    if dry_run {
+        println!("{C_CYAN}[DRY-RUN]{C_RESET} Would write: {}", path.display());
+        println!(
+            "---------- {} ----------\n{}",
+            path.display(),
+            redact_config(&contents, &manifest.service_name)
+        );
+        if !skip_init {
+            for name in &names {
+                println!("{C_CYAN}[DRY-RUN]{C_RESET} Would restart {}", name);
+            }
+        }
+        return;
+    }
+    write_template(backend, &path, &contents, &manifest.service_name);
+
+    section("Restarting instances");
+    if skip_init {
+        println!("{C_YELLOW}[SKIP]{C_RESET} reloading {}", backend);
+    } else {
+        reload_backend(backend);
+        for name in &names {
+            let ok = match backend {
+                InitBackend::Systemd { .. } => run_cmd("systemctl", &["restart", name]),
+                InitBackend::Supervisor => run_cmd("supervisorctl", &["restart", name]),
+            };
+            if !ok {
+                eprintln!("{C_YELLOW}[WARN]{C_RESET} Could not restart {}", name);
+            }
+        }
+    }
+
+    section("Done");
+    println!(
+        "{C_BGREEN}[SUCCESS]{C_RESET} {} installed as a template with {} instance(s).",
+        manifest.service_name,
+        names.len()
+    );
+    println!("  Template: {}", path.display());
+    println!(
+        "  Add one:  corky instance add {} <id> [KEY=VALUE...]",
+        manifest.service_name
+    );
+}
+
+/// Stop every instance of a template before it is uninstalled.
+pub fn stop_instances(
+    backend: &InitBackend,
+    dry_run: bool,
+    skip_init: bool,
+    manifest: &InstallManifest,
+) {
+    if manifest.instances.is_empty() {
+        return;
+    }
+    section("Stopping instances");
+    for id in &manifest.instances {
+        let name = instance_name(&manifest.service_name, id);
+        if dry_run {
+            println!("{C_CYAN}[DRY-RUN]{C_RESET} Would stop {}", name);
+        } else if skip_init {
+            println!("{C_YELLOW}[SKIP]{C_RESET} stop {}", name);
+        } else {
+            match backend {
+                InitBackend::Systemd { .. } => {
+                    let _ = run_cmd("systemctl", &["disable", "--now", &name]);
+                    let _ = run_cmd_quiet("systemctl", &["reset-failed", &name]);
+                }
+                InitBackend::Supervisor => {
+                    let _ = run_cmd("supervisorctl", &["stop", &name]);
+                }
+            }
+        }
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// corky instance add / remove
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Register instance `id` of a template, write its env file and start it.
+pub fn run_instance_add(backend: &InitBackend, service: &ServiceName, id: &str, pairs: &[String]) {
+    let mut manifest = template_manifest(backend, service);
+    if !is_valid_instance_id(id) {
+        exit_error(&format!(
+            "Invalid instance id '{}'. Use letters, digits, '.', '_' and '-'.",
+            id
+        ));
+    }
+    let vars = parse_env_pairs(pairs);
+    let name = instance_name(&manifest.service_name, id);
+    if manifest.instances.iter().any(|i| i == id) {
+        exit_error(&format!(
+            "{} already exists. Change its environment with `corky env set {}`.",
+            name, name
+        ));
+    }
+    ensure_root(backend);
+
+    section(&format!("Adding instance {}", name));
+    if !vars.is_empty() {
+        match write_env_file(&name, &vars) {
+            Ok(path) => println!("{C_GREEN}[INFO]{C_RESET} Wrote {} (0600)", path.display()),
+            Err(e) => exit_error(&format!("write {}: {}", env_file_path(&name).display(), e)),
+        }
+    }
+    manifest.instances.push(id.to_string());
+    manifest.instances.sort();
+    save_manifest(&manifest);
+
+    match backend {
+        InitBackend::Systemd { .. } => {
+            if !run_cmd("systemctl", &["enable", "--now", &name]) {
+                eprintln!("{C_YELLOW}[WARN]{C_RESET} {} failed to start", name);
+                print_failure_context(&ServiceInfo {
+                    name: name.clone(),
+                    backend: backend.clone(),
+                });
+                std::process::exit(1);
+            }
+        }
+        InitBackend::Supervisor => {
+            // update adds the new program and starts it (autostart=true)
+            refresh_template(backend, &manifest);
+            reload_backend(backend);
+        }
+    }
+    println!("{C_BGREEN}[OK]{C_RESET} Instance {} added and started", name);
+}
+
+/// Stop instance `id`, forget it and delete its env file.
+pub fn run_instance_remove(backend: &InitBackend, service: &ServiceName, id: &str) {
+    let mut manifest = template_manifest(backend, service);
+    let name = instance_name(&manifest.service_name, id);
+    if !manifest.instances.iter().any(|i| i == id) {
+        exit_error(&format!(
+            "{} has no instance '{}'. Instances: {}",
+            manifest.service_name,
+            id,
+            if manifest.instances.is_empty() {
+                "(none)".to_string()
+            } else {
+                manifest.instances.join(", ")
+            }
+        ));
+    }
+    ensure_root(backend);
+
+    section(&format!("Removing instance {}", name));
+    manifest.instances.retain(|i| i != id);
+    manifest.instance_autostart.remove(id);
+    match backend {
+        InitBackend::Systemd { .. } => {
+            let _ = run_cmd("systemctl", &["disable", "--now", &name]);
+            let _ = run_cmd_quiet("systemctl", &["reset-failed", &name]);
+        }
+        InitBackend::Supervisor => {
+            // update stops and drops the program that left the conf
+            refresh_template(backend, &manifest);
+            reload_backend(backend);
+        }
+    }
+    save_manifest(&manifest);
+
+    let env_path = env_file_path(&name);
+    if env_path.exists() {
+        match fs::remove_file(&env_path) {
+            Ok(()) => println!("{C_GREEN}[INFO]{C_RESET} Removed {}", env_path.display()),
+            Err(e) => eprintln!("{C_YELLOW}[WARN]{C_RESET} remove {}: {}", env_path.display(), e),
+        }
+    }
+    println!("{C_BGREEN}[OK]{C_RESET} Instance {} removed", name);
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Helpers
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// The manifest of a templated service on `backend`, or exit explaining why not.
+fn template_manifest(backend: &InitBackend, service: &ServiceName) -> InstallManifest {
+    let ServiceName::Custom(name) = service else {
+        exit_error("Specify the templated service by name.");
+    };
+    if let Some((template, _)) = split_instance(name) {
+        exit_error(&format!(
+            "Give the template name ({}), not an instance.",
+            template
+        ));
+    }
+    let manifest = load_manifest(name)
+        .or_else(|| load_manifest(&ensure_corky_prefix(name)))
+        .filter(|m| m.backend == backend.to_string())
+        .unwrap_or_else(|| {
+            exit_error(&format!("No {} service named {} is installed.", backend, name))
+        });
+    if !manifest.template {
+        exit_error(&format!(
+            "{} is not a template. Set `template = true` under [corky] and reinstall.",
+            manifest.service_name
+        ));
+    }
+    manifest
+}
+
+fn save_manifest(manifest: &InstallManifest) {
+    if let Err(e) = write_manifest(manifest) {
+        exit_error(&format!("Could not write install manifest: {}", e));
+    }
+}
+
+/// Re-render the supervisor conf after the instance list changed.
+fn refresh_template(backend: &InitBackend, manifest: &InstallManifest) {
+    let (path, contents) = expected_config(backend, manifest);
+    write_template(backend, &path, &contents, &manifest.service_name);
+}
+
+fn write_template(backend: &InitBackend, path: &Path, contents: &str, service_name: &str) {
+    if let Some(parent) = path.parent()
+        && let Err(e) = fs::create_dir_all(parent)
+    {
+        exit_error(&format!("create {}: {}", parent.display(), e));
+    }
+    if let Err(e) = write_config_file(backend, path, contents, service_name) {
+        exit_error(&format!("write {}: {}", path.display(), e));
+    }
+    println!("{C_GREEN}[INFO]{C_RESET} Wrote {}", path.display());
+}
+
+fn reload_backend(backend: &InitBackend) {
+    match backend {
+        InitBackend::Systemd { .. } => run_cmd_expect_ok("systemctl", &["daemon-reload"]),
+        InitBackend::Supervisor => {
+            run_cmd_expect_ok("supervisorctl", &["reread"]);
+            run_cmd_expect_ok("supervisorctl", &["update"]);
+        }
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Tests
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_instance_names() {
+        assert_eq!(split_instance("corky-worker@eu"), Some(("corky-worker", "eu")));
+        assert_eq!(split_instance("corky-worker"), None);
+        assert_eq!(instance_name("corky-worker", "us"), "corky-worker@us");
+        assert!(is_valid_instance_id("eu-west.1"));
+        assert!(!is_valid_instance_id(""));
+        assert!(!is_valid_instance_id("a/b"));
+        assert_eq!(
+            template_unit_path("corky-worker"),
+            PathBuf::from("/etc/systemd/system/corky-worker@.service")
+        );
+    }
+}
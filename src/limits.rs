@@ -0,0 +1,325 @@
+use serde::{Deserialize, Serialize};
+
+use crate::init::{find_in_path, is_systemd_available, C_RESET, C_YELLOW};
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Resource limits (`[corky.limits]`)
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Per-service resource caps. Unset fields leave the system default.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ResourceLimits {
+    /// Hard memory ceiling, systemd syntax ("512M", "2G", "80%").
+    pub memory_max: Option<String>,
+    /// CPU time share, e.g. "50%" for half a core or "200%" for two.
+    pub cpu_quota: Option<String>,
+    /// Maximum number of tasks (processes + threads).
+    pub tasks_max: Option<u64>,
+    /// Open file descriptor limit (soft and hard).
+    pub nofile: Option<u64>,
+    /// Scheduling niceness, -20 (highest priority) to 19.
+    pub nice: Option<i32>,
+    /// OOM killer preference, -1000 (never) to 1000 (first).
+    pub oom_score_adjust: Option<i32>,
+}
+
+impl ResourceLimits {
+    pub fn is_empty(&self) -> bool {
+        *self == ResourceLimits::default()
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(nice) = self.nice
+            && !(-20..=19).contains(&nice)
+        {
+            return Err(format!("nice = {} is outside -20..19", nice));
+        }
+        if let Some(oom) = self.oom_score_adjust
+            && !(-1000..=1000).contains(&oom)
+        {
+            return Err(format!("oom_score_adjust = {} is outside -1000..1000", oom));
+        }
+        Ok(())
+    }
+}
+
+/// The [Service] directives for `limits`, one `Key=value` line each.
+pub fn render_limit_directives(limits: &ResourceLimits) -> String {
+    let mut out = String::new();
+    if let Some(v) = &limits.memory_max {
+        out.push_str(&format!("MemoryMax={}\n", v));
+    }
+    if let Some(v) = &limits.cpu_quota {
+        out.push_str(&format!("CPUQuota={}\n", v));
+    }
+    if let Some(v) = limits.tasks_max {
+        out.push_str(&format!("TasksMax={}\n", v));
+    }
+    if let Some(v) = limits.nofile {
+        out.push_str(&format!("LimitNOFILE={}\n", v));
+    }
+    if let Some(v) = limits.nice {
+        out.push_str(&format!("Nice={}\n", v));
+    }
+    if let Some(v) = limits.oom_score_adjust {
+        out.push_str(&format!("OOMScoreAdjust={}\n", v));
+    }
+    out
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Supervisor wrapper
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Helpers a supervisor command can be wrapped in to apply limits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WrapperTools {
+    /// `systemd-run --scope` with a running systemd (memory, CPU, tasks).
+    pub systemd_run: bool,
+    pub prlimit: bool,
+    pub choom: bool,
+    pub nice: bool,
+    /// Drops to the service user after the root-only adjustments.
+    pub setpriv: bool,
+}
+
+impl WrapperTools {
+    pub fn probe() -> Self {
+        WrapperTools {
+            systemd_run: is_systemd_available() && find_in_path("systemd-run").is_some(),
+            prlimit: find_in_path("prlimit").is_some(),
+            choom: find_in_path("choom").is_some(),
+            nice: find_in_path("nice").is_some(),
+            setpriv: find_in_path("setpriv").is_some(),
+        }
+    }
+}
+
+/// Programs that may lead a wrapped supervisor command, each ending with `--`.
+const WRAPPERS: [&str; 5] = ["systemd-run", "prlimit", "choom", "nice", "setpriv"];
+
+/// Command prefix applying `limits` under supervisor, and the limits that
+/// couldn't be enforced. The chain runs as root (no `user=`) and ends in
+/// `setpriv`, so raising limits and lowering nice/OOM scores is permitted.
+pub fn supervisor_limit_wrapper(
+    limits: &ResourceLimits,
+    user: &str,
+    group: &str,
+    tools: WrapperTools,
+) -> (Vec<String>, Vec<&'static str>) {
+    let mut prefix: Vec<String> = Vec::new();
+    let mut unenforced = Vec::new();
+    if limits.is_empty() {
+        return (prefix, unenforced);
+    }
+    if !tools.setpriv {
+        // Without a way to drop privileges afterwards, nothing can be applied as root
+        unenforced.extend(limit_names(limits));
+        return (prefix, unenforced);
+    }
+    let words = |w: &[&str]| w.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+    let mut properties = Vec::new();
+    if let Some(v) = &limits.memory_max {
+        properties.push(format!("MemoryMax={}", v));
+    }
+    if let Some(v) = &limits.cpu_quota {
+        properties.push(format!("CPUQuota={}", v));
+    }
+    if let Some(v) = limits.tasks_max {
+        properties.push(format!("TasksMax={}", v));
+    }
+    if !properties.is_empty() {
+        if tools.systemd_run {
+            prefix.extend(words(&["systemd-run", "--scope", "--quiet"]));
+            for p in properties {
+                prefix.push("-p".to_string());
+                prefix.push(p);
+            }
+            prefix.push("--".to_string());
+        } else {
+            unenforced.extend(
+                [
+                    limits.memory_max.as_ref().map(|_| "memory_max"),
+                    limits.cpu_quota.as_ref().map(|_| "cpu_quota"),
+                    limits.tasks_max.map(|_| "tasks_max"),
+                ]
+                .into_iter()
+                .flatten(),
+            );
+        }
+    }
+    if let Some(n) = limits.nofile {
+        if tools.prlimit {
+            prefix.extend(words(&["prlimit", &format!("--nofile={}:{}", n, n), "--"]));
+        } else {
+            unenforced.push("nofile");
+        }
+    }
+    if let Some(n) = limits.oom_score_adjust {
+        if tools.choom {
+            prefix.extend(words(&["choom", "-n", &n.to_string(), "--"]));
+        } else {
+            unenforced.push("oom_score_adjust");
+        }
+    }
+    if let Some(n) = limits.nice {
+        if tools.nice {
+            prefix.extend(words(&["nice", "-n", &n.to_string(), "--"]));
+        } else {
+            unenforced.push("nice");
+        }
+    }
+    prefix.extend(words(&[
+        "setpriv",
+        &format!("--reuid={}", user),
+        &format!("--regid={}", group),
+        "--init-groups",
+        "--",
+    ]));
+    (prefix, unenforced)
+}
+
+/// Undo [`supervisor_limit_wrapper`]: the bare command, plus the user and
+/// group passed to setpriv if the command was wrapped.
+pub fn strip_limit_wrapper(words: Vec<String>) -> (Vec<String>, Option<(String, String)>) {
+    let mut rest = words.as_slice();
+    let mut identity = None;
+    while let Some(first) = rest.first() {
+        let program = first.rsplit('/').next().unwrap_or(first);
+        let Some(end) = rest.iter().position(|w| w == "--") else {
+            break;
+        };
+        if !WRAPPERS.contains(&program) {
+            break;
+        }
+        if program == "setpriv" {
+            let value = |flag: &str| {
+                rest[..end]
+                    .iter()
+                    .find_map(|w| w.strip_prefix(flag))
+                    .map(str::to_string)
+            };
+            if let (Some(user), Some(group)) = (value("--reuid="), value("--regid=")) {
+                identity = Some((user, group));
+            }
+        }
+        rest = &rest[end + 1..];
+    }
+    (rest.to_vec(), identity)
+}
+
+/// Tell the user which limits won't take effect under supervisor.
+pub fn warn_unenforced_limits(unenforced: &[&str]) {
+    if !unenforced.is_empty() {
+        eprintln!(
+            "{C_YELLOW}[WARN]{C_RESET} Supervisor can't enforce [corky.limits] {} on this host (needs systemd-run, prlimit, choom, nice and setpriv).",
+            unenforced.join(", ")
+        );
+    }
+}
+
+fn limit_names(limits: &ResourceLimits) -> Vec<&'static str> {
+    [
+        limits.memory_max.as_ref().map(|_| "memory_max"),
+        limits.cpu_quota.as_ref().map(|_| "cpu_quota"),
+        limits.tasks_max.map(|_| "tasks_max"),
+        limits.nofile.map(|_| "nofile"),
+        limits.nice.map(|_| "nice"),
+        limits.oom_score_adjust.map(|_| "oom_score_adjust"),
+    ]
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Tests
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL_TOOLS: WrapperTools = WrapperTools {
+        systemd_run: true,
+        prlimit: true,
+        choom: true,
+        nice: true,
+        setpriv: true,
+    };
+
+    fn limits() -> ResourceLimits {
+        ResourceLimits {
+            memory_max: Some("512M".to_string()),
+            cpu_quota: None,
+            tasks_max: Some(64),
+            nofile: Some(4096),
+            nice: Some(5),
+            oom_score_adjust: None,
+        }
+    }
+
+    #[test]
+    fn test_render_limit_directives() {
+        assert_eq!(
+            render_limit_directives(&limits()),
+            "MemoryMax=512M\nTasksMax=64\nLimitNOFILE=4096\nNice=5\n"
+        );
+        assert_eq!(render_limit_directives(&ResourceLimits::default()), "");
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(limits().validate().is_ok());
+        let bad = ResourceLimits {
+            nice: Some(40),
+            ..ResourceLimits::default()
+        };
+        assert!(bad.validate().is_err());
+    }
+
+    #[test]
+    fn test_wrapper_roundtrip() {
+        let (prefix, unenforced) = supervisor_limit_wrapper(&limits(), "app", "app", ALL_TOOLS);
+        assert!(unenforced.is_empty());
+        assert_eq!(
+            prefix.join(" "),
+            "systemd-run --scope --quiet -p MemoryMax=512M -p TasksMax=64 -- \
+             prlimit --nofile=4096:4096 -- nice -n 5 -- \
+             setpriv --reuid=app --regid=app --init-groups --"
+        );
+
+        let mut words = prefix;
+        words.extend(["/usr/local/bin/corky-x".to_string(), "--flag".to_string()]);
+        let (command, identity) = strip_limit_wrapper(words);
+        assert_eq!(command, vec!["/usr/local/bin/corky-x", "--flag"]);
+        assert_eq!(identity, Some(("app".to_string(), "app".to_string())));
+    }
+
+    #[test]
+    fn test_wrapper_without_tools() {
+        let tools = WrapperTools {
+            systemd_run: false,
+            ..ALL_TOOLS
+        };
+        let (prefix, unenforced) = supervisor_limit_wrapper(&limits(), "app", "app", tools);
+        assert_eq!(unenforced, vec!["memory_max", "tasks_max"]);
+        assert_eq!(prefix[0], "prlimit");
+
+        let none = WrapperTools {
+            setpriv: false,
+            ..ALL_TOOLS
+        };
+        let (prefix, unenforced) = supervisor_limit_wrapper(&limits(), "app", "app", none);
+        assert!(prefix.is_empty());
+        assert_eq!(unenforced.len(), 4);
+    }
+
+    #[test]
+    fn test_strip_leaves_plain_commands() {
+        let words = vec!["/bin/app".to_string(), "--".to_string(), "x".to_string()];
+        assert_eq!(strip_limit_wrapper(words.clone()), (words, None));
+    }
+}
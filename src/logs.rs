@@ -0,0 +1,356 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::escalate::ENV_ORIGINAL_USER;
+use crate::init::{
+    elevate_if_needed, exit_error, private_temp_dir, run_cmd_quiet, scoped_command, section, supervisor_conf_path,
+    supervisor_log_path, InitBackend, ServiceInfo, C_BGREEN, C_CYAN, C_GREEN, C_RESET, C_YELLOW,
+};
+use crate::manifest::manifest_path;
+
+/// Number of rotated supervisor log backups (`stdout_logfile_backups`).
+const SUPERVISOR_LOG_BACKUPS: u32 = 5;
+/// How many log lines to show when a start/stop fails.
+const FAILURE_LOG_LINES: usize = 20;
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Failure context
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// After a failed action, print how the main process ended and its last log lines.
+pub fn print_failure_context(service_info: &ServiceInfo) {
+    section("Recent logs");
+    match &service_info.backend {
+        InitBackend::Systemd { scope } => {
+            let unit = format!("{}.service", service_info.name);
+
+            let mut show = scoped_command("systemctl", scope);
+            show.args(["show", "-p", "Result", "-p", "ExecMainCode", "-p", "ExecMainStatus"])
+                .arg(&unit);
+            if let Ok(output) = show.output() {
+                let props = String::from_utf8_lossy(&output.stdout);
+                let prop = |key: &str| {
+                    props
+                        .lines()
+                        .find_map(|l| l.strip_prefix(key)?.strip_prefix('='))
+                        .unwrap_or("")
+                        .to_string()
+                };
+                eprintln!(
+                    "{C_GREEN}[INFO]{C_RESET} Main process {} (result: {})",
+                    describe_exit(&prop("ExecMainCode"), &prop("ExecMainStatus")),
+                    prop("Result")
+                );
+            }
+
+            let mut journal = scoped_command("journalctl", scope);
+            journal
+                .args(["-u", &unit, "--no-pager", "-o", "short-iso", "-n"])
+                .arg(FAILURE_LOG_LINES.to_string());
+            match journal.output() {
+                Ok(output) => print_indented(&String::from_utf8_lossy(&output.stdout)),
+                Err(e) => eprintln!(
+                    "{C_YELLOW}[WARN]{C_RESET} Failed to execute journalctl: {}",
+                    e
+                ),
+            }
+        }
+        InitBackend::Supervisor => {
+            if let Ok(output) = Command::new("supervisorctl")
+                .args(["status", &service_info.name])
+                .output()
+            {
+                let status = String::from_utf8_lossy(&output.stdout);
+                eprintln!("{C_GREEN}[INFO]{C_RESET} {}", status.trim());
+            }
+
+            let log_path = supervisor_log_path(&service_info.name);
+            match fs::read(&log_path) {
+                Ok(data) => print_indented(&last_lines(
+                    &String::from_utf8_lossy(&data),
+                    FAILURE_LOG_LINES,
+                )),
+                Err(e) => eprintln!(
+                    "{C_YELLOW}[WARN]{C_RESET} read {}: {}",
+                    log_path.display(),
+                    e
+                ),
+            }
+        }
+    }
+}
+
+/// Render systemd's ExecMainCode/ExecMainStatus pair (CLD_* code + status or signal).
+fn describe_exit(code: &str, status: &str) -> String {
+    let status: i32 = status.trim().parse().unwrap_or(0);
+    match code.trim() {
+        "1" => format!("exited with status {}", status),
+        "2" => format!("was killed by signal {} ({})", status, signal_name(status)),
+        "3" => format!("dumped core on signal {} ({})", status, signal_name(status)),
+        "0" | "" => "has not exited".to_string(),
+        other => format!("ended with code {} status {}", other, status),
+    }
+}
+
+pub fn signal_name(signal: i32) -> &'static str {
+    match signal {
+        1 => "SIGHUP",
+        2 => "SIGINT",
+        3 => "SIGQUIT",
+        4 => "SIGILL",
+        6 => "SIGABRT",
+        7 => "SIGBUS",
+        8 => "SIGFPE",
+        9 => "SIGKILL",
+        10 => "SIGUSR1",
+        11 => "SIGSEGV",
+        12 => "SIGUSR2",
+        13 => "SIGPIPE",
+        14 => "SIGALRM",
+        15 => "SIGTERM",
+        _ => "unknown",
+    }
+}
+
+fn last_lines(text: &str, n: usize) -> String {
+    let lines: Vec<&str> = text.lines().collect();
+    lines[lines.len().saturating_sub(n)..].join("\n")
+}
+
+fn print_indented(text: &str) {
+    if text.trim().is_empty() {
+        eprintln!("  (no log output)");
+        return;
+    }
+    for line in text.lines() {
+        eprintln!("  {}", line);
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Log export
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Bundle a service's logs, config and runtime state into one tar archive.
+pub fn export_service_logs(service_info: &ServiceInfo, since: Option<&str>, output: &Path) {
+    elevate_if_needed(service_info);
+
+    let compression = archive_compression_flag(output).unwrap_or_else(|| {
+        exit_error(&format!(
+            "Unsupported archive name '{}'. Use .tar, .tar.gz, .tar.xz or .tar.zst.",
+            output.display()
+        ))
+    });
+    let output = if output.is_absolute() {
+        output.to_path_buf()
+    } else {
+        env::current_dir()
+            .unwrap_or_else(|_| PathBuf::from("/"))
+            .join(output)
+    };
+
+    let bundle_name = format!("{}-logs", service_info.name);
+    let staging = private_temp_dir(&format!("corky-export-{}", service_info.name))
+        .unwrap_or_else(|e| exit_error(&format!("create temp dir: {}", e)));
+    let bundle_dir = staging.join(&bundle_name);
+    fs::create_dir(&bundle_dir).unwrap_or_else(|e| {
+        exit_error(&format!("create {}: {}", bundle_dir.display(), e));
+    });
+
+    section("Collecting logs & config");
+    match &service_info.backend {
+        InitBackend::Systemd { scope } => {
+            let unit = format!("{}.service", service_info.name);
+
+            let mut journal = scoped_command("journalctl", scope);
+            journal.args(["-u", &unit, "--no-pager", "-o", "short-iso"]);
+            if let Some(since) = since {
+                journal.args(["--since", since]);
+            }
+            capture_to_file(&mut journal, &bundle_dir.join("journal.log"));
+
+            let mut show = scoped_command("systemctl", scope);
+            show.args(["show", &unit]);
+            capture_to_file(&mut show, &bundle_dir.join("systemctl-show.txt"));
+
+            let mut cat = scoped_command("systemctl", scope);
+            cat.args(["cat", &unit]);
+            capture_to_file(&mut cat, &bundle_dir.join(&unit));
+        }
+        InitBackend::Supervisor => {
+            if since.is_some() {
+                eprintln!(
+                    "{C_YELLOW}[WARN]{C_RESET} --since is ignored for supervisor; log files are copied whole."
+                );
+            }
+            for path in supervisor_log_files(&service_info.name) {
+                copy_into(&path, &bundle_dir);
+            }
+
+            let mut status = Command::new("supervisorctl");
+            status.args(["status", &service_info.name]);
+            capture_to_file(&mut status, &bundle_dir.join("supervisorctl-status.txt"));
+
+            copy_into(&supervisor_conf_path(&service_info.name), &bundle_dir);
+        }
+    }
+    copy_into(&manifest_path(&service_info.name), &bundle_dir);
+
+    section("Writing archive");
+    let mut tar = Command::new("tar");
+    if !compression.is_empty() {
+        tar.arg(compression);
+    }
+    let status = tar
+        .arg("-cf")
+        .arg(&output)
+        .arg("-C")
+        .arg(&staging)
+        .arg(&bundle_name)
+        .stdin(Stdio::null())
+        .status();
+    let _ = fs::remove_dir_all(&staging);
+
+    match status {
+        Ok(s) if s.success() => {}
+        Ok(s) => {
+            eprintln!("{C_YELLOW}[WARN]{C_RESET} tar exited with {}", s);
+            exit_error(&format!("Failed to write {}", output.display()));
+        }
+        Err(e) => exit_error(&format!("Failed to execute tar: {}", e)),
+    }
+    hand_back_to_invoking_user(&output);
+
+    println!(
+        "{C_BGREEN}[OK]{C_RESET} Exported {} logs to {}",
+        service_info.name,
+        output.display()
+    );
+}
+
+/// Map an archive file name to the tar compression flag it implies.
+fn archive_compression_flag(path: &Path) -> Option<&'static str> {
+    let name = path.file_name()?.to_string_lossy().to_lowercase();
+    if name.ends_with(".tar.zst") || name.ends_with(".tzst") {
+        Some("--zstd")
+    } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        Some("--gzip")
+    } else if name.ends_with(".tar.xz") || name.ends_with(".txz") {
+        Some("--xz")
+    } else if name.ends_with(".tar") {
+        Some("")
+    } else {
+        None
+    }
+}
+
+/// The current supervisor log plus its rotated `.1`..`.5` backups, if present.
+fn supervisor_log_files(service_name: &str) -> Vec<PathBuf> {
+    let current = supervisor_log_path(service_name);
+    let mut files = vec![current.clone()];
+    for i in 1..=SUPERVISOR_LOG_BACKUPS {
+        files.push(PathBuf::from(format!("{}.{}", current.display(), i)));
+    }
+    files.into_iter().filter(|p| p.exists()).collect()
+}
+
+fn capture_to_file(cmd: &mut Command, dest: &Path) {
+    match cmd.stdin(Stdio::null()).output() {
+        Ok(output) => {
+            if !output.status.success() {
+                eprintln!(
+                    "{C_YELLOW}[WARN]{C_RESET} {:?} exited with {}",
+                    cmd.get_program(),
+                    output.status
+                );
+            }
+            let mut data = output.stdout;
+            data.extend_from_slice(&output.stderr);
+            if let Err(e) = fs::write(dest, data) {
+                eprintln!("{C_YELLOW}[WARN]{C_RESET} write {}: {}", dest.display(), e);
+            } else {
+                println!("{C_GREEN}[INFO]{C_RESET} Collected {}", file_label(dest));
+            }
+        }
+        Err(e) => eprintln!(
+            "{C_YELLOW}[WARN]{C_RESET} Failed to execute {:?}: {}",
+            cmd.get_program(),
+            e
+        ),
+    }
+}
+
+fn copy_into(src: &Path, dir: &Path) {
+    if !src.exists() {
+        println!("{C_CYAN}[SKIP]{C_RESET} Not found: {}", src.display());
+        return;
+    }
+    let Some(name) = src.file_name() else {
+        return;
+    };
+    match fs::copy(src, dir.join(name)) {
+        Ok(_) => println!("{C_GREEN}[INFO]{C_RESET} Collected {}", src.display()),
+        Err(e) => eprintln!("{C_YELLOW}[WARN]{C_RESET} copy {}: {}", src.display(), e),
+    }
+}
+
+fn file_label(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.display().to_string())
+}
+
+/// When elevated, give the archive back to the user who asked for it.
+fn hand_back_to_invoking_user(path: &Path) {
+    let (Ok(uid), Ok(gid)) = (env::var("SUDO_UID"), env::var("SUDO_GID")) else {
+        // doas, run0 and pkexec: only the name is known
+        if let Ok(user) = env::var(ENV_ORIGINAL_USER) {
+            let _ = run_cmd_quiet("chown", &[&format!("{}:", user), &path.to_string_lossy()]);
+        }
+        return;
+    };
+    let (Ok(uid), Ok(gid)) = (uid.parse::<libc::uid_t>(), gid.parse::<libc::gid_t>()) else {
+        return;
+    };
+    let Ok(c_path) = std::ffi::CString::new(path.as_os_str().as_encoded_bytes()) else {
+        return;
+    };
+    unsafe {
+        libc::chown(c_path.as_ptr(), uid, gid);
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Tests
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_describe_exit() {
+        assert_eq!(describe_exit("1", "3"), "exited with status 3");
+        assert_eq!(describe_exit("2", "9"), "was killed by signal 9 (SIGKILL)");
+        assert_eq!(describe_exit("3", "11"), "dumped core on signal 11 (SIGSEGV)");
+        assert_eq!(describe_exit("0", "0"), "has not exited");
+    }
+
+    #[test]
+    fn test_last_lines() {
+        assert_eq!(last_lines("a\nb\nc\nd", 2), "c\nd");
+        assert_eq!(last_lines("a\nb", 5), "a\nb");
+        assert_eq!(last_lines("", 3), "");
+    }
+
+    #[test]
+    fn test_archive_compression_flag() {
+        assert_eq!(archive_compression_flag(Path::new("out.tar.zst")), Some("--zstd"));
+        assert_eq!(archive_compression_flag(Path::new("/tmp/x.tgz")), Some("--gzip"));
+        assert_eq!(archive_compression_flag(Path::new("x.tar.xz")), Some("--xz"));
+        assert_eq!(archive_compression_flag(Path::new("x.tar")), Some(""));
+        assert_eq!(archive_compression_flag(Path::new("x.zip")), None);
+    }
+}
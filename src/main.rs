@@ -1,15 +1,59 @@
+mod adopt;
+mod archive;
+mod build;
+mod deploy;
+mod dev;
+mod diff;
+mod doctor;
+mod envfile;
+mod escalate;
+mod grant;
+mod hardening;
 mod init;
+mod instance;
+mod limits;
+mod logs;
+mod manifest;
+mod migrate;
+mod pack;
+mod package;
+mod prune;
+mod readiness;
+mod rpm;
+mod run;
+mod schedule;
+mod signal;
+mod socket;
+mod users;
 
-use clap::{CommandFactory, Parser, Subcommand};
+use clap::{Args, CommandFactory, Parser, Subcommand, ValueEnum};
 use clap_complete::{generate, Shell};
 use std::io;
+use std::path::PathBuf;
 
 use init::{
     check_migration_warning, detect_backend, install_service, list_corky_services,
     resolve_service, run_service_action, run_service_disable, run_service_enable,
-    run_service_logs, uninstall_service, ServiceName,
+    run_service_logs, uninstall_service, InitBackend, ServiceName,
     C_BOLD, C_RESET,
 };
+use adopt::adopt_service;
+use archive::InstallSource;
+use build::BuildOptions;
+use dev::run_dev;
+use diff::{run_config_diff, run_restart_if_changed};
+use doctor::run_doctor;
+use envfile::{run_env_list, run_env_set, run_env_unset};
+use grant::{run_grant, run_grant_list, run_revoke, GrantAction};
+use hardening::run_security_report;
+use instance::{run_instance_add, run_instance_remove};
+use logs::export_service_logs;
+use migrate::migrate_services;
+use package::{run_package, PackageFormat};
+use prune::prune;
+use run::run_service_foreground;
+use schedule::schedule_summary;
+use signal::{run_service_reload, run_service_signal};
 
 // ─────────────────────────────────────────────────────────────────────────────
 // ASCII art banner for CLI help output
@@ -56,6 +100,44 @@ enum Commands {
         /// Skip init system registration (install binary and config only)
         #[arg(long, alias = "skip-service")]
         skip_init: bool,
+
+        /// Reinstall even if the binary and config are up to date
+        #[arg(long)]
+        force: bool,
+
+        /// Install this prebuilt binary instead of building (metadata from ./Cargo.toml)
+        #[arg(long, value_name = "PATH", conflicts_with = "archive")]
+        artifact: Option<PathBuf>,
+
+        /// Install from an archive made by `corky package` (no Cargo.toml or cargo needed)
+        #[arg(long, value_name = "PATH")]
+        archive: Option<PathBuf>,
+
+        #[command(flatten)]
+        build: BuildFlags,
+    },
+    /// Build a release archive, .deb or .rpm of the package
+    Package {
+        /// Package format; tar is the archive `corky install --archive` takes
+        #[arg(long, value_enum, default_value = "tar")]
+        format: FormatKind,
+
+        /// Init system a deb or rpm installs the service for (default: systemd)
+        #[arg(long, value_enum)]
+        backend: Option<BackendKind>,
+
+        /// Where to write the package (default: under target/corky/)
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+
+        #[command(flatten)]
+        build: BuildFlags,
+    },
+    /// Rebuild and restart the installed service whenever the package changes
+    Dev {
+        /// Swap in a verified build (used internally when elevated)
+        #[arg(long, hide = true)]
+        apply: bool,
     },
     /// Uninstall corky services
     Uninstall {
@@ -66,9 +148,17 @@ enum Commands {
         /// Skip init system operations (remove binary and config only)
         #[arg(long, alias = "skip-service")]
         skip_init: bool,
+
+        /// Also delete the system user corky created for the service
+        #[arg(long)]
+        remove_user: bool,
     },
     /// View logs for a corky service
+    #[command(args_conflicts_with_subcommands = true)]
     Logs {
+        #[command(subcommand)]
+        action: Option<LogsAction>,
+
         /// Name of the service to view logs for
         service: Option<ServiceName>,
     },
@@ -91,6 +181,32 @@ enum Commands {
     Restart {
         /// Name of the service to restart
         service: Option<ServiceName>,
+
+        /// Only restart if the binary, config or env file changed since it started
+        #[arg(long)]
+        if_changed: bool,
+    },
+    /// Reload a service's configuration (sends [corky] reload_signal, default SIGHUP)
+    Reload {
+        /// Name of the service to reload
+        service: Option<ServiceName>,
+    },
+    /// Send a signal to a service's main process
+    Signal {
+        /// Name of the service to signal
+        service: ServiceName,
+
+        /// Signal name, e.g. USR1 or SIGTERM
+        signal: String,
+    },
+    /// Run a service in the foreground with its user, directory, environment and args
+    Run {
+        /// Name of the service to run
+        service: Option<ServiceName>,
+
+        /// Use the package's own build (per [corky.build]) instead of the installed binary
+        #[arg(long)]
+        local: bool,
     },
     /// Enable a corky service (auto-start)
     Enable {
@@ -104,6 +220,97 @@ enum Commands {
     },
     /// List all corky services
     List,
+    /// Remove orphaned configs, stale binaries and failed units
+    Prune {
+        /// Remove without asking for confirmation
+        #[arg(long, short = 'y')]
+        yes: bool,
+    },
+    /// Bring an existing unit or supervisor program under corky management
+    Adopt {
+        /// Unit or program name to adopt (e.g. legacy-api or legacy-api.service)
+        unit: String,
+
+        /// Rename it to corky-<name> and regenerate its config
+        #[arg(long)]
+        rename: bool,
+
+        /// Run in dry-run mode (no actual changes made)
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Show drift between a service's config on disk and the generated one
+    Diff {
+        /// Name of the service to check
+        service: Option<ServiceName>,
+
+        /// Overwrite the on-disk config with the generated one
+        #[arg(long)]
+        fix: bool,
+    },
+    /// Show a service's sandboxing exposure (systemd-analyze security)
+    Security {
+        /// Name of the service to analyze
+        service: Option<ServiceName>,
+    },
+    /// Manage a service's environment variables and secrets
+    Env {
+        #[command(subcommand)]
+        action: EnvAction,
+    },
+    /// Add or remove instances of a templated service (name@instance)
+    Instance {
+        #[command(subcommand)]
+        action: InstanceAction,
+    },
+    /// Let a user or %group start, stop, restart or follow logs of services without full root
+    Grant {
+        /// User name, or %group
+        #[arg(required_unless_present = "list", conflicts_with = "list")]
+        principal: Option<String>,
+
+        /// Services to allow (default: every installed corky service)
+        #[arg(long, value_delimiter = ',')]
+        services: Vec<String>,
+
+        /// Actions to allow
+        #[arg(
+            long,
+            value_enum,
+            value_delimiter = ',',
+            default_values = ["start", "stop", "restart", "logs"]
+        )]
+        actions: Vec<GrantActionKind>,
+
+        /// List existing grants
+        #[arg(long)]
+        list: bool,
+
+        /// Run in dry-run mode (no actual changes made)
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Remove a user's or %group's corky grants
+    Revoke {
+        /// User name, or %group
+        principal: String,
+
+        /// Run in dry-run mode (no actual changes made)
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Diagnose common environment problems
+    Doctor,
+    /// Move all corky services to another init system
+    Migrate {
+        /// Init system to move services to
+        #[arg(long, value_enum)]
+        to: BackendKind,
+
+        /// Run in dry-run mode (no actual changes made)
+        #[arg(long)]
+        dry_run: bool,
+    },
     /// Generate shell completion scripts
     Completion {
         /// Shell to generate completions for
@@ -114,18 +321,208 @@ enum Commands {
     CompletionItems,
 }
 
+#[derive(Subcommand)]
+enum LogsAction {
+    /// Bundle logs, config and state into an archive for incident reports
+    Export {
+        /// Name of the service to export logs for
+        service: Option<ServiceName>,
+
+        /// Only include journal entries since this time (journalctl --since syntax)
+        #[arg(long)]
+        since: Option<String>,
+
+        /// Archive to write (.tar, .tar.gz, .tar.xz or .tar.zst)
+        #[arg(long)]
+        to: PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+enum EnvAction {
+    /// Set one or more variables (KEY=VALUE)
+    Set {
+        /// Name of the service
+        service: ServiceName,
+
+        /// Variables to set, as KEY=VALUE
+        #[arg(required = true)]
+        pairs: Vec<String>,
+
+        /// Restart the service without asking
+        #[arg(long)]
+        restart: bool,
+    },
+    /// Remove one or more variables
+    Unset {
+        /// Name of the service
+        service: ServiceName,
+
+        /// Variable names to remove
+        #[arg(required = true)]
+        keys: Vec<String>,
+
+        /// Restart the service without asking
+        #[arg(long)]
+        restart: bool,
+    },
+    /// List a service's variables (values are masked)
+    List {
+        /// Name of the service
+        service: Option<ServiceName>,
+
+        /// Show values instead of masking them
+        #[arg(long)]
+        reveal: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum InstanceAction {
+    /// Create an instance and start it
+    Add {
+        /// Name of the templated service
+        service: ServiceName,
+
+        /// Instance id, e.g. `eu` for corky-worker@eu
+        id: String,
+
+        /// Variables for this instance only, as KEY=VALUE
+        pairs: Vec<String>,
+    },
+    /// Stop an instance and delete it with its variables
+    Remove {
+        /// Name of the templated service
+        service: ServiceName,
+
+        /// Instance id
+        id: String,
+    },
+}
+
+/// Command-line additions to `[corky.build]`.
+#[derive(Args)]
+struct BuildFlags {
+    /// Features to enable (comma-separated; added to [corky.build] features)
+    #[arg(long, value_delimiter = ',')]
+    features: Vec<String>,
+
+    /// Build without the package's default features
+    #[arg(long)]
+    no_default_features: bool,
+
+    /// Cargo profile to build with (default: release)
+    #[arg(long)]
+    profile: Option<String>,
+
+    /// Target triple to build for, e.g. x86_64-unknown-linux-musl
+    #[arg(long)]
+    target: Option<String>,
+
+    /// Require Cargo.lock to be up to date
+    #[arg(long)]
+    locked: bool,
+
+    /// Build without accessing the network
+    #[arg(long)]
+    offline: bool,
+}
+
+impl From<&BuildFlags> for BuildOptions {
+    fn from(flags: &BuildFlags) -> Self {
+        BuildOptions {
+            features: flags.features.clone(),
+            no_default_features: flags.no_default_features,
+            profile: flags.profile.clone(),
+            target: flags.target.clone(),
+            locked: flags.locked,
+            offline: flags.offline,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum BackendKind {
+    Systemd,
+    Supervisor,
+}
+
+impl BackendKind {
+    fn to_backend(self) -> InitBackend {
+        match self {
+            BackendKind::Systemd => InitBackend::Systemd {
+                scope: "system".to_string(),
+            },
+            BackendKind::Supervisor => InitBackend::Supervisor,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum FormatKind {
+    Tar,
+    Deb,
+    Rpm,
+}
+
+impl FormatKind {
+    fn to_format(self) -> PackageFormat {
+        match self {
+            FormatKind::Tar => PackageFormat::Tar,
+            FormatKind::Deb => PackageFormat::Deb,
+            FormatKind::Rpm => PackageFormat::Rpm,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum GrantActionKind {
+    Start,
+    Stop,
+    Restart,
+    Logs,
+}
+
+impl GrantActionKind {
+    fn to_action(self) -> GrantAction {
+        match self {
+            GrantActionKind::Start => GrantAction::Start,
+            GrantActionKind::Stop => GrantAction::Stop,
+            GrantActionKind::Restart => GrantAction::Restart,
+            GrantActionKind::Logs => GrantAction::Logs,
+        }
+    }
+}
+
 // ─────────────────────────────────────────────────────────────────────────────
 // main
 // ─────────────────────────────────────────────────────────────────────────────
 fn main() {
     let cli = Cli::parse();
+    escalate::restore_original_cwd();
 
-    // Completion commands don't need backend detection (avoids errors in dev containers)
+    // Completion, doctor and package don't need backend detection (avoids errors in dev containers)
     match &cli.command {
         Commands::Completion { shell } => {
             generate_completion(*shell);
             return;
         }
+        Commands::Doctor => run_doctor(),
+        Commands::Package {
+            format,
+            backend,
+            output,
+            build,
+        } => {
+            let backend = backend.map(BackendKind::to_backend);
+            run_package(
+                format.to_format(),
+                backend.as_ref(),
+                &build.into(),
+                output.as_deref(),
+            );
+            return;
+        }
         _ => {}
     }
 
@@ -136,18 +533,47 @@ fn main() {
     check_migration_warning(&backend);
 
     match &cli.command {
-        Commands::Install { dry_run, skip_init } => {
-            install_service(&backend, *dry_run, *skip_init);
+        Commands::Install {
+            dry_run,
+            skip_init,
+            force,
+            artifact,
+            archive,
+            build,
+        } => {
+            let source = match (artifact, archive) {
+                (Some(path), _) => InstallSource::Artifact(path.clone()),
+                (_, Some(path)) => InstallSource::Archive(path.clone()),
+                _ => InstallSource::Build,
+            };
+            install_service(&backend, *dry_run, *skip_init, *force, &build.into(), &source);
+        }
+        Commands::Dev { apply } => {
+            run_dev(&backend, *apply);
         }
-        Commands::Uninstall { dry_run, skip_init } => {
-            uninstall_service(&backend, *dry_run, *skip_init);
+        Commands::Uninstall {
+            dry_run,
+            skip_init,
+            remove_user,
+        } => {
+            uninstall_service(&backend, *dry_run, *skip_init, *remove_user);
         }
-        Commands::Logs { service } => {
+        Commands::Logs { action: None, service } => {
             let info = resolve_service(&backend, service.clone());
             run_service_logs(&info);
         }
+        Commands::Logs {
+            action: Some(LogsAction::Export { service, since, to }),
+            ..
+        } => {
+            let info = resolve_service(&backend, service.clone());
+            export_service_logs(&info, since.as_deref(), to);
+        }
         Commands::Status { service } => {
             let info = resolve_service(&backend, service.clone());
+            if let Some(summary) = schedule_summary(&info) {
+                println!("{C_BOLD}{}{C_RESET} {}", info.name, summary);
+            }
             run_service_action("status", &info);
         }
         Commands::Start { service } => {
@@ -158,9 +584,28 @@ fn main() {
             let info = resolve_service(&backend, service.clone());
             run_service_action("stop", &info);
         }
-        Commands::Restart { service } => {
+        Commands::Restart {
+            service,
+            if_changed,
+        } => {
             let info = resolve_service(&backend, service.clone());
-            run_service_action("restart", &info);
+            if *if_changed {
+                run_restart_if_changed(&info);
+            } else {
+                run_service_action("restart", &info);
+            }
+        }
+        Commands::Reload { service } => {
+            let info = resolve_service(&backend, service.clone());
+            run_service_reload(&info);
+        }
+        Commands::Signal { service, signal } => {
+            let info = resolve_service(&backend, Some(service.clone()));
+            run_service_signal(&info, signal);
+        }
+        Commands::Run { service, local } => {
+            let info = resolve_service(&backend, service.clone());
+            run_service_foreground(&info, *local);
         }
         Commands::Enable { service } => {
             let info = resolve_service(&backend, service.clone());
@@ -170,6 +615,74 @@ fn main() {
             let info = resolve_service(&backend, service.clone());
             run_service_disable(&info);
         }
+        Commands::Adopt {
+            unit,
+            rename,
+            dry_run,
+        } => {
+            adopt_service(&backend, unit, *rename, *dry_run);
+        }
+        Commands::Diff { service, fix } => {
+            let info = resolve_service(&backend, service.clone());
+            run_config_diff(&info, *fix);
+        }
+        Commands::Security { service } => {
+            let info = resolve_service(&backend, service.clone());
+            run_security_report(&info);
+        }
+        Commands::Env { action } => match action {
+            EnvAction::Set {
+                service,
+                pairs,
+                restart,
+            } => {
+                let info = resolve_service(&backend, Some(service.clone()));
+                run_env_set(&info, pairs, *restart);
+            }
+            EnvAction::Unset {
+                service,
+                keys,
+                restart,
+            } => {
+                let info = resolve_service(&backend, Some(service.clone()));
+                run_env_unset(&info, keys, *restart);
+            }
+            EnvAction::List { service, reveal } => {
+                let info = resolve_service(&backend, service.clone());
+                run_env_list(&info, *reveal);
+            }
+        },
+        Commands::Instance { action } => match action {
+            InstanceAction::Add { service, id, pairs } => {
+                run_instance_add(&backend, service, id, pairs);
+            }
+            InstanceAction::Remove { service, id } => {
+                run_instance_remove(&backend, service, id);
+            }
+        },
+        Commands::Migrate { to, dry_run } => {
+            migrate_services(&to.to_backend(), *dry_run);
+        }
+        Commands::Prune { yes } => {
+            prune(&backend, *yes);
+        }
+        Commands::Grant { list: true, .. } => {
+            run_grant_list(&backend);
+        }
+        Commands::Grant {
+            principal,
+            services,
+            actions,
+            dry_run,
+            ..
+        } => {
+            let actions: Vec<GrantAction> = actions.iter().map(|a| a.to_action()).collect();
+            let principal = principal.as_deref().unwrap_or_default();
+            run_grant(&backend, principal, services, &actions, *dry_run);
+        }
+        Commands::Revoke { principal, dry_run } => {
+            run_revoke(&backend, principal, *dry_run);
+        }
         Commands::List => {
             let services = list_corky_services(&backend);
             println!(
@@ -183,10 +696,17 @@ fn main() {
                 println!("  (none found)");
             }
             for s in &services {
-                println!("  {} ({})", s.name, s.backend.display_label());
+                match schedule_summary(s) {
+                    Some(summary) => {
+                        println!("  {} ({}) {}", s.name, s.backend.display_label(), summary)
+                    }
+                    None => println!("  {} ({})", s.name, s.backend.display_label()),
+                }
             }
         }
-        Commands::Completion { .. } => unreachable!(), // handled above
+        Commands::Completion { .. } | Commands::Doctor | Commands::Package { .. } => {
+            unreachable!() // handled above
+        }
         Commands::CompletionItems => {
             for s in list_corky_services(&backend) {
                 println!("{}", s.name.replace("corky-", ""));
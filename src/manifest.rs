@@ -0,0 +1,192 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::hardening::HardeningPreset;
+use crate::instance::split_instance;
+use crate::limits::ResourceLimits;
+use crate::readiness::ReadyMode;
+use crate::signal::DEFAULT_RELOAD_SIGNAL;
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Install manifest
+// ─────────────────────────────────────────────────────────────────────────────
+pub const MANIFEST_DIR: &str = "/var/lib/corky/manifests";
+
+/// Everything `corky install` used to render a service's init config.
+/// Written at install time so later commands can inspect or regenerate it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct InstallManifest {
+    pub service_name: String,
+    pub description: String,
+    pub backend: String,
+    pub exec_path: PathBuf,
+    #[serde(default)]
+    pub args: Vec<String>,
+    pub working_dir: PathBuf,
+    /// State/config/logs dirs under /var/lib, /etc and /var/log/corky/<name>
+    #[serde(default)]
+    pub managed_dirs: bool,
+    pub user: String,
+    pub group: String,
+    /// systemd allocates the user at start (DynamicUser=yes)
+    #[serde(default)]
+    pub dynamic_user: bool,
+    /// corky created `user` and may remove it on uninstall
+    #[serde(default)]
+    pub created_user: bool,
+    #[serde(default)]
+    pub environment: BTreeMap<String, String>,
+    #[serde(default = "default_autostart")]
+    pub autostart: bool,
+    #[serde(default)]
+    pub hardening: HardeningPreset,
+    #[serde(default)]
+    pub hardening_overrides: BTreeMap<String, String>,
+    #[serde(default)]
+    pub limits: ResourceLimits,
+    /// OnCalendar expression for scheduled jobs; None for daemons
+    #[serde(default)]
+    pub schedule: Option<String>,
+    /// Socket activation addresses (systemd ListenStream=)
+    #[serde(default)]
+    pub listen: Vec<String>,
+    /// Installed as a template (`<name>@.service`) run once per instance
+    #[serde(default)]
+    pub template: bool,
+    /// Instance ids added with `corky instance add`
+    #[serde(default)]
+    pub instances: Vec<String>,
+    /// `corky enable/disable <name>@<id>` on supervisor, overriding `autostart`
+    #[serde(default)]
+    pub instance_autostart: BTreeMap<String, bool>,
+    /// Started means READY=1 from corky-sdk rather than a running process
+    #[serde(default)]
+    pub ready: ReadyMode,
+    /// systemd WatchdogSec= for notify services; 0 disables
+    #[serde(default)]
+    pub watchdog_sec: u64,
+    /// Sent by `corky reload` (systemd ExecReload=), without the SIG prefix
+    #[serde(default = "default_reload_signal")]
+    pub reload_signal: String,
+    #[serde(default)]
+    pub checksum: String,
+}
+
+fn default_autostart() -> bool {
+    true
+}
+
+fn default_reload_signal() -> String {
+    DEFAULT_RELOAD_SIGNAL.to_string()
+}
+
+pub fn manifest_path(service_name: &str) -> PathBuf {
+    PathBuf::from(MANIFEST_DIR).join(format!("{}.toml", service_name))
+}
+
+/// Left by a package's postinst when it created the service user. Upgrades
+/// ship a fresh manifest, so the postinst re-applies `created_user` from it.
+pub fn created_user_marker_path(service_name: &str) -> PathBuf {
+    PathBuf::from(MANIFEST_DIR).join(format!("{}.created-user", service_name))
+}
+
+/// The service's manifest; an instance (`name@id`) resolves to its template's.
+pub fn load_manifest(service_name: &str) -> Option<InstallManifest> {
+    let read = |name: &str| -> Option<InstallManifest> {
+        toml::from_str(&fs::read_to_string(manifest_path(name)).ok()?).ok()
+    };
+    read(service_name).or_else(|| {
+        let (template, _) = split_instance(service_name)?;
+        read(template).filter(|m| m.template)
+    })
+}
+
+/// Every recorded manifest, i.e. the registry of corky-managed services.
+pub fn list_manifests() -> Vec<InstallManifest> {
+    let Ok(entries) = fs::read_dir(MANIFEST_DIR) else {
+        return Vec::new();
+    };
+    let mut manifests: Vec<InstallManifest> = entries
+        .flatten()
+        .filter(|e| e.path().extension().is_some_and(|ext| ext == "toml"))
+        .filter_map(|e| toml::from_str(&fs::read_to_string(e.path()).ok()?).ok())
+        .collect();
+    manifests.sort_by(|a, b| a.service_name.cmp(&b.service_name));
+    manifests
+}
+
+pub fn write_manifest(manifest: &InstallManifest) -> io::Result<PathBuf> {
+    let path = manifest_path(&manifest.service_name);
+    fs::create_dir_all(MANIFEST_DIR)?;
+    let content = toml::to_string_pretty(manifest)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    fs::write(&path, content)?;
+    Ok(path)
+}
+
+pub fn remove_manifest(service_name: &str) -> io::Result<()> {
+    for path in [manifest_path(service_name), created_user_marker_path(service_name)] {
+        if Path::new(&path).exists() {
+            fs::remove_file(path)?;
+        }
+    }
+    Ok(())
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Tests
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manifest_path() {
+        assert_eq!(
+            manifest_path("corky-zmq"),
+            PathBuf::from("/var/lib/corky/manifests/corky-zmq.toml")
+        );
+    }
+
+    #[test]
+    fn test_manifest_roundtrip() {
+        let manifest = InstallManifest {
+            service_name: "corky-test".to_string(),
+            description: "Test service".to_string(),
+            backend: "systemd".to_string(),
+            exec_path: PathBuf::from("/usr/local/bin/corky-test"),
+            args: vec!["--verbose".to_string()],
+            working_dir: PathBuf::from("/opt/corky"),
+            managed_dirs: true,
+            user: "appuser".to_string(),
+            group: "appgroup".to_string(),
+            dynamic_user: false,
+            created_user: true,
+            environment: BTreeMap::from([("RUST_LOG".to_string(), "info".to_string())]),
+            autostart: false,
+            hardening: HardeningPreset::Strict,
+            hardening_overrides: BTreeMap::from([("PrivateTmp".to_string(), String::new())]),
+            limits: ResourceLimits {
+                memory_max: Some("1G".to_string()),
+                nice: Some(-5),
+                ..ResourceLimits::default()
+            },
+            schedule: Some("*-*-* 03:00".to_string()),
+            listen: vec!["127.0.0.1:8080".to_string()],
+            template: true,
+            instances: vec!["eu".to_string()],
+            instance_autostart: BTreeMap::from([("eu".to_string(), false)]),
+            ready: ReadyMode::Notify,
+            watchdog_sec: 30,
+            reload_signal: "USR1".to_string(),
+            checksum: "00ff".to_string(),
+        };
+        let text = toml::to_string_pretty(&manifest).unwrap();
+        let parsed: InstallManifest = toml::from_str(&text).unwrap();
+        assert_eq!(parsed, manifest);
+    }
+}
@@ -0,0 +1,701 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use corky_sdk::READY_FILE_ENV;
+
+use crate::deploy::managed_dir_env;
+use crate::envfile::{read_env_file, redact_config, write_config_file};
+use crate::escalate::escalator_or_exit;
+use crate::hardening::HardeningPreset;
+use crate::init::{
+    elevate_privileges, exit_error, generate_supervisor_conf, is_root, is_supervisor_available,
+    is_systemd_available, orphaned_configs, primary_group_for_user, render_systemd_unit,
+    run_cmd, run_cmd_expect_ok, run_cmd_quiet, section, supervisor_conf_path, systemd_unit_path,
+    InitBackend, ServiceInfo, UNIT_DIR_SYSTEM, C_BGREEN, C_CYAN, C_GREEN, C_RESET, C_YELLOW,
+};
+use crate::limits::{strip_limit_wrapper, ResourceLimits};
+use crate::logs::print_failure_context;
+use crate::socket::remove_socket;
+use crate::manifest::{load_manifest, write_manifest, InstallManifest};
+use crate::readiness::ReadyMode;
+use crate::schedule::{
+    cron_job_enabled, cron_path, install_cron_job, install_systemd_timer, oncalendar_to_cron,
+    remove_schedule, set_schedule_enabled, CRON_DIR,
+};
+use crate::signal::{normalize_signal, DEFAULT_RELOAD_SIGNAL};
+use crate::users::{created_by_corky, ensure_system_user};
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Migration between init systems
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Move every corky service from the other init system onto `target`,
+/// one at a time, removing each old config once the new one is running.
+pub fn migrate_services(target: &InitBackend, dry_run: bool) {
+    if !is_root() && !dry_run {
+        println!(
+            "{C_GREEN}[INFO]{C_RESET} Elevating with {} to migrate services...",
+            escalator_or_exit()
+        );
+        let args: Vec<String> = env::args().skip(1).collect();
+        elevate_privileges(&args, &[]);
+    }
+
+    let available = match target {
+        InitBackend::Systemd { .. } => is_systemd_available(),
+        InitBackend::Supervisor => is_supervisor_available(),
+    };
+    if !available {
+        if dry_run {
+            eprintln!("{C_YELLOW}[WARN]{C_RESET} {} is not available on this host.", target);
+        } else {
+            exit_error(&format!("Cannot migrate: {} is not available on this host.", target));
+        }
+    }
+
+    let sources = migration_sources(target);
+    if sources.is_empty() {
+        println!("{C_GREEN}[INFO]{C_RESET} No corky services to migrate to {}.", target);
+        return;
+    }
+
+    let mut migrated = 0;
+    for path in &sources {
+        let Some(name) = path.file_stem().map(|s| s.to_string_lossy().to_string()) else {
+            continue;
+        };
+        section(&format!("Migrating {} to {}", name, target));
+        if load_manifest(name.trim_end_matches('@')).is_some_and(|m| m.template) {
+            eprintln!(
+                "{C_YELLOW}[WARN]{C_RESET} {} is a templated service; reinstall it with `corky install` on {} and re-add its instances.",
+                name.trim_end_matches('@'),
+                target
+            );
+            continue;
+        }
+
+        let content = fs::read_to_string(path).unwrap_or_else(|e| {
+            exit_error(&format!("Read {}: {}", path.display(), e));
+        });
+        let parsed = match target {
+            // A cron.d entry isn't parsed back; the manifest has the whole job
+            _ if path.starts_with(CRON_DIR) => load_manifest(&name),
+            InitBackend::Systemd { .. } => parse_supervisor_conf(&name, &content),
+            InitBackend::Supervisor => parse_systemd_unit(&name, &content),
+        };
+        let Some(mut manifest) = parsed else {
+            eprintln!(
+                "{C_YELLOW}[WARN]{C_RESET} Could not find a command in {}; skipping.",
+                path.display()
+            );
+            continue;
+        };
+        if manifest.group.is_empty() {
+            manifest.group = primary_group_for_user(&manifest.user);
+        }
+        if manifest.description.is_empty() {
+            manifest.description = "corky service".to_string();
+        }
+        if let Some(previous) = load_manifest(&name) {
+            manifest.created_user = created_by_corky(Some(&previous), &manifest.user);
+            manifest.checksum = previous.checksum;
+            manifest.hardening = previous.hardening;
+            manifest.hardening_overrides = previous.hardening_overrides;
+            manifest.managed_dirs = previous.managed_dirs;
+            manifest.limits = previous.limits;
+            manifest.schedule = previous.schedule;
+            manifest.listen = previous.listen;
+            if let InitBackend::Systemd { .. } = target {
+                // Migrating from supervisor: its conf doesn't record the signal
+                manifest.reload_signal = previous.reload_signal;
+            }
+        }
+        if *target == InitBackend::Supervisor && !manifest.listen.is_empty() {
+            eprintln!(
+                "{C_YELLOW}[WARN]{C_RESET} {} is socket-activated, which supervisor can't do; remove `listen` and reinstall it with `corky install`.",
+                name
+            );
+            continue;
+        }
+        if *target == InitBackend::Supervisor
+            && let Some(schedule) = &manifest.schedule
+            && let Err(e) = oncalendar_to_cron(schedule)
+        {
+            eprintln!("{C_YELLOW}[WARN]{C_RESET} {}: schedule {}; cron can't run it.", name, e);
+            continue;
+        }
+        // Scheduled jobs are on when their timer or cron line is
+        manifest.autostart = match (target, manifest.schedule.is_some()) {
+            // The unit file doesn't say whether it's enabled; systemd does
+            (InitBackend::Supervisor, false) => systemd_unit_enabled(&format!("{}.service", name)),
+            (InitBackend::Supervisor, true) => systemd_unit_enabled(&format!("{}.timer", name)),
+            (InitBackend::Systemd { .. }, true) => cron_job_enabled(&name),
+            (InitBackend::Systemd { .. }, false) => manifest.autostart,
+        };
+        if *target == InitBackend::Supervisor && manifest.dynamic_user {
+            // Supervisor can't allocate users on the fly; make the account real
+            manifest.created_user |= ensure_system_user(&manifest.user, dry_run);
+            manifest.group = manifest.user.clone();
+            manifest.dynamic_user = false;
+        }
+        manifest.backend = target.to_string();
+
+        match target {
+            InitBackend::Systemd { .. } => switch_to_systemd(&manifest, path, dry_run),
+            InitBackend::Supervisor => switch_to_supervisor(&manifest, path, dry_run),
+        }
+
+        if !dry_run && let Err(e) = write_manifest(&manifest) {
+            eprintln!("{C_YELLOW}[WARN]{C_RESET} Could not write install manifest: {}", e);
+        }
+        migrated += 1;
+    }
+
+    section("Done");
+    if dry_run {
+        println!(
+            "{C_CYAN}[DRY-RUN]{C_RESET} Would migrate {} service(s) to {}.",
+            migrated, target
+        );
+    } else {
+        println!(
+            "{C_BGREEN}[SUCCESS]{C_RESET} Migrated {} service(s) to {}.",
+            migrated, target
+        );
+    }
+}
+
+/// Configs to migrate onto `target`: the other init system's, plus cron.d
+/// entries of jobs scheduled on a supervisor host, which have no program.
+fn migration_sources(target: &InitBackend) -> Vec<PathBuf> {
+    let mut sources = orphaned_configs(target);
+    if *target == InitBackend::Supervisor {
+        return sources;
+    }
+    let supervisor = InitBackend::Supervisor.to_string();
+    for entry in fs::read_dir(CRON_DIR).into_iter().flatten().flatten() {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name.starts_with("corky-")
+            && load_manifest(&name).is_some_and(|m| m.schedule.is_some() && m.backend == supervisor)
+        {
+            sources.push(entry.path());
+        }
+    }
+    sources
+}
+
+fn switch_to_systemd(manifest: &InstallManifest, old_conf: &Path, dry_run: bool) {
+    let name = manifest.service_name.as_str();
+    let unit_path = systemd_unit_path(name);
+    let unit = render_systemd_unit(manifest);
+    let supervisor_up = is_supervisor_available();
+
+    if let Some(schedule) = &manifest.schedule {
+        // The cron entry becomes a timer
+        remove_schedule(dry_run, false, name);
+        install_systemd_timer(dry_run, false, manifest, schedule);
+        if !manifest.autostart {
+            disable_schedule(&InitBackend::Systemd { scope: "system".to_string() }, name, dry_run);
+        }
+        if old_conf != cron_path(name) {
+            remove_old_config(old_conf, dry_run);
+        }
+        if !dry_run {
+            println!("{C_BGREEN}[OK]{C_RESET} {} now scheduled by systemd", name);
+        }
+        return;
+    }
+
+    if dry_run {
+        println!("{C_CYAN}[DRY-RUN]{C_RESET} Would run: supervisorctl stop {}", name);
+        show_would_write(&unit_path, &unit, name);
+        println!("{C_CYAN}[DRY-RUN]{C_RESET} Would run: systemctl daemon-reload");
+        if manifest.autostart {
+            println!("{C_CYAN}[DRY-RUN]{C_RESET} Would run: systemctl enable --now {}", name);
+        }
+        remove_schedule(dry_run, false, name);
+        remove_old_config(old_conf, dry_run);
+        return;
+    }
+
+    if supervisor_up {
+        let _ = run_cmd("supervisorctl", &["stop", name]);
+    }
+    write_config(&InitBackend::Systemd { scope: "system".to_string() }, &unit_path, &unit, name);
+    run_cmd_expect_ok("systemctl", &["daemon-reload"]);
+    if manifest.autostart {
+        run_cmd_expect_ok("systemctl", &["enable", name]);
+        if !run_cmd("systemctl", &["start", name]) {
+            print_failure_context(&ServiceInfo {
+                name: name.to_string(),
+                backend: InitBackend::Systemd {
+                    scope: "system".to_string(),
+                },
+            });
+            exit_error(&format!(
+                "{} failed to start under systemd; old config kept at {}",
+                name,
+                old_conf.display()
+            ));
+        }
+    } else {
+        println!(
+            "{C_YELLOW}[SKIP]{C_RESET} {} had autostart=false; left disabled and stopped",
+            name
+        );
+    }
+
+    remove_schedule(dry_run, false, name);
+    remove_old_config(old_conf, dry_run);
+    if supervisor_up {
+        let _ = run_cmd("supervisorctl", &["reread"]);
+        let _ = run_cmd("supervisorctl", &["update"]);
+    }
+    println!("{C_BGREEN}[OK]{C_RESET} {} now managed by systemd", name);
+}
+
+fn switch_to_supervisor(manifest: &InstallManifest, old_unit: &Path, dry_run: bool) {
+    let name = manifest.service_name.as_str();
+    let conf_path = supervisor_conf_path(name);
+    let conf = generate_supervisor_conf(manifest);
+    let systemd_up = is_systemd_available();
+
+    if let Some(schedule) = &manifest.schedule {
+        // The timer becomes a cron entry
+        if dry_run {
+            println!("{C_CYAN}[DRY-RUN]{C_RESET} Would run: systemctl disable --now {}", name);
+        } else if systemd_up {
+            let _ = run_cmd("systemctl", &["disable", "--now", name]);
+        }
+        remove_schedule(dry_run, !systemd_up, name);
+        install_cron_job(dry_run, manifest, schedule);
+        if !manifest.autostart {
+            disable_schedule(&InitBackend::Supervisor, name, dry_run);
+        }
+        remove_old_config(old_unit, dry_run);
+        if !dry_run {
+            if systemd_up {
+                let _ = run_cmd("systemctl", &["daemon-reload"]);
+            }
+            println!("{C_BGREEN}[OK]{C_RESET} {} now scheduled by cron", name);
+        }
+        return;
+    }
+
+    if dry_run {
+        println!("{C_CYAN}[DRY-RUN]{C_RESET} Would run: systemctl disable --now {}", name);
+        remove_schedule(dry_run, false, name);
+        remove_socket(dry_run, false, name);
+        show_would_write(&conf_path, &conf, name);
+        println!("{C_CYAN}[DRY-RUN]{C_RESET} Would run: supervisorctl reread");
+        println!("{C_CYAN}[DRY-RUN]{C_RESET} Would run: supervisorctl update");
+        remove_old_config(old_unit, dry_run);
+        return;
+    }
+
+    if systemd_up {
+        let _ = run_cmd("systemctl", &["stop", name]);
+        let _ = run_cmd("systemctl", &["disable", name]);
+    }
+    remove_schedule(dry_run, !systemd_up, name);
+    remove_socket(dry_run, !systemd_up, name);
+    if let Some(dir) = conf_path.parent() {
+        let _ = fs::create_dir_all(dir);
+    }
+    write_config(&InitBackend::Supervisor, &conf_path, &conf, name);
+    run_cmd_expect_ok("supervisorctl", &["reread"]);
+    run_cmd_expect_ok("supervisorctl", &["update"]);
+    if manifest.autostart {
+        // update usually starts it already; "already started" is fine
+        let _ = run_cmd_quiet("supervisorctl", &["start", name]);
+        if !run_cmd_quiet("supervisorctl", &["status", name]) {
+            print_failure_context(&ServiceInfo {
+                name: name.to_string(),
+                backend: InitBackend::Supervisor,
+            });
+            exit_error(&format!(
+                "{} failed to start under supervisor; old unit kept at {}",
+                name,
+                old_unit.display()
+            ));
+        }
+    }
+
+    remove_old_config(old_unit, dry_run);
+    if systemd_up {
+        let _ = run_cmd("systemctl", &["daemon-reload"]);
+        let _ = run_cmd_quiet("systemctl", &["reset-failed", name]);
+    }
+    println!("{C_BGREEN}[OK]{C_RESET} {} now managed by supervisor", name);
+}
+
+/// Whether systemd starts `unit` at boot, from `systemctl is-enabled` or,
+/// without systemctl, the unit's .wants/ symlinks.
+fn systemd_unit_enabled(unit: &str) -> bool {
+    // is-enabled exits non-zero for disabled units but still prints the state
+    if let Ok(output) = Command::new("systemctl").args(["is-enabled", unit]).output()
+        && let Some(state) = String::from_utf8_lossy(&output.stdout).lines().next()
+    {
+        return is_enabled_state(state);
+    }
+    fs::read_dir(UNIT_DIR_SYSTEM)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .any(|e| e.file_name().to_string_lossy().ends_with(".wants") && e.path().join(unit).exists())
+}
+
+/// Carry a switched-off schedule over; installing one always enables it.
+fn disable_schedule(backend: &InitBackend, name: &str, dry_run: bool) {
+    if dry_run {
+        println!("{C_CYAN}[DRY-RUN]{C_RESET} Would disable the schedule for {}", name);
+        return;
+    }
+    set_schedule_enabled(
+        &ServiceInfo {
+            name: name.to_string(),
+            backend: backend.clone(),
+        },
+        false,
+    );
+}
+
+/// `systemctl is-enabled` states that mean the unit starts at boot.
+fn is_enabled_state(state: &str) -> bool {
+    matches!(state.trim(), "enabled" | "enabled-runtime" | "alias")
+}
+
+fn show_would_write(path: &Path, contents: &str, service_name: &str) {
+    println!("{C_CYAN}[DRY-RUN]{C_RESET} Would write: {}", path.display());
+    println!(
+        "---------- {} ----------\n{}",
+        path.display(),
+        redact_config(contents, service_name)
+    );
+}
+
+fn write_config(backend: &InitBackend, path: &Path, contents: &str, service_name: &str) {
+    write_config_file(backend, path, contents, service_name).unwrap_or_else(|e| {
+        exit_error(&format!("write {}: {}", path.display(), e));
+    });
+    println!("{C_GREEN}[INFO]{C_RESET} Wrote {}", path.display());
+}
+
+fn remove_old_config(path: &Path, dry_run: bool) {
+    if dry_run {
+        println!("{C_CYAN}[DRY-RUN]{C_RESET} Would remove: {}", path.display());
+        return;
+    }
+    match fs::remove_file(path) {
+        Ok(()) => println!("{C_GREEN}[INFO]{C_RESET} Removed {}", path.display()),
+        Err(e) => eprintln!("{C_YELLOW}[WARN]{C_RESET} remove {}: {}", path.display(), e),
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Config parsing
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Read a corky systemd unit back into a manifest. Returns None without ExecStart.
+pub fn parse_systemd_unit(service_name: &str, text: &str) -> Option<InstallManifest> {
+    let mut manifest = empty_manifest(service_name);
+    let mut command = None;
+    let mut section = "";
+
+    for line in text.lines() {
+        let line = line.trim();
+        if line.starts_with('[') && line.ends_with(']') {
+            section = line;
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let value = value.trim();
+        match (section, key.trim()) {
+            ("[Unit]", "Description") => manifest.description = value.to_string(),
+            ("[Service]", "User") => manifest.user = value.to_string(),
+            ("[Service]", "Group") => manifest.group = value.to_string(),
+            ("[Service]", "DynamicUser") => manifest.dynamic_user = value == "yes",
+            ("[Service]", "StateDirectory") => manifest.managed_dirs = true,
+            ("[Service]", "Type") if value == "notify" => manifest.ready = ReadyMode::Notify,
+            ("[Service]", "ExecReload") => {
+                // /bin/kill -<SIG> $MAINPID
+                if let Some(signal) = value
+                    .split_whitespace()
+                    .find_map(|w| w.strip_prefix('-'))
+                    .and_then(|s| normalize_signal(s).ok())
+                {
+                    manifest.reload_signal = signal;
+                }
+            }
+            ("[Service]", "WatchdogSec") => {
+                manifest.watchdog_sec = value.trim_end_matches('s').parse().unwrap_or(0)
+            }
+            ("[Service]", "MemoryMax") => manifest.limits.memory_max = Some(value.to_string()),
+            ("[Service]", "CPUQuota") => manifest.limits.cpu_quota = Some(value.to_string()),
+            ("[Service]", "TasksMax") => manifest.limits.tasks_max = value.parse().ok(),
+            ("[Service]", "LimitNOFILE") => manifest.limits.nofile = value.parse().ok(),
+            ("[Service]", "Nice") => manifest.limits.nice = value.parse().ok(),
+            ("[Service]", "OOMScoreAdjust") => manifest.limits.oom_score_adjust = value.parse().ok(),
+            ("[Service]", "WorkingDirectory") => {
+                manifest.working_dir = PathBuf::from(value.trim_start_matches('-'))
+            }
+            ("[Service]", "ExecStart") => {
+                let value = value.trim_start_matches(['-', '@', ':', '+', '!']);
+                command = Some(
+                    split_words(value, None)
+                        .iter()
+                        .map(|w| systemd_unescape(w))
+                        .collect::<Vec<_>>(),
+                );
+            }
+            ("[Service]", "Environment") => {
+                for word in split_words(value, None) {
+                    if let Some((k, v)) = word.split_once('=') {
+                        manifest
+                            .environment
+                            .insert(k.to_string(), systemd_unescape(v));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut command = command?.into_iter();
+    manifest.exec_path = PathBuf::from(command.next()?);
+    manifest.args = command.collect();
+    Some(manifest)
+}
+
+/// Read a corky supervisor program back into a manifest. Returns None without command=.
+pub fn parse_supervisor_conf(service_name: &str, text: &str) -> Option<InstallManifest> {
+    let mut manifest = empty_manifest(service_name);
+    let mut command = None;
+
+    for line in text.lines() {
+        let line = line.trim();
+        if let Some(comment) = line.strip_prefix(';') {
+            let comment = comment.trim();
+            if manifest.description.is_empty() && !comment.starts_with("Managed by corky") {
+                manifest.description = comment.to_string();
+            }
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let value = value.trim();
+        match key.trim() {
+            "command" => {
+                command = Some(
+                    split_words(value, None)
+                        .iter()
+                        .map(|w| w.replace("%%", "%"))
+                        .collect::<Vec<_>>(),
+                )
+            }
+            "directory" => manifest.working_dir = PathBuf::from(value),
+            "user" => manifest.user = value.to_string(),
+            "autostart" => manifest.autostart = value.eq_ignore_ascii_case("true"),
+            "environment" => {
+                for pair in split_words(value, Some(',')) {
+                    if let Some((k, v)) = pair.split_once('=') {
+                        manifest
+                            .environment
+                            .insert(k.trim().to_string(), v.replace("%%", "%"));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    // Supervisor configs always carry this default; don't turn it into an explicit setting.
+    if manifest.environment.get("RUST_LOG_STYLE").map(String::as_str) == Some("always") {
+        manifest.environment.remove("RUST_LOG_STYLE");
+    }
+    // Managed directory variables are implied by managed_dirs.
+    let dir_env = managed_dir_env(service_name);
+    if dir_env.iter().all(|(k, dir)| {
+        manifest.environment.get(*k).map(String::as_str) == Some(&*dir.to_string_lossy())
+    }) {
+        manifest.managed_dirs = true;
+        for (k, _) in &dir_env {
+            manifest.environment.remove(*k);
+        }
+    }
+    // The ready file is implied by ready = "notify".
+    if manifest.environment.remove(READY_FILE_ENV).is_some() {
+        manifest.ready = ReadyMode::Notify;
+    }
+    // Values inlined from the env file stay there instead of moving into the manifest.
+    let from_env_file = read_env_file(service_name);
+    manifest
+        .environment
+        .retain(|k, v| from_env_file.get(k) != Some(v));
+
+    let (command, wrapped_identity) = strip_limit_wrapper(command?);
+    if let Some((user, group)) = wrapped_identity {
+        manifest.user = user;
+        manifest.group = group;
+    }
+    let mut command = command.into_iter();
+    manifest.exec_path = PathBuf::from(command.next()?);
+    manifest.args = command.collect();
+    Some(manifest)
+}
+
+fn empty_manifest(service_name: &str) -> InstallManifest {
+    InstallManifest {
+        service_name: service_name.to_string(),
+        description: String::new(),
+        backend: String::new(),
+        exec_path: PathBuf::new(),
+        args: Vec::new(),
+        working_dir: PathBuf::from("/"),
+        managed_dirs: false,
+        user: "root".to_string(),
+        group: String::new(),
+        dynamic_user: false,
+        created_user: false,
+        environment: BTreeMap::new(),
+        autostart: true,
+        hardening: HardeningPreset::None,
+        hardening_overrides: BTreeMap::new(),
+        limits: ResourceLimits::default(),
+        schedule: None,
+        listen: Vec::new(),
+        template: false,
+        instances: Vec::new(),
+        instance_autostart: BTreeMap::new(),
+        ready: ReadyMode::Simple,
+        watchdog_sec: 0,
+        reload_signal: DEFAULT_RELOAD_SIGNAL.to_string(),
+        checksum: String::new(),
+    }
+}
+
+fn systemd_unescape(word: &str) -> String {
+    word.replace("%%", "%").replace("$$", "$")
+}
+
+/// Split on whitespace (and `sep`, if given), honouring single/double quotes
+/// and backslash escapes inside double quotes.
+pub fn split_words(input: &str, sep: Option<char>) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut in_word = false;
+    let mut chars = input.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                in_word = true;
+                while let Some(q) = chars.next() {
+                    match q {
+                        '"' => break,
+                        '\\' => {
+                            if let Some(escaped) = chars.next() {
+                                current.push(escaped);
+                            }
+                        }
+                        _ => current.push(q),
+                    }
+                }
+            }
+            '\'' => {
+                in_word = true;
+                for q in chars.by_ref() {
+                    if q == '\'' {
+                        break;
+                    }
+                    current.push(q);
+                }
+            }
+            c if c.is_whitespace() || Some(c) == sep => {
+                if in_word {
+                    words.push(std::mem::take(&mut current));
+                    in_word = false;
+                }
+            }
+            _ => {
+                in_word = true;
+                current.push(c);
+            }
+        }
+    }
+    if in_word {
+        words.push(current);
+    }
+    words
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Tests
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_words() {
+        assert_eq!(split_words("a  b", None), vec!["a", "b"]);
+        assert_eq!(split_words(r#"a "b c" 'd e'"#, None), vec!["a", "b c", "d e"]);
+        assert_eq!(split_words(r#""x\"y""#, None), vec![r#"x"y"#]);
+        assert_eq!(
+            split_words(r#"A="1,2",B="3""#, Some(',')),
+            vec!["A=1,2", "B=3"]
+        );
+    }
+
+    #[test]
+    fn test_systemd_roundtrip() {
+        let mut manifest = empty_manifest("corky-test");
+        manifest.description = "Test service".to_string();
+        manifest.exec_path = PathBuf::from("/usr/local/bin/corky-test");
+        manifest.args = vec!["--name".to_string(), "a b".to_string()];
+        manifest.working_dir = PathBuf::from("/opt/corky");
+        manifest.user = "appuser".to_string();
+        manifest.group = "appgroup".to_string();
+        manifest.environment.insert("PCT".to_string(), "50%".to_string());
+
+        let parsed = parse_systemd_unit("corky-test", &render_systemd_unit(&manifest)).unwrap();
+        assert_eq!(parsed, manifest);
+    }
+
+    #[test]
+    fn test_supervisor_roundtrip() {
+        let mut manifest = empty_manifest("corky-test");
+        manifest.description = "Test service".to_string();
+        manifest.exec_path = PathBuf::from("/usr/local/bin/corky-test");
+        manifest.args = vec!["--flag".to_string()];
+        manifest.working_dir = PathBuf::from("/opt/corky");
+        manifest.user = "appuser".to_string();
+        manifest.environment.insert("TOKEN".to_string(), "a,\"b\"".to_string());
+        manifest.autostart = false;
+
+        let parsed =
+            parse_supervisor_conf("corky-test", &generate_supervisor_conf(&manifest)).unwrap();
+        assert_eq!(parsed, manifest);
+    }
+
+    #[test]
+    fn test_is_enabled_state() {
+        assert!(is_enabled_state("enabled\n"));
+        assert!(is_enabled_state("enabled-runtime"));
+        assert!(!is_enabled_state("disabled"));
+        assert!(!is_enabled_state("static"));
+        assert!(!is_enabled_state("masked"));
+    }
+
+    #[test]
+    fn test_parse_requires_command() {
+        assert!(parse_systemd_unit("corky-x", "[Service]\nUser=a\n").is_none());
+        assert!(parse_supervisor_conf("corky-x", "[program:corky-x]\nuser=a\n").is_none());
+    }
+}
@@ -0,0 +1,524 @@
+use std::collections::BTreeSet;
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Package payloads (tar, gzip, ar, cpio) written without external tools
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// A file or directory to pack. Paths are relative to the filesystem root
+/// ("usr/local/bin/corky-api"); directories have no data.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PackEntry {
+    pub path: String,
+    pub mode: u32,
+    pub data: Option<Vec<u8>>,
+}
+
+impl PackEntry {
+    pub fn file(path: &str, mode: u32, data: Vec<u8>) -> Self {
+        PackEntry {
+            path: path.trim_start_matches('/').to_string(),
+            mode,
+            data: Some(data),
+        }
+    }
+
+    pub fn dir(path: &str) -> Self {
+        PackEntry {
+            path: path.trim_matches('/').to_string(),
+            mode: 0o755,
+            data: None,
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.data.as_ref().map_or(0, Vec::len)
+    }
+}
+
+/// `entries` preceded by every parent directory they need, each once.
+pub fn with_parent_dirs(entries: &[PackEntry]) -> Vec<PackEntry> {
+    let mut seen = BTreeSet::new();
+    let mut out = Vec::new();
+    for entry in entries {
+        let parts: Vec<&str> = entry.path.split('/').collect();
+        for depth in 1..parts.len() {
+            let dir = parts[..depth].join("/");
+            if seen.insert(dir.clone()) {
+                out.push(PackEntry::dir(&dir));
+            }
+        }
+        if entry.data.is_some() || seen.insert(entry.path.clone()) {
+            out.push(entry.clone());
+        }
+    }
+    out
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// tar (ustar)
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// A ustar archive of `entries`, owned by root, with names prefixed by
+/// `prefix` (e.g. "./" as dpkg expects).
+pub fn tar(entries: &[PackEntry], prefix: &str, mtime: u64) -> Result<Vec<u8>, String> {
+    let mut out = Vec::new();
+    for entry in entries {
+        let mut name = format!("{}{}", prefix, entry.path);
+        if entry.data.is_none() {
+            name.push('/');
+        }
+        out.extend_from_slice(&tar_header(&name, entry, mtime)?);
+        if let Some(data) = &entry.data {
+            out.extend_from_slice(data);
+            out.resize(out.len().next_multiple_of(512), 0);
+        }
+    }
+    // End-of-archive marker: two zero blocks
+    out.resize(out.len() + 1024, 0);
+    Ok(out)
+}
+
+fn tar_header(name: &str, entry: &PackEntry, mtime: u64) -> Result<[u8; 512], String> {
+    let mut header = [0u8; 512];
+    let (prefix, name) = split_ustar_name(name)
+        .ok_or_else(|| format!("path too long for a tar archive: {}", name))?;
+    put(&mut header, 0, 100, name.as_bytes());
+    put_octal(&mut header, 100, 8, u64::from(entry.mode & 0o7777));
+    put_octal(&mut header, 108, 8, 0);
+    put_octal(&mut header, 116, 8, 0);
+    put_octal(&mut header, 124, 12, entry.size() as u64);
+    put_octal(&mut header, 136, 12, mtime);
+    header[156] = if entry.data.is_some() { b'0' } else { b'5' };
+    put(&mut header, 257, 6, b"ustar\0");
+    put(&mut header, 263, 2, b"00");
+    put(&mut header, 265, 32, b"root");
+    put(&mut header, 297, 32, b"root");
+    put(&mut header, 345, 155, prefix.as_bytes());
+
+    // The checksum is computed with its own field read as spaces
+    header[148..156].fill(b' ');
+    let sum: u32 = header.iter().map(|&b| u32::from(b)).sum();
+    put(&mut header, 148, 8, format!("{:06o}\0 ", sum).as_bytes());
+    Ok(header)
+}
+
+/// Split a long name at a '/' into ustar's 155-byte prefix and 100-byte name.
+fn split_ustar_name(name: &str) -> Option<(&str, &str)> {
+    if name.len() <= 100 {
+        return Some(("", name));
+    }
+    name.match_indices('/')
+        .map(|(i, _)| (&name[..i], &name[i + 1..]))
+        .find(|(prefix, rest)| prefix.len() <= 155 && rest.len() <= 100 && !rest.is_empty())
+}
+
+fn put(buf: &mut [u8], offset: usize, len: usize, value: &[u8]) {
+    let n = value.len().min(len);
+    buf[offset..offset + n].copy_from_slice(&value[..n]);
+}
+
+/// Zero-padded octal, NUL-terminated, filling `len` bytes.
+fn put_octal(buf: &mut [u8], offset: usize, len: usize, value: u64) {
+    let text = format!("{:0width$o}\0", value, width = len - 1);
+    put(buf, offset, len, text.as_bytes());
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// gzip
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// gzip of `data`, deflated with LZ77 matching and deflate's fixed Huffman
+/// codes: no compressor needed, and every gunzip reads it.
+pub fn gzip(data: &[u8]) -> Vec<u8> {
+    let mut out = vec![0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 3];
+    out.extend_from_slice(&deflate(data));
+    out.extend_from_slice(&crc32(data).to_le_bytes());
+    out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    out
+}
+
+/// Match lengths for length codes 257..=285, and their extra bits.
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+/// Distances for distance codes 0..=29, and their extra bits.
+const DIST_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537, 2049,
+    3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DIST_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13,
+];
+
+const WINDOW: usize = 32 * 1024;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+/// Candidates tried per position; more compresses slightly better, slower.
+const MAX_CHAIN: usize = 64;
+
+/// One final deflate block with fixed Huffman codes (RFC 1951 3.2.6).
+fn deflate(data: &[u8]) -> Vec<u8> {
+    let mut bits = BitWriter::default();
+    bits.put(1, 1); // BFINAL
+    bits.put(1, 2); // BTYPE: fixed Huffman
+    let hash = |i: usize| {
+        ((usize::from(data[i]) << 10) ^ (usize::from(data[i + 1]) << 5) ^ usize::from(data[i + 2]))
+            & 0x7fff
+    };
+    // Most recent position per hash, and the previous one per window slot
+    let mut head = vec![usize::MAX; 1 << 15];
+    let mut prev = vec![usize::MAX; WINDOW];
+    let mut i = 0;
+    while i < data.len() {
+        let max = MAX_MATCH.min(data.len() - i);
+        let (mut best_len, mut best_dist) = (0, 0);
+        if max >= MIN_MATCH {
+            let mut candidate = head[hash(i)];
+            let mut chain = 0;
+            while candidate < i && i - candidate <= WINDOW && chain < MAX_CHAIN {
+                let len = data[candidate..]
+                    .iter()
+                    .zip(&data[i..i + max])
+                    .take_while(|(a, b)| a == b)
+                    .count();
+                if len > best_len {
+                    (best_len, best_dist) = (len, i - candidate);
+                    if len == max {
+                        break;
+                    }
+                }
+                // Slots are reused once the window moves on; stop at a newer entry
+                let next = prev[candidate % WINDOW];
+                if next >= candidate {
+                    break;
+                }
+                candidate = next;
+                chain += 1;
+            }
+        }
+        let step = if best_len >= MIN_MATCH {
+            bits.put_match(best_len, best_dist);
+            best_len
+        } else {
+            bits.put_symbol(u32::from(data[i]));
+            1
+        };
+        for j in i..i + step {
+            if j + MIN_MATCH <= data.len() {
+                let h = hash(j);
+                prev[j % WINDOW] = head[h];
+                head[h] = j;
+            }
+        }
+        i += step;
+    }
+    bits.put_symbol(256); // end of block
+    bits.finish()
+}
+
+/// Deflate's bit order: values least significant bit first, Huffman codes
+/// most significant bit first.
+#[derive(Default)]
+struct BitWriter {
+    out: Vec<u8>,
+    buffer: u64,
+    count: u32,
+}
+
+impl BitWriter {
+    fn put(&mut self, value: u32, len: u32) {
+        self.buffer |= u64::from(value) << self.count;
+        self.count += len;
+        while self.count >= 8 {
+            self.out.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.count -= 8;
+        }
+    }
+
+    fn put_code(&mut self, code: u32, len: u32) {
+        self.put(code.reverse_bits() >> (32 - len), len);
+    }
+
+    /// A literal byte, end of block (256) or length code (257..=285).
+    fn put_symbol(&mut self, symbol: u32) {
+        match symbol {
+            0..=143 => self.put_code(0x30 + symbol, 8),
+            144..=255 => self.put_code(0x190 + symbol - 144, 9),
+            256..=279 => self.put_code(symbol - 256, 7),
+            _ => self.put_code(0xc0 + symbol - 280, 8),
+        }
+    }
+
+    fn put_match(&mut self, len: usize, dist: usize) {
+        let code = LENGTH_BASE.iter().rposition(|&b| usize::from(b) <= len).unwrap_or(0);
+        self.put_symbol(257 + code as u32);
+        self.put((len - usize::from(LENGTH_BASE[code])) as u32, u32::from(LENGTH_EXTRA[code]));
+        let code = DIST_BASE.iter().rposition(|&b| usize::from(b) <= dist).unwrap_or(0);
+        self.put_code(code as u32, 5);
+        self.put((dist - usize::from(DIST_BASE[code])) as u32, u32::from(DIST_EXTRA[code]));
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.count > 0 {
+            self.out.push(self.buffer as u8);
+        }
+        self.out
+    }
+}
+
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut table = [0u32; 256];
+    for (i, slot) in table.iter_mut().enumerate() {
+        let mut c = i as u32;
+        for _ in 0..8 {
+            c = if c & 1 != 0 { 0xedb8_8320 ^ (c >> 1) } else { c >> 1 };
+        }
+        *slot = c;
+    }
+    let mut crc = !0u32;
+    for &byte in data {
+        crc = table[((crc ^ u32::from(byte)) & 0xff) as usize] ^ (crc >> 8);
+    }
+    !crc
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// ar (the .deb container)
+// ─────────────────────────────────────────────────────────────────────────────
+
+pub fn ar(members: &[(&str, &[u8])], mtime: u64) -> Vec<u8> {
+    let mut out = b"!<arch>\n".to_vec();
+    for (name, data) in members {
+        let header = format!(
+            "{:<16}{:<12}{:<6}{:<6}{:<8}{:<10}`\n",
+            name,
+            mtime,
+            0,
+            0,
+            "100644",
+            data.len()
+        );
+        out.extend_from_slice(header.as_bytes());
+        out.extend_from_slice(data);
+        if data.len() % 2 == 1 {
+            out.push(b'\n');
+        }
+    }
+    out
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// cpio (newc, the .rpm payload)
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// A newc cpio archive of the files in `entries`, named "./<path>".
+pub fn cpio(entries: &[PackEntry], mtime: u64) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut ino = 0;
+    for entry in entries {
+        let Some(data) = &entry.data else {
+            continue;
+        };
+        ino += 1;
+        cpio_record(&mut out, &format!("./{}", entry.path), ino, 0o100000 | entry.mode, 1, mtime, data);
+    }
+    cpio_record(&mut out, "TRAILER!!!", 0, 0, 1, 0, &[]);
+    out
+}
+
+fn cpio_record(out: &mut Vec<u8>, name: &str, ino: u32, mode: u32, nlink: u32, mtime: u64, data: &[u8]) {
+    let fields = [
+        ino,
+        mode,
+        0, // uid
+        0, // gid
+        nlink,
+        mtime as u32,
+        data.len() as u32,
+        0, // devmajor
+        0, // devminor
+        0, // rdevmajor
+        0, // rdevminor
+        name.len() as u32 + 1,
+        0, // check
+    ];
+    out.extend_from_slice(b"070701");
+    for field in fields {
+        out.extend_from_slice(format!("{:08x}", field).as_bytes());
+    }
+    out.extend_from_slice(name.as_bytes());
+    out.push(0);
+    out.resize(out.len().next_multiple_of(4), 0);
+    out.extend_from_slice(data);
+    out.resize(out.len().next_multiple_of(4), 0);
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// SHA-256 (rpm header and payload digests)
+// ─────────────────────────────────────────────────────────────────────────────
+
+const SHA256_K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+/// Lowercase hex SHA-256 of `data`.
+pub fn sha256_hex(data: &[u8]) -> String {
+    let mut h: [u32; 8] = [
+        0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+    ];
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&((data.len() as u64) * 8).to_be_bytes());
+
+    for block in message.chunks(64) {
+        let mut w = [0u32; 64];
+        for (i, word) in block.chunks(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(w[i - 7])
+                .wrapping_add(s1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut hh] = h;
+        for i in 0..64 {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let ch = (e & f) ^ (!e & g);
+            let t1 = hh
+                .wrapping_add(s1)
+                .wrapping_add(ch)
+                .wrapping_add(SHA256_K[i])
+                .wrapping_add(w[i]);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(maj);
+            hh = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(t1);
+            d = c;
+            c = b;
+            b = a;
+            a = t1.wrapping_add(t2);
+        }
+        for (state, value) in h.iter_mut().zip([a, b, c, d, e, f, g, hh]) {
+            *state = state.wrapping_add(value);
+        }
+    }
+    h.iter().map(|word| format!("{:08x}", word)).collect()
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Tests
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checksums() {
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        assert_eq!(
+            sha256_hex(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            sha256_hex(&[b'a'; 1000]),
+            "41edece42d63e8d9bf515a9ba6932e1c20cbc9f5a5d134645adb5db1b9737ea3"
+        );
+    }
+
+    #[test]
+    fn test_gzip() {
+        let gunzip = |gz: &[u8]| {
+            let mut child = std::process::Command::new("gzip")
+                .arg("-dc")
+                .stdin(std::process::Stdio::piped())
+                .stdout(std::process::Stdio::piped())
+                .spawn()
+                .unwrap();
+            std::io::Write::write_all(&mut child.stdin.take().unwrap(), gz).unwrap();
+            child.wait_with_output().unwrap().stdout
+        };
+        let text: Vec<u8> = (0..5_000)
+            .flat_map(|i| format!("line {} of the corky test payload\n", i % 700).into_bytes())
+            .collect();
+        let mut mixed = vec![0u8; 70_000];
+        mixed.extend((0..100_000u32).map(|i| (i.wrapping_mul(2_654_435_761) >> 13) as u8));
+        for data in [Vec::new(), b"a".to_vec(), b"abcabcabcabcabc".to_vec(), text.clone(), mixed] {
+            let gz = gzip(&data);
+            assert_eq!(&gz[..3], &[0x1f, 0x8b, 8]);
+            assert_eq!(&gz[gz.len() - 4..], &(data.len() as u32).to_le_bytes());
+            assert_eq!(gunzip(&gz), data);
+        }
+        assert!(gzip(&text).len() < text.len() / 4);
+    }
+
+    #[test]
+    fn test_tar_header() {
+        let entry = PackEntry::file("usr/local/bin/corky-api", 0o755, b"hello".to_vec());
+        let archive = tar(&[entry], "./", 0).unwrap();
+        assert_eq!(archive.len(), 512 + 512 + 1024);
+        assert!(archive.starts_with(b"./usr/local/bin/corky-api\0"));
+        assert_eq!(&archive[100..108], b"0000755\0");
+        assert_eq!(&archive[124..136], b"00000000005\0");
+        assert_eq!(&archive[257..263], b"ustar\0");
+        let stored: u32 = u32::from_str_radix(
+            std::str::from_utf8(&archive[148..154]).unwrap(),
+            8,
+        )
+        .unwrap();
+        let mut header = archive[..512].to_vec();
+        header[148..156].fill(b' ');
+        assert_eq!(stored, header.iter().map(|&b| u32::from(b)).sum::<u32>());
+        assert_eq!(&archive[512..517], b"hello");
+    }
+
+    #[test]
+    fn test_split_ustar_name() {
+        let long = format!("{}/{}", "d".repeat(120), "f".repeat(90));
+        let (prefix, name) = split_ustar_name(&long).unwrap();
+        assert_eq!((prefix.len(), name.len()), (120, 90));
+        assert!(split_ustar_name(&"x".repeat(300)).is_none());
+    }
+
+    #[test]
+    fn test_with_parent_dirs() {
+        let entries = with_parent_dirs(&[
+            PackEntry::file("etc/corky/a", 0o644, Vec::new()),
+            PackEntry::file("etc/corky/b", 0o644, Vec::new()),
+        ]);
+        let paths: Vec<&str> = entries.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(paths, ["etc", "etc/corky", "etc/corky/a", "etc/corky/b"]);
+    }
+
+    #[test]
+    fn test_ar_and_cpio_layout() {
+        let deb = ar(&[("debian-binary", b"2.0\n")], 0);
+        assert!(deb.starts_with(b"!<arch>\ndebian-binary   0  "));
+        assert_eq!(deb.len(), 8 + 60 + 4);
+
+        let payload = cpio(&[PackEntry::file("usr/bin/x", 0o755, b"abc".to_vec())], 0);
+        assert!(payload.starts_with(b"07070100000001000081ed"));
+        assert!(payload.ends_with(b"TRAILER!!!\0\0\0\0"));
+    }
+}
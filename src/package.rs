@@ -0,0 +1,679 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::archive::write_archive;
+use crate::build::{target_dir, BuildOptions};
+use crate::deploy::{asset_relative_path, managed_dir_env, state_dir};
+use crate::diff::expected_config_for;
+use crate::init::{
+    compute_file_checksum, corky_config, ensure_corky_prefix, exit_error, manifest_from_config,
+    pkg_name_and_description, primary_group_for_user, section, validate_corky_package_or_exit,
+    CorkyConfig, InitBackend, RenderHost, BIN_PATH_SYSTEM, C_BGREEN, C_GREEN, C_RESET, C_YELLOW,
+};
+use crate::instance::validate_template;
+use crate::limits::{supervisor_limit_wrapper, warn_unenforced_limits, WrapperTools};
+use crate::manifest::{created_user_marker_path, manifest_path, InstallManifest};
+use crate::pack::{ar, gzip, tar, with_parent_dirs, PackEntry};
+use crate::rpm::{build_rpm, RpmSpec};
+use crate::schedule::{render_timer_unit, timer_unit_path, validate_schedule};
+use crate::socket::{render_socket_unit, socket_unit_path, validate_listen};
+use crate::users::{useradd_args, ServiceUser, UserSpec};
+
+/// Limit wrappers assumed on the target host: util-linux's, not systemd-run.
+const TARGET_TOOLS: WrapperTools = WrapperTools {
+    systemd_run: false,
+    prlimit: true,
+    choom: true,
+    nice: true,
+    setpriv: true,
+};
+
+/// What `corky package` writes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PackageFormat {
+    /// An archive for `corky install --archive`.
+    Tar,
+    Deb,
+    Rpm,
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// corky package
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Build the package and write it as `format`. Deb and rpm packages target
+/// `backend` and carry everything `corky install` would put on the host.
+pub fn run_package(
+    format: PackageFormat,
+    backend: Option<&InitBackend>,
+    build_flags: &BuildOptions,
+    output: Option<&Path>,
+) {
+    section("Validating Corky package");
+    validate_corky_package_or_exit();
+    let (pkg_name, description) = pkg_name_and_description()
+        .unwrap_or_else(|| exit_error("Could not read the package name from Cargo.toml."));
+    let config = corky_config();
+    let build = config.build.with_overrides(build_flags);
+    if let Err(e) = build.validate() {
+        exit_error(&format!("[corky.build] {}", e));
+    }
+    let system_package = match (format, backend) {
+        (PackageFormat::Tar, Some(_)) => {
+            eprintln!(
+                "{C_YELLOW}[WARN]{C_RESET} --backend is ignored for archives; `corky install --archive` detects it."
+            );
+            None
+        }
+        (PackageFormat::Tar, None) => None,
+        (_, backend) => {
+            let backend = backend.cloned().unwrap_or(InitBackend::Systemd {
+                scope: "system".to_string(),
+            });
+            Some(SystemPackage::prepare(format, backend, &pkg_name, &description, &config, &build))
+        }
+    };
+
+    section(&format!("Building ({})", build.profile()));
+    build.build_or_exit();
+    let binary = build.artifact_path(&pkg_name);
+    let checksum = compute_file_checksum(&binary)
+        .unwrap_or_else(|| exit_error(&format!("Could not read {}", binary.display())));
+
+    let Some(mut package) = system_package else {
+        section("Writing archive");
+        let output = output.map(Path::to_path_buf).unwrap_or_else(|| {
+            target_dir()
+                .join("corky")
+                .join(format!("{}.tar.gz", ensure_corky_prefix(&pkg_name)))
+        });
+        if let Some(parent) = output.parent().filter(|p| !p.as_os_str().is_empty()) {
+            let _ = fs::create_dir_all(parent);
+        }
+        let mtime = source_date_epoch();
+        write_archive(&output, &pkg_name, &description, &binary, &checksum, &config.assets, mtime)
+            .unwrap_or_else(|e| exit_error(&e));
+        println!("{C_GREEN}[INFO]{C_RESET} Checksum: {}", checksum);
+        println!("{C_BGREEN}[OK]{C_RESET} Wrote {}", output.display());
+        println!("  Install with: corky install --archive {}", output.display());
+        return;
+    };
+
+    section(&format!("Writing .{}", package.extension()));
+    package.manifest.checksum = checksum;
+    let data = fs::read(&binary)
+        .unwrap_or_else(|e| exit_error(&format!("Read {}: {}", binary.display(), e)));
+    let files = package
+        .files(data, &config.assets)
+        .unwrap_or_else(|e| exit_error(&e));
+    let output = output
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| target_dir().join("corky").join(package.file_name()));
+    if let Some(parent) = output.parent().filter(|p| !p.as_os_str().is_empty()) {
+        let _ = fs::create_dir_all(parent);
+    }
+    let bytes = package.encode(&files).unwrap_or_else(|e| exit_error(&e));
+    fs::write(&output, bytes)
+        .unwrap_or_else(|e| exit_error(&format!("write {}: {}", output.display(), e)));
+    for file in &files {
+        println!("{C_GREEN}[INFO]{C_RESET} /{}", file.path);
+    }
+    println!("{C_BGREEN}[OK]{C_RESET} Wrote {}", output.display());
+    match format {
+        PackageFormat::Deb => println!("  Install with: apt install ./{}", package.file_name()),
+        _ => println!("  Install with: dnf install ./{}", package.file_name()),
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// deb / rpm contents
+// ─────────────────────────────────────────────────────────────────────────────
+
+struct SystemPackage {
+    format: PackageFormat,
+    backend: InitBackend,
+    /// Lowercase package name, e.g. "corky-api".
+    name: String,
+    version: String,
+    arch: String,
+    license: String,
+    maintainer: String,
+    /// The postinst creates the service user when it doesn't exist yet.
+    create_user: bool,
+    manifest: InstallManifest,
+}
+
+impl SystemPackage {
+    /// Check the `[corky]` settings against `backend` and work out the
+    /// manifest the installed package will record.
+    fn prepare(
+        format: PackageFormat,
+        backend: InitBackend,
+        pkg_name: &str,
+        description: &str,
+        config: &CorkyConfig,
+        build: &BuildOptions,
+    ) -> SystemPackage {
+        let user_spec: UserSpec = match &config.user {
+            Some(value) => value
+                .parse()
+                .unwrap_or_else(|e| exit_error(&format!("[corky] user: {}", e))),
+            None => UserSpec::Invoker,
+        };
+        if let Err(e) = config.limits.validate() {
+            exit_error(&format!("[corky.limits] {}", e));
+        }
+        if let Some(schedule) = &config.schedule {
+            validate_schedule(&backend, schedule);
+        }
+        validate_listen(&backend, &config.listen, config.schedule.is_some());
+        validate_template(config);
+        if config.managed_dirs == Some(false) {
+            exit_error(
+                "[corky] managed_dirs = false runs from the checkout, which a package can't ship. \
+                 Remove it to package this service.",
+            );
+        }
+
+        let cargo_toml = fs::read_to_string("Cargo.toml")
+            .unwrap_or_else(|e| exit_error(&format!("Cargo.toml: {}", e)));
+        let meta = PackageMeta::parse(&cargo_toml).unwrap_or_else(|e| exit_error(&e));
+        let service_name = ensure_corky_prefix(pkg_name);
+        let identity = package_identity(&user_spec, &backend, &service_name);
+        if backend == InitBackend::Supervisor {
+            let (_, unenforced) =
+                supervisor_limit_wrapper(&config.limits, &identity.user, &identity.group, TARGET_TOOLS);
+            warn_unenforced_limits(&unenforced);
+            println!(
+                "{C_GREEN}[INFO]{C_RESET} Env file values aren't packaged; set them on the host with `corky env set`."
+            );
+        }
+        let create_user = identity.created;
+        // Only the postinst knows whether the account was missing; it records that
+        let manifest = manifest_from_config(
+            config,
+            &backend,
+            &service_name,
+            description,
+            Path::new(BIN_PATH_SYSTEM).join(pkg_name),
+            state_dir(&service_name),
+            ServiceUser {
+                created: false,
+                ..identity
+            },
+        );
+        let machine = build
+            .target
+            .as_deref()
+            .and_then(|t| t.split('-').next())
+            .unwrap_or(env::consts::ARCH);
+        SystemPackage {
+            format,
+            backend,
+            name: service_name.to_lowercase().replace('_', "-"),
+            version: package_version(&meta.version),
+            arch: package_arch(format, machine),
+            license: meta.license,
+            maintainer: meta.maintainer,
+            create_user,
+            manifest,
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match self.format {
+            PackageFormat::Deb => "deb",
+            _ => "rpm",
+        }
+    }
+
+    /// dpkg's `<name>_<version>_<arch>.deb`, rpm's `<name>-<version>-1.<arch>.rpm`.
+    fn file_name(&self) -> String {
+        match self.format {
+            PackageFormat::Deb => format!("{}_{}_{}.deb", self.name, self.version, self.arch),
+            _ => format!("{}-{}-1.{}.rpm", self.name, self.version, self.arch),
+        }
+    }
+
+    /// Binary, init config, install manifest and assets at their installed paths.
+    fn files(&self, binary: Vec<u8>, assets: &[String]) -> Result<Vec<PackEntry>, String> {
+        let manifest = &self.manifest;
+        let name = manifest.service_name.as_str();
+        let path = |p: PathBuf| p.to_string_lossy().into_owned();
+        let mut files = vec![PackEntry::file(&path(manifest.exec_path.clone()), 0o755, binary)];
+
+        let (config_path, config) =
+            expected_config_for(&self.backend, manifest, RenderHost::Target(TARGET_TOOLS));
+        files.push(PackEntry::file(&path(config_path), 0o644, config.into_bytes()));
+        if let InitBackend::Systemd { .. } = self.backend {
+            if let Some(schedule) = &manifest.schedule {
+                let timer = render_timer_unit(manifest, schedule);
+                files.push(PackEntry::file(&path(timer_unit_path(name)), 0o644, timer.into_bytes()));
+            } else if !manifest.listen.is_empty() {
+                let socket = render_socket_unit(manifest);
+                files.push(PackEntry::file(&path(socket_unit_path(name)), 0o644, socket.into_bytes()));
+            }
+        }
+        let recorded = toml::to_string_pretty(manifest).map_err(|e| e.to_string())?;
+        files.push(PackEntry::file(&path(manifest_path(name)), 0o644, recorded.into_bytes()));
+
+        for asset in assets {
+            let relative = asset_relative_path(asset).ok_or_else(|| {
+                format!("[corky] assets: '{}' must be a relative path inside the package.", asset)
+            })?;
+            collect_files(&relative, &state_dir(name).join(&relative), &mut files)
+                .map_err(|e| format!("[corky] assets: {}: {}", asset, e))?;
+        }
+        Ok(files)
+    }
+
+    fn encode(&self, files: &[PackEntry]) -> Result<Vec<u8>, String> {
+        let mtime = source_date_epoch();
+        let postinst = post_install_script(&self.backend, &self.manifest, self.create_user, self.format);
+        let prerm = pre_remove_script(&self.backend, &self.manifest, self.format);
+        let requires = match self.backend {
+            InitBackend::Supervisor if self.manifest.schedule.is_none() => {
+                vec!["supervisor".to_string()]
+            }
+            _ => Vec::new(),
+        };
+        if self.format == PackageFormat::Rpm {
+            return Ok(build_rpm(&RpmSpec {
+                name: &self.name,
+                version: &self.version,
+                release: "1",
+                arch: &self.arch,
+                summary: &self.manifest.description,
+                license: &self.license,
+                requires: &requires,
+                files,
+                post_install: &postinst,
+                pre_uninstall: &prerm,
+                mtime,
+            }));
+        }
+
+        let installed_kib = files.iter().map(PackEntry::size).sum::<usize>().div_ceil(1024);
+        let mut control = format!(
+            "Package: {}\nVersion: {}\nArchitecture: {}\nMaintainer: {}\nInstalled-Size: {}\n",
+            self.name, self.version, self.arch, self.maintainer, installed_kib
+        );
+        if !requires.is_empty() {
+            control.push_str(&format!("Depends: {}\n", requires.join(", ")));
+        }
+        control.push_str(&format!(
+            "Section: misc\nPriority: optional\nDescription: {}\n",
+            self.manifest.description.lines().next().unwrap_or(&self.name)
+        ));
+        let control_tar = tar(
+            &[
+                PackEntry::file("control", 0o644, control.into_bytes()),
+                PackEntry::file("postinst", 0o755, postinst.into_bytes()),
+                PackEntry::file("prerm", 0o755, prerm.into_bytes()),
+            ],
+            "./",
+            mtime,
+        )?;
+        let data_tar = tar(&with_parent_dirs(files), "./", mtime)?;
+        Ok(ar(
+            &[
+                ("debian-binary", &b"2.0\n"[..]),
+                ("control.tar.gz", &gzip(&control_tar)[..]),
+                ("data.tar.gz", &gzip(&data_tar)[..]),
+            ],
+            mtime,
+        ))
+    }
+}
+
+/// `[package]` fields beyond name and description.
+struct PackageMeta {
+    version: String,
+    license: String,
+    maintainer: String,
+}
+
+impl PackageMeta {
+    fn parse(cargo_toml: &str) -> Result<PackageMeta, String> {
+        let parsed: toml::Table =
+            toml::from_str(cargo_toml).map_err(|e| format!("Failed to parse Cargo.toml: {}", e))?;
+        let package = parsed.get("package");
+        let field = |key: &str| package.and_then(|p| p.get(key));
+        let version = field("version")
+            .and_then(|v| v.as_str())
+            .ok_or("Cargo.toml needs a literal [package] version to build a package.")?;
+        Ok(PackageMeta {
+            version: version.to_string(),
+            license: field("license")
+                .and_then(|v| v.as_str())
+                .unwrap_or("Unspecified")
+                .to_string(),
+            maintainer: field("authors")
+                .and_then(|v| v.as_array())
+                .and_then(|a| a.first())
+                .and_then(|v| v.as_str())
+                .unwrap_or("corky")
+                .to_string(),
+        })
+    }
+}
+
+/// Semver pre-releases ("1.2.0-rc.1") sort before the release only with "~".
+fn package_version(version: &str) -> String {
+    version.split('+').next().unwrap_or(version).replace('-', "~")
+}
+
+fn package_arch(format: PackageFormat, machine: &str) -> String {
+    let arch = match (format, machine) {
+        (PackageFormat::Deb, "x86_64") => "amd64",
+        (PackageFormat::Deb, "aarch64") => "arm64",
+        (PackageFormat::Deb, "x86" | "i686") => "i386",
+        (PackageFormat::Deb, "arm" | "armv7") => "armhf",
+        (_, "x86" | "i686") => "i686",
+        (_, "arm" | "armv7") => "armv7hl",
+        (_, machine) => machine,
+    };
+    arch.to_string()
+}
+
+/// The account the packaged service runs as; `created` marks accounts the
+/// postinst script creates on the target host if they don't exist.
+fn package_identity(spec: &UserSpec, backend: &InitBackend, service_name: &str) -> ServiceUser {
+    let create = |name: &str| ServiceUser {
+        user: name.to_string(),
+        group: name.to_string(),
+        dynamic: false,
+        created: true,
+    };
+    match spec {
+        UserSpec::Invoker => {
+            eprintln!(
+                "{C_YELLOW}[WARN]{C_RESET} No [corky] user set; the package creates a system user named {}.",
+                service_name
+            );
+            create(service_name)
+        }
+        UserSpec::Dynamic if *backend == InitBackend::Supervisor => {
+            eprintln!(
+                "{C_YELLOW}[WARN]{C_RESET} Supervisor has no dynamic users; the package creates a system user named {} instead.",
+                service_name
+            );
+            create(service_name)
+        }
+        UserSpec::Dynamic => ServiceUser {
+            user: service_name.to_string(),
+            group: service_name.to_string(),
+            dynamic: true,
+            created: false,
+        },
+        UserSpec::Create(name) => create(name),
+        UserSpec::Existing(name) => ServiceUser {
+            user: name.clone(),
+            group: primary_group_for_user(name),
+            dynamic: false,
+            created: false,
+        },
+    }
+}
+
+/// Regular files under `from` (a file or directory), placed under `to`.
+fn collect_files(from: &Path, to: &Path, files: &mut Vec<PackEntry>) -> std::io::Result<()> {
+    if from.is_dir() {
+        let mut entries: Vec<_> = fs::read_dir(from)?.flatten().map(|e| e.path()).collect();
+        entries.sort();
+        for entry in entries {
+            let name = entry.file_name().unwrap_or_default();
+            collect_files(&entry, &to.join(name), files)?;
+        }
+        return Ok(());
+    }
+    files.push(PackEntry::file(&to.to_string_lossy(), 0o644, fs::read(from)?));
+    Ok(())
+}
+
+/// $SOURCE_DATE_EPOCH for reproducible builds, else now.
+fn source_date_epoch() -> u64 {
+    env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0)
+        })
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Maintainer scripts
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Create the user and directories, then reload and start the service as
+/// `corky install` does. The manifest records `created_user` only when this
+/// script made the account, so `--remove-user` never deletes an existing one.
+fn post_install_script(
+    backend: &InitBackend,
+    manifest: &InstallManifest,
+    create_user: bool,
+    format: PackageFormat,
+) -> String {
+    let name = &manifest.service_name;
+    let mut script = String::from("#!/bin/sh\nset -e\n");
+    if format == PackageFormat::Deb {
+        script.push_str("[ \"$1\" = configure ] || exit 0\n");
+    }
+    if create_user {
+        let marker = created_user_marker_path(name);
+        script.push_str(&format!(
+            "if ! id -u {user} >/dev/null 2>&1; then\n    useradd {}\n    usermod --lock {user} || true\n    touch {marker}\nfi\n",
+            useradd_args(&manifest.user).join(" "),
+            user = manifest.user,
+            marker = marker.display(),
+        ));
+        script.push_str(&format!(
+            "if [ -e {} ]; then\n    sed -i 's/^created_user = false$/created_user = true/' {}\nfi\n",
+            marker.display(),
+            manifest_path(name).display()
+        ));
+    }
+    for (_, dir) in managed_dir_env(name) {
+        script.push_str(&format!("mkdir -p {}\n", dir.display()));
+        if !manifest.dynamic_user {
+            script.push_str(&format!(
+                "chown -R {}:{} {}\n",
+                manifest.user,
+                manifest.group,
+                dir.display()
+            ));
+        }
+    }
+
+    match backend {
+        InitBackend::Systemd { .. } => {
+            script.push_str("if [ -d /run/systemd/system ]; then\n    systemctl daemon-reload\n");
+            if manifest.template {
+                // Instances are added with `corky instance add`
+            } else if manifest.schedule.is_some() {
+                script.push_str(&format!("    systemctl enable --now {}.timer\n", name));
+            } else if !manifest.listen.is_empty() {
+                script.push_str(&format!(
+                    "    systemctl disable --now {}.service >/dev/null 2>&1 || true\n    systemctl enable --now {}.socket\n",
+                    name, name
+                ));
+            } else {
+                // restart rather than start so upgrades run the new binary
+                script.push_str(&format!(
+                    "    systemctl enable {}\n    systemctl restart {}\n",
+                    name, name
+                ));
+            }
+            script.push_str("fi\n");
+        }
+        InitBackend::Supervisor if manifest.schedule.is_some() => {
+            // cron picks up /etc/cron.d on its own
+        }
+        InitBackend::Supervisor => {
+            script.push_str(
+                "if command -v supervisorctl >/dev/null 2>&1; then\n    supervisorctl reread\n    supervisorctl update\n",
+            );
+            if !manifest.template {
+                script.push_str(&format!("    supervisorctl start {} || true\n", name));
+            }
+            script.push_str("fi\n");
+        }
+    }
+    script
+}
+
+/// Stop and disable the service when the package is removed, not upgraded.
+fn pre_remove_script(
+    backend: &InitBackend,
+    manifest: &InstallManifest,
+    format: PackageFormat,
+) -> String {
+    let name = &manifest.service_name;
+    let mut script = String::from("#!/bin/sh\n");
+    script.push_str(match format {
+        PackageFormat::Deb => "[ \"$1\" = remove ] || exit 0\n",
+        _ => "[ \"$1\" -eq 0 ] || exit 0\n",
+    });
+    match backend {
+        InitBackend::Systemd { .. } => {
+            script.push_str("if [ -d /run/systemd/system ]; then\n");
+            if manifest.template {
+                script.push_str(&format!("    systemctl stop '{}@*.service' || true\n", name));
+            } else if manifest.schedule.is_some() {
+                script.push_str(&format!("    systemctl disable --now {}.timer || true\n", name));
+            } else if !manifest.listen.is_empty() {
+                script.push_str(&format!("    systemctl disable --now {}.socket || true\n", name));
+            }
+            if !manifest.template {
+                script.push_str(&format!("    systemctl disable --now {} || true\n", name));
+            }
+            script.push_str("fi\n");
+        }
+        InitBackend::Supervisor if manifest.schedule.is_some() => {}
+        InitBackend::Supervisor => {
+            script.push_str(&format!(
+                "if command -v supervisorctl >/dev/null 2>&1; then\n    supervisorctl stop {} || true\n    supervisorctl remove {} || true\nfi\n",
+                name, name
+            ));
+        }
+    }
+    script.push_str("exit 0\n");
+    script
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Tests
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::init::systemd_unit_path;
+
+    fn test_package(format: PackageFormat, backend: InitBackend, config: &CorkyConfig) -> SystemPackage {
+        let manifest = manifest_from_config(
+            config,
+            &backend,
+            "corky-api",
+            "The API",
+            PathBuf::from("/usr/local/bin/api"),
+            state_dir("corky-api"),
+            ServiceUser {
+                created: false,
+                ..package_identity(&UserSpec::Create("api".to_string()), &backend, "corky-api")
+            },
+        );
+        SystemPackage {
+            format,
+            backend,
+            name: "corky-api".to_string(),
+            version: "1.2.0".to_string(),
+            arch: package_arch(format, "x86_64"),
+            license: "MIT".to_string(),
+            maintainer: "corky".to_string(),
+            create_user: true,
+            manifest,
+        }
+    }
+
+    fn systemd() -> InitBackend {
+        InitBackend::Systemd {
+            scope: "system".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_package_names() {
+        assert_eq!(package_version("1.2.0-rc.1+build5"), "1.2.0~rc.1");
+        let config = CorkyConfig::default();
+        let deb = test_package(PackageFormat::Deb, systemd(), &config);
+        assert_eq!(deb.file_name(), "corky-api_1.2.0_amd64.deb");
+        let rpm = test_package(PackageFormat::Rpm, systemd(), &config);
+        assert_eq!(rpm.file_name(), "corky-api-1.2.0-1.x86_64.rpm");
+        assert_eq!(package_arch(PackageFormat::Deb, "aarch64"), "arm64");
+    }
+
+    #[test]
+    fn test_package_files() {
+        let config = CorkyConfig::default();
+        let package = test_package(PackageFormat::Deb, systemd(), &config);
+        let files = package.files(b"bin".to_vec(), &[]).unwrap();
+        let paths: Vec<&str> = files.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            [
+                "usr/local/bin/api",
+                systemd_unit_path("corky-api").to_str().unwrap().trim_start_matches('/'),
+                "var/lib/corky/manifests/corky-api.toml",
+            ]
+        );
+        let recorded: InstallManifest =
+            toml::from_str(std::str::from_utf8(files[2].data.as_deref().unwrap()).unwrap()).unwrap();
+        assert_eq!(recorded, package.manifest);
+        // The postinst flips this only if it creates the account
+        assert!(std::str::from_utf8(files[2].data.as_deref().unwrap())
+            .unwrap()
+            .contains("\ncreated_user = false\n"));
+    }
+
+    #[test]
+    fn test_maintainer_scripts() {
+        let config = CorkyConfig::default();
+        let package = test_package(PackageFormat::Deb, systemd(), &config);
+        let postinst = post_install_script(&package.backend, &package.manifest, true, PackageFormat::Deb);
+        assert!(postinst.contains("[ \"$1\" = configure ] || exit 0"));
+        assert!(postinst.contains("useradd --system --user-group"));
+        assert!(postinst.contains("    touch /var/lib/corky/manifests/corky-api.created-user\nfi\n"));
+        assert!(postinst.contains(
+            "sed -i 's/^created_user = false$/created_user = true/' /var/lib/corky/manifests/corky-api.toml"
+        ));
+        assert!(postinst.contains("chown -R api:api /var/lib/corky/corky-api"));
+        assert!(postinst.contains("systemctl daemon-reload\n    systemctl enable corky-api\n"));
+        let prerm = pre_remove_script(&package.backend, &package.manifest, PackageFormat::Rpm);
+        assert!(prerm.contains("[ \"$1\" -eq 0 ] || exit 0"));
+        assert!(prerm.contains("systemctl disable --now corky-api || true"));
+
+        let supervised = test_package(PackageFormat::Rpm, InitBackend::Supervisor, &config);
+        let postinst =
+            post_install_script(&supervised.backend, &supervised.manifest, false, PackageFormat::Rpm);
+        assert!(postinst.contains("supervisorctl update\n    supervisorctl start corky-api"));
+        assert!(!postinst.contains("systemctl"));
+        assert!(!postinst.contains("useradd"));
+    }
+
+    #[test]
+    fn test_deb_layout() {
+        let config = CorkyConfig::default();
+        let package = test_package(PackageFormat::Deb, systemd(), &config);
+        let files = package.files(b"bin".to_vec(), &[]).unwrap();
+        let deb = package.encode(&files).unwrap();
+        assert!(deb.starts_with(b"!<arch>\ndebian-binary   "));
+        assert_eq!(&deb[68..72], b"2.0\n");
+        assert_eq!(&deb[72..86], b"control.tar.gz");
+    }
+}
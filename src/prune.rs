@@ -0,0 +1,329 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::adopt::{has_other_sections, remove_program_section};
+use crate::escalate::escalator_or_exit;
+use crate::init::{
+    elevate_privileges, is_root, list_corky_services, orphaned_configs, run_cmd, run_cmd_quiet,
+    section, supervisor_conf_for, systemd_fragment_path, systemd_unit_path, InitBackend,
+    ServiceInfo, BIN_PATH_SYSTEM, C_BGREEN, C_GREEN, C_RESET, C_YELLOW, ENV_INIT_BACKEND,
+};
+use crate::instance::template_unit_path;
+use crate::manifest::{list_manifests, load_manifest, manifest_path, remove_manifest};
+use crate::migrate::{parse_supervisor_conf, parse_systemd_unit};
+use crate::schedule::{cron_path, remove_schedule};
+use crate::socket::remove_socket;
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Prunable items
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Something corky left behind that can be cleaned up.
+#[derive(Debug, Clone)]
+enum PruneItem {
+    /// Unit or conf belonging to the init system that is not in use.
+    OrphanedConfig(PathBuf),
+    /// Binary and/or manifest whose service config no longer exists.
+    Leftover {
+        binary: Option<PathBuf>,
+        manifest: Option<String>,
+    },
+    /// Service whose configured binary is gone.
+    MissingBinary { service: ServiceInfo, exec_path: PathBuf },
+    /// systemd unit stuck in the failed state.
+    FailedUnit(String),
+}
+
+impl PruneItem {
+    fn describe(&self) -> String {
+        match self {
+            PruneItem::OrphanedConfig(path) => format!("orphaned config   {}", path.display()),
+            PruneItem::Leftover { binary, manifest } => {
+                let mut parts = Vec::new();
+                if let Some(b) = binary {
+                    parts.push(b.display().to_string());
+                }
+                if let Some(m) = manifest {
+                    parts.push(manifest_path(m).display().to_string());
+                }
+                format!("no config         {}", parts.join(" + "))
+            }
+            PruneItem::MissingBinary { service, exec_path } => format!(
+                "binary missing    {} (ExecStart {} not found)",
+                service.name,
+                exec_path.display()
+            ),
+            PruneItem::FailedUnit(name) => format!("failed unit       {} (reset-failed)", name),
+        }
+    }
+
+    fn remove(&self) {
+        match self {
+            PruneItem::OrphanedConfig(path) => remove_logged(path),
+            PruneItem::Leftover { binary, manifest } => {
+                if let Some(b) = binary {
+                    remove_logged(b);
+                }
+                if let Some(m) = manifest {
+                    forget(m);
+                }
+            }
+            PruneItem::MissingBinary { service, .. } => {
+                // A .timer or .socket left behind would point at a missing unit
+                remove_schedule(false, false, &service.name);
+                remove_socket(false, false, &service.name);
+                match &service.backend {
+                    InitBackend::Systemd { .. } => {
+                        let _ = run_cmd_quiet("systemctl", &["disable", "--now", &service.name]);
+                        remove_logged(&systemd_unit_path(&service.name));
+                        let _ = run_cmd("systemctl", &["daemon-reload"]);
+                        let _ = run_cmd_quiet("systemctl", &["reset-failed", &service.name]);
+                    }
+                    InitBackend::Supervisor => {
+                        let _ = run_cmd_quiet("supervisorctl", &["stop", &service.name]);
+                        remove_program(&supervisor_conf_for(&service.name), &service.name);
+                        let _ = run_cmd("supervisorctl", &["reread"]);
+                        let _ = run_cmd("supervisorctl", &["update"]);
+                    }
+                }
+                forget(&service.name);
+            }
+            PruneItem::FailedUnit(name) => {
+                if run_cmd("systemctl", &["reset-failed", name]) {
+                    println!("{C_GREEN}[INFO]{C_RESET} Reset {}", name);
+                }
+            }
+        }
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// corky prune
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Find and, after confirmation (or with `yes`), remove leftovers.
+pub fn prune(backend: &InitBackend, yes: bool) {
+    section("Scanning for leftovers");
+    let items = find_prunable(backend);
+    if items.is_empty() {
+        println!("{C_BGREEN}[OK]{C_RESET} Nothing to prune.");
+        return;
+    }
+    for item in &items {
+        println!("  {}", item.describe());
+    }
+    println!();
+
+    if !yes {
+        let confirmed = inquire::Confirm::new(&format!("Remove {} item(s)?", items.len()))
+            .with_default(false)
+            .prompt()
+            .unwrap_or(false);
+        if !confirmed {
+            println!("{C_YELLOW}[SKIP]{C_RESET} Nothing removed.");
+            return;
+        }
+    }
+
+    if !is_root() {
+        println!(
+            "{C_GREEN}[INFO]{C_RESET} Elevating with {} to prune...",
+            escalator_or_exit()
+        );
+        let mut args: Vec<String> = env::args().skip(1).collect();
+        if !yes {
+            // Already confirmed; don't ask again after elevation
+            args.push("--yes".to_string());
+        }
+        let backend_str = backend.to_string();
+        elevate_privileges(&args, &[(ENV_INIT_BACKEND, &backend_str)]);
+    }
+
+    section("Pruning");
+    for item in &items {
+        item.remove();
+    }
+    println!("{C_BGREEN}[SUCCESS]{C_RESET} Pruned {} item(s).", items.len());
+}
+
+fn find_prunable(backend: &InitBackend) -> Vec<PruneItem> {
+    let mut items: Vec<PruneItem> = orphaned_configs(backend)
+        .into_iter()
+        .map(PruneItem::OrphanedConfig)
+        .collect();
+
+    let services = list_corky_services(backend);
+    let manifests = list_manifests();
+
+    // Manifests (and their binaries) whose service config is gone
+    for manifest in &manifests {
+        if manifest.backend != backend.to_string() || has_config(backend, &manifest.service_name)
+        {
+            continue;
+        }
+        let binary = Some(manifest.exec_path.clone()).filter(|p| p.exists());
+        items.push(PruneItem::Leftover {
+            binary,
+            manifest: Some(manifest.service_name.clone()),
+        });
+    }
+
+    // corky-* binaries nothing refers to
+    if let Ok(entries) = fs::read_dir(BIN_PATH_SYSTEM) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let name = entry.file_name().to_string_lossy().to_string();
+            let referenced = manifests.iter().any(|m| m.exec_path == path)
+                || services.iter().any(|s| s.name == name);
+            let already = items
+                .iter()
+                .any(|i| matches!(i, PruneItem::Leftover { binary: Some(b), .. } if *b == path));
+            if name.starts_with("corky-") && !referenced && !already {
+                items.push(PruneItem::Leftover {
+                    binary: Some(path),
+                    manifest: None,
+                });
+            }
+        }
+    }
+
+    // Services whose binary has been deleted
+    for service in &services {
+        if let Some(exec_path) = configured_exec_path(service)
+            && !exec_path.exists()
+        {
+            items.push(PruneItem::MissingBinary {
+                service: service.clone(),
+                exec_path,
+            });
+        }
+    }
+
+    // Failed systemd units
+    if let InitBackend::Systemd { .. } = backend
+        && let Ok(output) = Command::new("systemctl")
+            .args(["list-units", "--state=failed", "--no-legend", "--plain", "corky-*"])
+            .output()
+    {
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        items.extend(parse_failed_units(&stdout).into_iter().map(PruneItem::FailedUnit));
+    }
+
+    items
+}
+
+fn config_path(backend: &InitBackend, service_name: &str) -> PathBuf {
+    match backend {
+        InitBackend::Systemd { .. } => systemd_unit_path(service_name),
+        InitBackend::Supervisor => supervisor_conf_for(service_name),
+    }
+}
+
+fn has_config(backend: &InitBackend, service_name: &str) -> bool {
+    config_path(backend, service_name).exists()
+        || cron_path(service_name).exists()
+        || template_unit_path(service_name).exists()
+        || matches!(backend, InitBackend::Systemd { .. })
+            && systemd_fragment_path(service_name).is_some()
+}
+
+/// The binary a service runs: from its manifest, else parsed from its config.
+fn configured_exec_path(service: &ServiceInfo) -> Option<PathBuf> {
+    if let Some(manifest) = load_manifest(&service.name) {
+        return Some(manifest.exec_path);
+    }
+    let text = fs::read_to_string(config_path(&service.backend, &service.name)).ok()?;
+    let parsed = match &service.backend {
+        InitBackend::Systemd { .. } => parse_systemd_unit(&service.name, &text),
+        InitBackend::Supervisor => parse_supervisor_conf(&service.name, &text),
+    };
+    parsed.map(|m| m.exec_path).filter(|p| p.is_absolute())
+}
+
+/// Unit names from `systemctl list-units --plain --no-legend` output.
+fn parse_failed_units(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter_map(|l| l.split_whitespace().next())
+        .filter(|u| u.starts_with("corky-"))
+        .map(|u| u.trim_end_matches(".service").to_string())
+        .collect()
+}
+
+fn remove_logged(path: &Path) {
+    if !path.exists() {
+        return;
+    }
+    match fs::remove_file(path) {
+        Ok(()) => println!("{C_GREEN}[INFO]{C_RESET} Removed {}", path.display()),
+        Err(e) => eprintln!("{C_YELLOW}[WARN]{C_RESET} remove {}: {}", path.display(), e),
+    }
+}
+
+/// Remove `name`'s program from `conf`, keeping the file for any others.
+fn remove_program(conf: &Path, name: &str) {
+    let Ok(text) = fs::read_to_string(conf) else {
+        return;
+    };
+    match conf_without_program(&text, name) {
+        // Only content goes away; the file keeps its mode
+        Some(rest) => match fs::write(conf, rest) {
+            Ok(()) => println!(
+                "{C_GREEN}[INFO]{C_RESET} Removed [program:{}] from {}",
+                name,
+                conf.display()
+            ),
+            Err(e) => eprintln!("{C_YELLOW}[WARN]{C_RESET} write {}: {}", conf.display(), e),
+        },
+        None => remove_logged(conf),
+    }
+}
+
+/// The conf without `name`'s program, or None when nothing else is left in it.
+fn conf_without_program(text: &str, name: &str) -> Option<String> {
+    has_other_sections(text, name).then(|| remove_program_section(text, name))
+}
+
+fn forget(service_name: &str) {
+    if let Err(e) = remove_manifest(service_name) {
+        eprintln!(
+            "{C_YELLOW}[WARN]{C_RESET} remove {}: {}",
+            manifest_path(service_name).display(),
+            e
+        );
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Tests
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_failed_units() {
+        let out = "corky-zmq.service loaded failed failed ZMQ bridge\n\
+                   other.service loaded failed failed Other\n";
+        assert_eq!(parse_failed_units(out), vec!["corky-zmq".to_string()]);
+        assert!(parse_failed_units("").is_empty());
+    }
+
+    #[test]
+    fn test_conf_without_program() {
+        let conf = "[program:corky-a]\ncommand=/usr/local/bin/corky-a\n\n\
+                    [program:legacy]\ncommand=/opt/legacy\n";
+        assert_eq!(
+            conf_without_program(conf, "corky-a").as_deref(),
+            Some("[program:legacy]\ncommand=/opt/legacy\n")
+        );
+        assert_eq!(
+            conf_without_program(conf, "legacy").as_deref(),
+            Some("[program:corky-a]\ncommand=/usr/local/bin/corky-a\n\n")
+        );
+        assert_eq!(conf_without_program("[program:corky-a]\ncommand=/x\n", "corky-a"), None);
+    }
+}
@@ -0,0 +1,169 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::init::{run_cmd_quiet, C_BGREEN, C_GREEN, C_RED, C_RESET, C_YELLOW};
+use crate::instance::split_instance;
+use crate::manifest::{load_manifest, InstallManifest};
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Readiness notification (`[corky] ready = "notify"`)
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Ready files for supervisor programs, one directory per service owned by
+/// its user, so a service can only report readiness for itself.
+pub const READY_DIR: &str = "/run/corky";
+
+/// WatchdogSec= used when `[corky] watchdog_sec` isn't set.
+pub const DEFAULT_WATCHDOG_SEC: u64 = 30;
+
+/// How long start/install wait for READY=1 (systemd's default TimeoutStartSec=).
+const READY_TIMEOUT: Duration = Duration::from_secs(90);
+
+/// When a service counts as started.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ReadyMode {
+    /// As soon as the process is running.
+    #[default]
+    Simple,
+    /// Once it reports READY=1 through corky-sdk.
+    Notify,
+}
+
+/// `[Service]` directives for notify services; empty for simple ones.
+pub fn render_notify_directives(manifest: &InstallManifest) -> String {
+    if manifest.ready != ReadyMode::Notify {
+        return String::new();
+    }
+    let mut out = "Type=notify\n".to_string();
+    if manifest.watchdog_sec > 0 {
+        out.push_str(&format!("WatchdogSec={}\n", manifest.watchdog_sec));
+    }
+    out
+}
+
+/// The service's ready directory; template instances share their template's.
+pub fn ready_dir(service_name: &str) -> PathBuf {
+    let service_name = split_instance(service_name).map_or(service_name, |(template, _)| template);
+    PathBuf::from(READY_DIR).join(service_name)
+}
+
+/// Where a supervisor program reports readiness (passed as $CORKY_READY_FILE).
+pub fn ready_file_path(program: &str) -> PathBuf {
+    ready_dir(program).join(format!("{}.ready", program))
+}
+
+pub fn is_notify_service(service_name: &str) -> bool {
+    load_manifest(service_name).is_some_and(|m| m.ready == ReadyMode::Notify)
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Waiting (supervisor; systemctl start already blocks on Type=notify)
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Clear a stale ready file before (re)starting `program`. Returns false if
+/// that isn't possible, in which case readiness can't be observed.
+fn prepare_ready_file(program: &str) -> bool {
+    let Some(manifest) = load_manifest(program) else {
+        return false;
+    };
+    let dir = ready_dir(program);
+    if fs::create_dir_all(&dir).is_err() {
+        return false;
+    }
+    // Earlier versions left READY_DIR world-writable
+    let _ = fs::set_permissions(READY_DIR, fs::Permissions::from_mode(0o755));
+    let owner = format!("{}:{}", manifest.user, manifest.group);
+    if fs::set_permissions(&dir, fs::Permissions::from_mode(0o700)).is_err()
+        || !run_cmd_quiet("chown", &[&owner, &dir.to_string_lossy()])
+    {
+        return false;
+    }
+    match fs::remove_file(ready_file_path(program)) {
+        Ok(()) => true,
+        Err(e) => e.kind() == std::io::ErrorKind::NotFound,
+    }
+}
+
+/// Block until `program` writes READY=1, printing the outcome.
+pub fn wait_until_ready(program: &str) -> bool {
+    println!("{C_GREEN}[INFO]{C_RESET} Waiting for {} to report READY=1...", program);
+    let path = ready_file_path(program);
+    let started = Instant::now();
+    while started.elapsed() < READY_TIMEOUT {
+        let contents = fs::read_to_string(&path).unwrap_or_default();
+        if contents.lines().any(|l| l.trim() == "READY=1") {
+            println!("{C_BGREEN}[OK]{C_RESET} {} is ready", program);
+            return true;
+        }
+        thread::sleep(Duration::from_millis(200));
+    }
+    eprintln!(
+        "{C_RED}[ERROR]{C_RESET} {} did not report READY=1 within {}s.",
+        program,
+        READY_TIMEOUT.as_secs()
+    );
+    false
+}
+
+/// Reset `program`'s ready file before a start; warns and returns false when
+/// readiness can't be tracked.
+pub fn can_wait_for_ready(program: &str) -> bool {
+    let ok = prepare_ready_file(program);
+    if !ok {
+        eprintln!(
+            "{C_YELLOW}[WARN]{C_RESET} Can't reset {}; not waiting for readiness.",
+            ready_file_path(program).display()
+        );
+    }
+    ok
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Tests
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_notify_directives() {
+        let mut manifest: InstallManifest = toml::from_str(
+            r#"
+service_name = "corky-api"
+description = "API"
+backend = "systemd"
+exec_path = "/usr/local/bin/corky-api"
+working_dir = "/"
+user = "api"
+group = "api"
+"#,
+        )
+        .unwrap();
+        assert_eq!(render_notify_directives(&manifest), "");
+        manifest.ready = ReadyMode::Notify;
+        assert_eq!(render_notify_directives(&manifest), "Type=notify\n");
+        manifest.watchdog_sec = 30;
+        assert_eq!(
+            render_notify_directives(&manifest),
+            "Type=notify\nWatchdogSec=30\n"
+        );
+    }
+
+    #[test]
+    fn test_ready_file_path() {
+        assert_eq!(
+            ready_file_path("corky-api"),
+            PathBuf::from("/run/corky/corky-api/corky-api.ready")
+        );
+        assert_eq!(
+            ready_file_path("corky-worker@2"),
+            PathBuf::from("/run/corky/corky-worker/corky-worker@2.ready")
+        );
+    }
+}
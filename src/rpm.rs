@@ -0,0 +1,377 @@
+use std::collections::BTreeMap;
+
+use crate::pack::{cpio, gzip, sha256_hex, PackEntry};
+
+// ─────────────────────────────────────────────────────────────────────────────
+// RPM v3 package writer (lead, signature header, header, gzip'd cpio payload)
+// ─────────────────────────────────────────────────────────────────────────────
+
+const RPMTAG_HEADERSIGNATURES: u32 = 62;
+const RPMTAG_HEADERIMMUTABLE: u32 = 63;
+const RPMTAG_HEADERI18NTABLE: u32 = 100;
+
+const RPMSIGTAG_SHA256: u32 = 273;
+const RPMSIGTAG_SIZE: u32 = 1000;
+const RPMSIGTAG_PAYLOADSIZE: u32 = 1007;
+
+const RPMTAG_NAME: u32 = 1000;
+const RPMTAG_VERSION: u32 = 1001;
+const RPMTAG_RELEASE: u32 = 1002;
+const RPMTAG_SUMMARY: u32 = 1004;
+const RPMTAG_DESCRIPTION: u32 = 1005;
+const RPMTAG_BUILDTIME: u32 = 1006;
+const RPMTAG_BUILDHOST: u32 = 1007;
+const RPMTAG_SIZE: u32 = 1009;
+const RPMTAG_LICENSE: u32 = 1014;
+const RPMTAG_GROUP: u32 = 1016;
+const RPMTAG_OS: u32 = 1021;
+const RPMTAG_ARCH: u32 = 1022;
+const RPMTAG_POSTIN: u32 = 1024;
+const RPMTAG_PREUN: u32 = 1025;
+const RPMTAG_FILESIZES: u32 = 1028;
+const RPMTAG_FILEMODES: u32 = 1030;
+const RPMTAG_FILERDEVS: u32 = 1033;
+const RPMTAG_FILEMTIMES: u32 = 1034;
+const RPMTAG_FILEDIGESTS: u32 = 1035;
+const RPMTAG_FILELINKTOS: u32 = 1036;
+const RPMTAG_FILEFLAGS: u32 = 1037;
+const RPMTAG_FILEUSERNAME: u32 = 1039;
+const RPMTAG_FILEGROUPNAME: u32 = 1040;
+const RPMTAG_SOURCERPM: u32 = 1044;
+const RPMTAG_PROVIDENAME: u32 = 1047;
+const RPMTAG_REQUIREFLAGS: u32 = 1048;
+const RPMTAG_REQUIRENAME: u32 = 1049;
+const RPMTAG_REQUIREVERSION: u32 = 1050;
+const RPMTAG_POSTINPROG: u32 = 1085;
+const RPMTAG_PREUNPROG: u32 = 1086;
+const RPMTAG_FILEDEVICES: u32 = 1095;
+const RPMTAG_FILEINODES: u32 = 1096;
+const RPMTAG_FILELANGS: u32 = 1097;
+const RPMTAG_PROVIDEFLAGS: u32 = 1112;
+const RPMTAG_PROVIDEVERSION: u32 = 1113;
+const RPMTAG_DIRINDEXES: u32 = 1116;
+const RPMTAG_BASENAMES: u32 = 1117;
+const RPMTAG_DIRNAMES: u32 = 1118;
+const RPMTAG_PAYLOADFORMAT: u32 = 1124;
+const RPMTAG_PAYLOADCOMPRESSOR: u32 = 1125;
+const RPMTAG_PAYLOADFLAGS: u32 = 1126;
+const RPMTAG_FILEDIGESTALGO: u32 = 5011;
+const RPMTAG_PAYLOADDIGEST: u32 = 5092;
+const RPMTAG_PAYLOADDIGESTALGO: u32 = 5093;
+
+const PGPHASHALGO_SHA256: u32 = 8;
+const RPMSENSE_LESS: u32 = 1 << 1;
+const RPMSENSE_EQUAL: u32 = 1 << 3;
+const RPMSENSE_INTERP: u32 = 1 << 8;
+const RPMSENSE_SCRIPT_POST: u32 = 1 << 10;
+const RPMSENSE_SCRIPT_PREUN: u32 = 1 << 11;
+const RPMSENSE_RPMLIB: u32 = 1 << 24;
+
+/// rpmlib() features the header relies on, with the version that added each.
+const RPMLIB_FEATURES: [(&str, &str); 3] = [
+    ("rpmlib(CompressedFileNames)", "3.0.4-1"),
+    ("rpmlib(FileDigests)", "4.6.0-1"),
+    ("rpmlib(PayloadFilesHavePrefix)", "4.0-1"),
+];
+
+/// Everything that goes into a binary .rpm.
+pub struct RpmSpec<'a> {
+    pub name: &'a str,
+    pub version: &'a str,
+    pub release: &'a str,
+    pub arch: &'a str,
+    pub summary: &'a str,
+    pub license: &'a str,
+    /// Extra package names required at install.
+    pub requires: &'a [String],
+    /// Regular files only; rpm creates missing parent directories.
+    pub files: &'a [PackEntry],
+    pub post_install: &'a str,
+    pub pre_uninstall: &'a str,
+    pub mtime: u64,
+}
+
+pub fn build_rpm(spec: &RpmSpec) -> Vec<u8> {
+    let files: Vec<&PackEntry> = spec.files.iter().filter(|f| f.data.is_some()).collect();
+    let payload_raw = cpio(spec.files, spec.mtime);
+    let payload = gzip(&payload_raw);
+    let header = main_header(spec, &files, &payload).serialize(RPMTAG_HEADERIMMUTABLE);
+
+    let mut signature = Header::default();
+    signature.put(RPMSIGTAG_SHA256, Value::Str(sha256_hex(&header)));
+    signature.put(RPMSIGTAG_SIZE, Value::Int32(vec![(header.len() + payload.len()) as u32]));
+    signature.put(RPMSIGTAG_PAYLOADSIZE, Value::Int32(vec![payload_raw.len() as u32]));
+    let mut signature = signature.serialize(RPMTAG_HEADERSIGNATURES);
+    signature.resize(signature.len().next_multiple_of(8), 0);
+
+    let mut out = lead(spec);
+    out.extend_from_slice(&signature);
+    out.extend_from_slice(&header);
+    out.extend_from_slice(&payload);
+    out
+}
+
+fn main_header(spec: &RpmSpec, files: &[&PackEntry], payload: &[u8]) -> Header {
+    let nevra = format!("{}-{}-{}", spec.name, spec.version, spec.release);
+    let mut h = Header::default();
+    h.put(RPMTAG_HEADERI18NTABLE, Value::StrArray(vec!["C".to_string()]));
+    h.put(RPMTAG_NAME, Value::Str(spec.name.to_string()));
+    h.put(RPMTAG_VERSION, Value::Str(spec.version.to_string()));
+    h.put(RPMTAG_RELEASE, Value::Str(spec.release.to_string()));
+    h.put(RPMTAG_SUMMARY, Value::I18n(spec.summary.to_string()));
+    h.put(RPMTAG_DESCRIPTION, Value::I18n(spec.summary.to_string()));
+    h.put(RPMTAG_BUILDTIME, Value::Int32(vec![spec.mtime as u32]));
+    h.put(RPMTAG_BUILDHOST, Value::Str("localhost".to_string()));
+    h.put(
+        RPMTAG_SIZE,
+        Value::Int32(vec![files.iter().map(|f| f.size() as u32).sum()]),
+    );
+    h.put(RPMTAG_LICENSE, Value::Str(spec.license.to_string()));
+    h.put(RPMTAG_GROUP, Value::I18n("Unspecified".to_string()));
+    h.put(RPMTAG_OS, Value::Str("linux".to_string()));
+    h.put(RPMTAG_ARCH, Value::Str(spec.arch.to_string()));
+    h.put(RPMTAG_POSTIN, Value::Str(spec.post_install.to_string()));
+    h.put(RPMTAG_PREUN, Value::Str(spec.pre_uninstall.to_string()));
+    h.put(RPMTAG_POSTINPROG, Value::Str("/bin/sh".to_string()));
+    h.put(RPMTAG_PREUNPROG, Value::Str("/bin/sh".to_string()));
+    h.put(RPMTAG_SOURCERPM, Value::Str(format!("{}.src.rpm", nevra)));
+
+    // File list, with paths split into directory + basename
+    let mut dirnames: Vec<String> = Vec::new();
+    let mut dirindexes = Vec::new();
+    let mut basenames = Vec::new();
+    for file in files {
+        let (dir, base) = file.path.rsplit_once('/').unwrap_or(("", &file.path));
+        let dir = format!("/{}/", dir).replace("//", "/");
+        let index = dirnames.iter().position(|d| *d == dir).unwrap_or_else(|| {
+            dirnames.push(dir);
+            dirnames.len() - 1
+        });
+        dirindexes.push(index as u32);
+        basenames.push(base.to_string());
+    }
+    let count = files.len();
+    let same = |s: &str| vec![s.to_string(); count];
+    h.put(RPMTAG_FILESIZES, Value::Int32(files.iter().map(|f| f.size() as u32).collect()));
+    h.put(
+        RPMTAG_FILEMODES,
+        Value::Int16(files.iter().map(|f| (0o100000 | f.mode) as u16).collect()),
+    );
+    h.put(RPMTAG_FILERDEVS, Value::Int16(vec![0; count]));
+    h.put(RPMTAG_FILEMTIMES, Value::Int32(vec![spec.mtime as u32; count]));
+    h.put(
+        RPMTAG_FILEDIGESTS,
+        Value::StrArray(
+            files
+                .iter()
+                .map(|f| sha256_hex(f.data.as_deref().unwrap_or_default()))
+                .collect(),
+        ),
+    );
+    h.put(RPMTAG_FILELINKTOS, Value::StrArray(same("")));
+    h.put(RPMTAG_FILEFLAGS, Value::Int32(vec![0; count]));
+    h.put(RPMTAG_FILEUSERNAME, Value::StrArray(same("root")));
+    h.put(RPMTAG_FILEGROUPNAME, Value::StrArray(same("root")));
+    h.put(RPMTAG_FILEDEVICES, Value::Int32(vec![1; count]));
+    h.put(RPMTAG_FILEINODES, Value::Int32((1..=count as u32).collect()));
+    h.put(RPMTAG_FILELANGS, Value::StrArray(same("")));
+    h.put(RPMTAG_DIRINDEXES, Value::Int32(dirindexes));
+    h.put(RPMTAG_BASENAMES, Value::StrArray(basenames));
+    h.put(RPMTAG_DIRNAMES, Value::StrArray(dirnames));
+    h.put(RPMTAG_FILEDIGESTALGO, Value::Int32(vec![PGPHASHALGO_SHA256]));
+
+    // Dependencies
+    h.put(RPMTAG_PROVIDENAME, Value::StrArray(vec![spec.name.to_string()]));
+    h.put(RPMTAG_PROVIDEFLAGS, Value::Int32(vec![RPMSENSE_EQUAL]));
+    h.put(
+        RPMTAG_PROVIDEVERSION,
+        Value::StrArray(vec![format!("{}-{}", spec.version, spec.release)]),
+    );
+    let mut requires: Vec<(String, u32, String)> = vec![(
+        "/bin/sh".to_string(),
+        RPMSENSE_INTERP | RPMSENSE_SCRIPT_POST | RPMSENSE_SCRIPT_PREUN,
+        String::new(),
+    )];
+    requires.extend(spec.requires.iter().map(|r| (r.clone(), 0, String::new())));
+    requires.extend(RPMLIB_FEATURES.iter().map(|(name, version)| {
+        (
+            name.to_string(),
+            RPMSENSE_RPMLIB | RPMSENSE_LESS | RPMSENSE_EQUAL,
+            version.to_string(),
+        )
+    }));
+    h.put(
+        RPMTAG_REQUIRENAME,
+        Value::StrArray(requires.iter().map(|r| r.0.clone()).collect()),
+    );
+    h.put(RPMTAG_REQUIREFLAGS, Value::Int32(requires.iter().map(|r| r.1).collect()));
+    h.put(
+        RPMTAG_REQUIREVERSION,
+        Value::StrArray(requires.iter().map(|r| r.2.clone()).collect()),
+    );
+
+    h.put(RPMTAG_PAYLOADFORMAT, Value::Str("cpio".to_string()));
+    h.put(RPMTAG_PAYLOADCOMPRESSOR, Value::Str("gzip".to_string()));
+    h.put(RPMTAG_PAYLOADFLAGS, Value::Str("0".to_string()));
+    h.put(RPMTAG_PAYLOADDIGEST, Value::StrArray(vec![sha256_hex(payload)]));
+    h.put(RPMTAG_PAYLOADDIGESTALGO, Value::Int32(vec![PGPHASHALGO_SHA256]));
+    h
+}
+
+/// The 96-byte lead; obsolete, but still checked for its magic and name.
+fn lead(spec: &RpmSpec) -> Vec<u8> {
+    let mut out = vec![0xed, 0xab, 0xee, 0xdb, 3, 0];
+    out.extend_from_slice(&0u16.to_be_bytes()); // binary package
+    let archnum: u16 = match spec.arch {
+        "x86_64" | "i686" => 1,
+        "aarch64" => 19,
+        _ => 0,
+    };
+    out.extend_from_slice(&archnum.to_be_bytes());
+    let mut name = format!("{}-{}-{}", spec.name, spec.version, spec.release).into_bytes();
+    name.resize(66, 0);
+    name[65] = 0;
+    out.extend_from_slice(&name);
+    out.extend_from_slice(&1u16.to_be_bytes()); // Linux
+    out.extend_from_slice(&5u16.to_be_bytes()); // header-style signature
+    out.resize(96, 0);
+    out
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Header structures
+// ─────────────────────────────────────────────────────────────────────────────
+
+enum Value {
+    Int16(Vec<u16>),
+    Int32(Vec<u32>),
+    Str(String),
+    StrArray(Vec<String>),
+    I18n(String),
+}
+
+impl Value {
+    /// (type code, alignment, item count)
+    fn layout(&self) -> (u32, usize, usize) {
+        match self {
+            Value::Int16(v) => (3, 2, v.len()),
+            Value::Int32(v) => (4, 4, v.len()),
+            Value::Str(_) => (6, 1, 1),
+            Value::StrArray(v) => (8, 1, v.len()),
+            Value::I18n(_) => (9, 1, 1),
+        }
+    }
+
+    fn encode(&self, out: &mut Vec<u8>) {
+        let mut push_str = |s: &str| {
+            out.extend_from_slice(s.as_bytes());
+            out.push(0);
+        };
+        match self {
+            Value::Int16(v) => v.iter().for_each(|n| out.extend_from_slice(&n.to_be_bytes())),
+            Value::Int32(v) => v.iter().for_each(|n| out.extend_from_slice(&n.to_be_bytes())),
+            Value::Str(s) | Value::I18n(s) => push_str(s),
+            Value::StrArray(v) => v.iter().for_each(|s| push_str(s)),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Header {
+    entries: BTreeMap<u32, Value>,
+}
+
+impl Header {
+    fn put(&mut self, tag: u32, value: Value) {
+        self.entries.insert(tag, value);
+    }
+
+    /// Header blob with every entry inside one immutable region.
+    fn serialize(&self, region_tag: u32) -> Vec<u8> {
+        let index_len = self.entries.len() + 1;
+        let mut index = Vec::new();
+        let mut data = Vec::new();
+        for (tag, value) in &self.entries {
+            let (kind, align, count) = value.layout();
+            data.resize(data.len().next_multiple_of(align), 0);
+            for n in [*tag, kind, data.len() as u32, count as u32] {
+                index.extend_from_slice(&n.to_be_bytes());
+            }
+            value.encode(&mut data);
+        }
+        // The region trailer points back over the whole index
+        let trailer_offset = data.len() as u32;
+        for n in [region_tag, 7, (-(index_len as i32 * 16)) as u32, 16] {
+            data.extend_from_slice(&n.to_be_bytes());
+        }
+
+        let mut out = vec![0x8e, 0xad, 0xe8, 0x01, 0, 0, 0, 0];
+        out.extend_from_slice(&(index_len as u32).to_be_bytes());
+        out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        for n in [region_tag, 7, trailer_offset, 16] {
+            out.extend_from_slice(&n.to_be_bytes());
+        }
+        out.extend_from_slice(&index);
+        out.extend_from_slice(&data);
+        out
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Tests
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn be32(bytes: &[u8], at: usize) -> u32 {
+        u32::from_be_bytes(bytes[at..at + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn test_header_region() {
+        let mut h = Header::default();
+        h.put(RPMTAG_NAME, Value::Str("corky-api".to_string()));
+        h.put(RPMTAG_FILEMODES, Value::Int16(vec![0o100755]));
+        h.put(RPMTAG_FILESIZES, Value::Int32(vec![5]));
+        let blob = h.serialize(RPMTAG_HEADERIMMUTABLE);
+        let (il, dl) = (be32(&blob, 8) as usize, be32(&blob, 12) as usize);
+        assert_eq!(il, 4);
+        assert_eq!(blob.len(), 16 + il * 16 + dl);
+        // Region entry first, its trailer last in the data store
+        assert_eq!(be32(&blob, 16), RPMTAG_HEADERIMMUTABLE);
+        assert_eq!(be32(&blob, 24) as usize, dl - 16);
+        let data = &blob[16 + il * 16..];
+        assert_eq!(be32(data, dl - 8) as i32, -64);
+        // Entries sorted by tag; the INT32 after the INT16 is 4-byte aligned
+        assert_eq!(be32(&blob, 32), RPMTAG_NAME);
+        assert_eq!(be32(&blob, 48), RPMTAG_FILESIZES);
+        assert_eq!(be32(&blob, 56) % 4, 0);
+    }
+
+    #[test]
+    fn test_build_rpm_lead_and_signature() {
+        let files = [PackEntry::file("usr/local/bin/corky-api", 0o755, b"bin".to_vec())];
+        let rpm = build_rpm(&RpmSpec {
+            name: "corky-api",
+            version: "1.2.0",
+            release: "1",
+            arch: "x86_64",
+            summary: "API",
+            license: "MIT",
+            requires: &[],
+            files: &files,
+            post_install: "true",
+            pre_uninstall: "true",
+            mtime: 0,
+        });
+        assert_eq!(&rpm[..4], &[0xed, 0xab, 0xee, 0xdb]);
+        assert!(rpm[10..].starts_with(b"corky-api-1.2.0-1\0"));
+        let sig = &rpm[96..];
+        assert_eq!(&sig[..3], &[0x8e, 0xad, 0xe8]);
+        let sig_len = 16 + be32(sig, 8) as usize * 16 + be32(sig, 12) as usize;
+        let header_start = 96 + sig_len.next_multiple_of(8);
+        assert_eq!(&rpm[header_start..header_start + 3], &[0x8e, 0xad, 0xe8]);
+    }
+}
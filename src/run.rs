@@ -0,0 +1,251 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::deploy::managed_dir_env;
+use crate::envfile::read_env_file;
+use crate::init::{
+    corky_config, elevate_privileges, ensure_corky_prefix, exit_error, find_in_path, is_root,
+    pkg_name_and_description, section, supervisor_conf_for, systemd_fragment_path,
+    systemd_unit_path, InitBackend, ServiceInfo, C_GREEN, C_RESET, C_YELLOW, ENV_INIT_BACKEND,
+    ENV_ORIGINAL_CWD,
+};
+use crate::instance::split_instance;
+use crate::manifest::{load_manifest, InstallManifest};
+use crate::migrate::{parse_supervisor_conf, parse_systemd_unit};
+
+/// PATH systemd gives services; supervisor programs get the same here for parity.
+const SERVICE_PATH: &str = "/usr/local/sbin:/usr/local/bin:/usr/sbin:/usr/bin:/sbin:/bin";
+
+// ─────────────────────────────────────────────────────────────────────────────
+// corky run
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Exec a service's command in the foreground as its user, from its working
+/// directory and with its environment. With `local`, the package's own
+/// build (per `[corky.build]`) replaces the installed binary.
+pub fn run_service_foreground(service_info: &ServiceInfo, local: bool) -> ! {
+    if let Ok(original_cwd) = env::var(ENV_ORIGINAL_CWD) {
+        let _ = env::set_current_dir(&original_cwd);
+    }
+    let manifest = service_manifest(service_info);
+    let exec_path = if local {
+        local_binary(&manifest)
+    } else {
+        manifest.exec_path.clone()
+    };
+    let environment = service_environment(service_info, &manifest);
+
+    let current_user = if is_root() {
+        "root".to_string()
+    } else {
+        env::var("USER").unwrap_or_default()
+    };
+    if (manifest.dynamic_user || current_user != manifest.user) && !is_root() {
+        let args: Vec<String> = env::args().skip(1).collect();
+        let backend_str = service_info.backend.to_string();
+        elevate_privileges(&args, &[(ENV_INIT_BACKEND, &backend_str)]);
+    }
+    if !manifest.working_dir.is_dir() {
+        exit_error(&format!(
+            "Working directory {} does not exist.",
+            manifest.working_dir.display()
+        ));
+    }
+
+    section(&format!("Running {} in the foreground", service_info.name));
+    println!("{C_GREEN}[INFO]{C_RESET} User:      {}", manifest.user);
+    println!(
+        "{C_GREEN}[INFO]{C_RESET} Directory: {}",
+        manifest.working_dir.display()
+    );
+    println!(
+        "{C_GREEN}[INFO]{C_RESET} Command:   {} {}",
+        exec_path.display(),
+        manifest.args.join(" ")
+    );
+    println!(
+        "{C_GREEN}[INFO]{C_RESET} Env:       {}",
+        environment.keys().cloned().collect::<Vec<_>>().join(", ")
+    );
+    if !manifest.limits.is_empty() {
+        eprintln!("{C_YELLOW}[WARN]{C_RESET} [corky.limits] are not applied to foreground runs.");
+    }
+    println!();
+
+    let mut cmd = if manifest.dynamic_user {
+        dynamic_user_command(service_info, &manifest, &exec_path, &environment)
+    } else {
+        let mut cmd = if manifest.user == current_user {
+            Command::new(&exec_path)
+        } else {
+            let Some(setpriv) = find_in_path("setpriv") else {
+                exit_error("setpriv is needed to run as the service user.");
+            };
+            let mut cmd = Command::new(setpriv);
+            cmd.arg(format!("--reuid={}", manifest.user))
+                .arg(format!("--regid={}", manifest.group))
+                .args(["--init-groups", "--"])
+                .arg(&exec_path);
+            cmd
+        };
+        cmd.args(&manifest.args)
+            .current_dir(&manifest.working_dir)
+            .env_clear()
+            .envs(&environment);
+        cmd
+    };
+    let err = cmd.exec();
+    exit_error(&format!("exec {}: {}", exec_path.display(), err));
+}
+
+/// The recorded manifest, or one parsed from the installed unit/conf.
+fn service_manifest(service_info: &ServiceInfo) -> InstallManifest {
+    if let Some(manifest) = load_manifest(&service_info.name) {
+        return manifest;
+    }
+    let (path, parsed) = match &service_info.backend {
+        InitBackend::Systemd { .. } => {
+            let path = systemd_fragment_path(&service_info.name)
+                .unwrap_or_else(|| systemd_unit_path(&service_info.name));
+            let text = fs::read_to_string(&path).unwrap_or_default();
+            (path, parse_systemd_unit(&service_info.name, &text))
+        }
+        InitBackend::Supervisor => {
+            let path = supervisor_conf_for(&service_info.name);
+            let text = fs::read_to_string(&path).unwrap_or_default();
+            (path, parse_supervisor_conf(&service_info.name, &text))
+        }
+    };
+    parsed.unwrap_or_else(|| {
+        exit_error(&format!("Could not read a command from {}.", path.display()))
+    })
+}
+
+/// Everything the init system would put in the service's environment.
+fn service_environment(
+    service_info: &ServiceInfo,
+    manifest: &InstallManifest,
+) -> BTreeMap<String, String> {
+    let mut env_vars = BTreeMap::from([
+        ("PATH".to_string(), SERVICE_PATH.to_string()),
+        ("USER".to_string(), manifest.user.clone()),
+        ("LOGNAME".to_string(), manifest.user.clone()),
+    ]);
+    // Keep the terminal usable for the foreground run
+    for key in ["LANG", "TERM"] {
+        if let Ok(value) = env::var(key) {
+            env_vars.insert(key.to_string(), value);
+        }
+    }
+    if service_info.backend == InitBackend::Supervisor {
+        env_vars.insert("RUST_LOG_STYLE".to_string(), "always".to_string());
+    }
+    if manifest.managed_dirs {
+        for (key, dir) in managed_dir_env(&manifest.service_name) {
+            env_vars.insert(key.to_string(), dir.display().to_string());
+        }
+    }
+    env_vars.extend(manifest.environment.clone());
+    env_vars.extend(read_env_file(&manifest.service_name));
+    if let Some((_, id)) = split_instance(&service_info.name) {
+        env_vars.insert("CORKY_INSTANCE".to_string(), id.to_string());
+        env_vars.extend(read_env_file(&service_info.name));
+    }
+    env_vars
+}
+
+/// The package's build artifact, checked against the service being run.
+fn local_binary(manifest: &InstallManifest) -> PathBuf {
+    let Some((pkg_name, _)) = pkg_name_and_description() else {
+        exit_error("--local needs to be run from the package directory (no Cargo.toml here).");
+    };
+    let build = corky_config().build;
+    let binary = env::current_dir().unwrap_or_default().join(build.artifact_path(&pkg_name));
+    if !binary.is_file() {
+        exit_error(&format!(
+            "{} not found; run `{}` first.",
+            binary.display(),
+            build.display()
+        ));
+    }
+    if ensure_corky_prefix(&pkg_name) != manifest.service_name {
+        eprintln!(
+            "{C_YELLOW}[WARN]{C_RESET} This package builds {}, but {} runs {}.",
+            pkg_name,
+            manifest.service_name,
+            manifest.exec_path.display()
+        );
+    }
+    binary
+}
+
+/// DynamicUser= accounts only exist inside systemd, so run in a transient unit.
+fn dynamic_user_command(
+    service_info: &ServiceInfo,
+    manifest: &InstallManifest,
+    exec_path: &Path,
+    environment: &BTreeMap<String, String>,
+) -> Command {
+    let mut cmd = Command::new("systemd-run");
+    cmd.args(["--pty", "--quiet", "--wait", "--collect"])
+        .arg(format!("--unit=corky-run-{}", service_info.name.replace('@', "-")))
+        .args(["-p", "DynamicUser=yes", "-p"])
+        .arg(format!("User={}", manifest.user))
+        .arg(format!("--working-directory={}", manifest.working_dir.display()));
+    if manifest.managed_dirs {
+        let dir = format!("corky/{}", manifest.service_name);
+        for property in ["StateDirectory", "ConfigurationDirectory", "LogsDirectory"] {
+            cmd.arg("-p").arg(format!("{}={}", property, dir));
+        }
+    }
+    for (key, value) in environment {
+        cmd.arg(format!("--setenv={}={}", key, value));
+    }
+    cmd.arg("--").arg(exec_path).args(&manifest.args);
+    cmd
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Tests
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_service_environment() {
+        let manifest: InstallManifest = toml::from_str(
+            r#"
+service_name = "corky-worker"
+description = "Worker"
+backend = "supervisor"
+exec_path = "/usr/local/bin/corky-worker"
+working_dir = "/var/lib/corky/corky-worker"
+managed_dirs = true
+user = "worker"
+group = "worker"
+template = true
+instances = ["eu"]
+
+[environment]
+PATH = "/opt/worker/bin"
+"#,
+        )
+        .unwrap();
+        let info = ServiceInfo {
+            name: "corky-worker@eu".to_string(),
+            backend: InitBackend::Supervisor,
+        };
+        let env_vars = service_environment(&info, &manifest);
+        assert_eq!(env_vars["PATH"], "/opt/worker/bin");
+        assert_eq!(env_vars["USER"], "worker");
+        assert_eq!(env_vars["RUST_LOG_STYLE"], "always");
+        assert_eq!(env_vars["STATE_DIRECTORY"], "/var/lib/corky/corky-worker");
+        assert_eq!(env_vars["CORKY_INSTANCE"], "eu");
+    }
+}
@@ -0,0 +1,553 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+use crate::deploy::managed_dir_env;
+use crate::envfile::write_config_file;
+use crate::init::{
+    exit_error, find_in_path, render_systemd_unit, run_cmd, run_cmd_expect_ok, run_cmd_quiet,
+    section, supervisor_log_path, systemd_unit_path, InitBackend, RenderHost, ServiceInfo, C_BGREEN, C_CYAN,
+    C_GREEN, C_RESET, C_YELLOW, UNIT_DIR_SYSTEM,
+};
+use crate::limits::supervisor_limit_wrapper;
+use crate::manifest::{load_manifest, InstallManifest};
+
+pub const CRON_DIR: &str = "/etc/cron.d";
+
+/// Prefix marking a disabled cron job line.
+const CRON_DISABLED: &str = "# disabled: ";
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Paths & rendering
+// ─────────────────────────────────────────────────────────────────────────────
+
+pub fn timer_unit_path(service_name: &str) -> PathBuf {
+    PathBuf::from(UNIT_DIR_SYSTEM).join(format!("{}.timer", service_name))
+}
+
+pub fn cron_path(service_name: &str) -> PathBuf {
+    PathBuf::from(CRON_DIR).join(service_name)
+}
+
+/// Whether the service is a scheduled job rather than a daemon.
+pub fn scheduled_manifest(service_name: &str) -> Option<InstallManifest> {
+    load_manifest(service_name).filter(|m| m.schedule.is_some())
+}
+
+pub fn render_timer_unit(manifest: &InstallManifest, schedule: &str) -> String {
+    format!(
+        r#"[Unit]
+Description=Schedule for {description}
+
+[Timer]
+OnCalendar={schedule}
+Persistent=true
+Unit={service_name}.service
+
+[Install]
+WantedBy=timers.target
+"#,
+        description = manifest.description,
+        schedule = schedule,
+        service_name = manifest.service_name,
+    )
+}
+
+/// A cron.d file running the job as its user, appending output to the
+/// same log file a supervisor program would use.
+pub fn render_cron_entry(manifest: &InstallManifest, schedule: &str) -> Result<String, String> {
+    render_cron_entry_for(manifest, schedule, RenderHost::Local)
+}
+
+pub fn render_cron_entry_for(
+    manifest: &InstallManifest,
+    schedule: &str,
+    host: RenderHost,
+) -> Result<String, String> {
+    let cron = oncalendar_to_cron(schedule)?;
+
+    let mut env = BTreeMap::new();
+    if manifest.managed_dirs {
+        for (key, dir) in managed_dir_env(&manifest.service_name) {
+            env.insert(key.to_string(), dir.display().to_string());
+        }
+    }
+    env.extend(manifest.environment.clone());
+    env.extend(host.env_file(&manifest.service_name));
+    let environment: String = env.iter().map(|(k, v)| format!("{}={}\n", k, v)).collect();
+
+    // Like supervisor, limits need a root wrapper chain that drops to the user
+    let (wrapper, _) = if manifest.limits.is_empty() {
+        (Vec::new(), Vec::new())
+    } else {
+        supervisor_limit_wrapper(
+            &manifest.limits,
+            &manifest.user,
+            &manifest.group,
+            host.wrapper_tools(),
+        )
+    };
+    let user = if wrapper.is_empty() { manifest.user.as_str() } else { "root" };
+    let command = wrapper
+        .iter()
+        .map(String::as_str)
+        .chain([&*manifest.exec_path.to_string_lossy()])
+        .chain(manifest.args.iter().map(String::as_str))
+        .map(shell_quote)
+        .collect::<Vec<_>>()
+        .join(" ");
+    let job = format!(
+        "cd {} && {{ echo \"corky: scheduled run\"; exec {}; }} >> {} 2>&1",
+        shell_quote(&manifest.working_dir.to_string_lossy()),
+        command,
+        shell_quote(&supervisor_log_path(&manifest.service_name).to_string_lossy()),
+    );
+
+    Ok(format!(
+        r#"# {description}
+# Managed by corky CLI -- do not edit manually
+# schedule: {schedule}
+SHELL=/bin/sh
+PATH=/usr/local/sbin:/usr/local/bin:/usr/sbin:/usr/bin:/sbin:/bin
+{environment}{cron} {user} {job}
+"#,
+        description = manifest.description,
+        schedule = schedule,
+        environment = environment,
+        cron = cron,
+        user = user,
+        // cron turns unescaped % into newlines
+        job = job.replace('%', "\\%"),
+    ))
+}
+
+/// Single-quote a word for /bin/sh unless it is plainly safe.
+fn shell_quote(word: &str) -> String {
+    if !word.is_empty()
+        && word
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "_-./:=@+,".contains(c))
+    {
+        return word.to_string();
+    }
+    format!("'{}'", word.replace('\'', "'\\''"))
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// OnCalendar -> cron
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Translate the cron-expressible subset of systemd OnCalendar= syntax
+/// (`[DOW] [*-MM-DD] HH:MM[:00]` and the named shorthands) to a cron schedule.
+pub fn oncalendar_to_cron(expr: &str) -> Result<String, String> {
+    let shorthand = match expr.trim() {
+        "minutely" => Some("* * * * *"),
+        "hourly" => Some("0 * * * *"),
+        "daily" => Some("0 0 * * *"),
+        "weekly" => Some("0 0 * * 1"),
+        "monthly" => Some("0 0 1 * *"),
+        "yearly" | "annually" => Some("0 0 1 1 *"),
+        _ => None,
+    };
+    if let Some(cron) = shorthand {
+        return Ok(cron.to_string());
+    }
+
+    let unsupported = || format!("schedule '{}' can't be expressed as a cron entry", expr);
+    let mut tokens: Vec<&str> = expr.split_whitespace().collect();
+
+    let weekdays = match tokens.first() {
+        Some(t) if t.starts_with(|c: char| c.is_ascii_alphabetic()) => {
+            let t = tokens.remove(0);
+            cron_weekdays(t).ok_or_else(unsupported)?
+        }
+        _ => "*".to_string(),
+    };
+    let (month, day) = match tokens.as_slice() {
+        [date, _] => {
+            let parts: Vec<&str> = date.split('-').collect();
+            let [year, month, day] = parts.as_slice() else {
+                return Err(unsupported());
+            };
+            if *year != "*" {
+                return Err(unsupported());
+            }
+            (
+                cron_field(month, 1, 12).ok_or_else(unsupported)?,
+                cron_field(day, 1, 31).ok_or_else(unsupported)?,
+            )
+        }
+        [_] => ("*".to_string(), "*".to_string()),
+        _ => return Err(unsupported()),
+    };
+    let time = tokens.last().ok_or_else(unsupported)?;
+    let parts: Vec<&str> = time.split(':').collect();
+    let (hour, minute) = match parts.as_slice() {
+        [h, m] => (h, m),
+        [h, m, s] if matches!(*s, "0" | "00") => (h, m),
+        _ => return Err(unsupported()),
+    };
+    let hour = cron_field(hour, 0, 23).ok_or_else(unsupported)?;
+    let minute = cron_field(minute, 0, 59).ok_or_else(unsupported)?;
+    Ok(format!("{} {} {} {} {}", minute, hour, day, month, weekdays))
+}
+
+/// `*`, numbers, comma lists, `a..b` ranges and `start/step` repetition.
+/// `min` is 1 for day-of-month and month, which count from 1.
+fn cron_field(field: &str, min: u32, max: u32) -> Option<String> {
+    if field == "*" {
+        return Some(field.to_string());
+    }
+    let range = min..=max;
+    let items: Option<Vec<String>> = field
+        .split(',')
+        .map(|item| {
+            if let Some((start, step)) = item.split_once('/') {
+                let start: u32 = if start == "*" { min } else { start.parse().ok()? };
+                let step: u32 = step.parse().ok()?;
+                (range.contains(&start) && step > 0).then(|| format!("{}-{}/{}", start, max, step))
+            } else if let Some((a, b)) = item.split_once("..") {
+                let (a, b): (u32, u32) = (a.parse().ok()?, b.parse().ok()?);
+                (range.contains(&a) && a <= b && b <= max).then(|| format!("{}-{}", a, b))
+            } else {
+                let n: u32 = item.parse().ok()?;
+                range.contains(&n).then(|| n.to_string())
+            }
+        })
+        .collect();
+    items.map(|v| v.join(","))
+}
+
+fn cron_weekdays(field: &str) -> Option<String> {
+    const DAYS: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+    let day = |name: &str| {
+        let name = name.to_ascii_lowercase();
+        DAYS.iter().position(|d| name.starts_with(d))
+    };
+    let items: Option<Vec<String>> = field
+        .split(',')
+        .map(|item| match item.split_once("..") {
+            Some((a, b)) => {
+                // Sunday is 0 but must end a range as 7; cron rejects 6-0
+                let (start, end) = (day(a)?, day(b)?);
+                if start == end {
+                    return Some(start.to_string());
+                }
+                let end = if end == 0 { 7 } else { end };
+                (start < end).then(|| format!("{}-{}", start, end))
+            }
+            None => Some(day(item)?.to_string()),
+        })
+        .collect();
+    items.map(|v| v.join(","))
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Install / uninstall
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Check a schedule before installing anything.
+pub fn validate_schedule(backend: &InitBackend, schedule: &str) {
+    if *backend == InitBackend::Supervisor
+        && let Err(e) = oncalendar_to_cron(schedule)
+    {
+        exit_error(&format!("[corky] schedule: {} (supervisor hosts use cron).", e));
+    }
+    if find_in_path("systemd-analyze").is_some()
+        && !run_cmd_quiet("systemd-analyze", &["calendar", schedule])
+    {
+        exit_error(&format!(
+            "[corky] schedule: '{}' is not a valid OnCalendar expression.",
+            schedule
+        ));
+    }
+}
+
+/// Oneshot service plus a `.timer`; the timer is what gets enabled.
+pub fn install_systemd_timer(
+    dry_run: bool,
+    skip_init: bool,
+    manifest: &InstallManifest,
+    schedule: &str,
+) {
+    let name = manifest.service_name.as_str();
+    let unit_path = systemd_unit_path(name);
+    let timer_path = timer_unit_path(name);
+    let unit = render_systemd_unit(manifest);
+    let timer = render_timer_unit(manifest, schedule);
+
+    section("Writing systemd unit & timer");
+    if dry_run {
+        for (path, contents) in [(&unit_path, &unit), (&timer_path, &timer)] {
+            println!("{C_CYAN}[DRY-RUN]{C_RESET} Would write: {}", path.display());
+            println!("---------- {} ----------\n{}", path.display(), contents);
+        }
+        if !skip_init {
+            println!("{C_CYAN}[DRY-RUN]{C_RESET} Would run: systemctl daemon-reload");
+            println!("{C_CYAN}[DRY-RUN]{C_RESET} Would run: systemctl enable --now {}.timer", name);
+        }
+        return;
+    }
+    for (path, contents) in [(&unit_path, &unit), (&timer_path, &timer)] {
+        fs::write(path, contents).unwrap_or_else(|e| {
+            exit_error(&format!("write {}: {}", path.display(), e));
+        });
+        println!("{C_GREEN}[INFO]{C_RESET} Wrote {}", path.display());
+    }
+
+    section("Enabling timer");
+    if skip_init {
+        println!("{C_YELLOW}[SKIP]{C_RESET} systemctl daemon-reload");
+        println!("{C_YELLOW}[SKIP]{C_RESET} systemctl enable --now {}.timer", name);
+        return;
+    }
+    run_cmd_expect_ok("systemctl", &["daemon-reload"]);
+    run_cmd_expect_ok("systemctl", &["enable", "--now", &format!("{}.timer", name)]);
+    println!(
+        "{C_BGREEN}[SUCCESS]{C_RESET} {} scheduled ({}). Run now with: corky start {}",
+        name,
+        schedule,
+        name.trim_start_matches("corky-")
+    );
+}
+
+/// Write the cron.d entry; cron picks it up on its own.
+pub fn install_cron_job(dry_run: bool, manifest: &InstallManifest, schedule: &str) {
+    let name = manifest.service_name.as_str();
+    let path = cron_path(name);
+    let entry = render_cron_entry(manifest, schedule).unwrap_or_else(|e| exit_error(&e));
+    let log = supervisor_log_path(name);
+
+    section("Writing cron entry");
+    if dry_run {
+        println!("{C_CYAN}[DRY-RUN]{C_RESET} Would write: {}", path.display());
+        println!("---------- {} ----------\n{}", path.display(), entry);
+        return;
+    }
+    // The job runs as the service user, so it must be able to append to its log
+    if let Some(dir) = log.parent() {
+        let _ = fs::create_dir_all(dir);
+    }
+    let _ = fs::OpenOptions::new().create(true).append(true).open(&log);
+    let owner = format!("{}:{}", manifest.user, manifest.group);
+    let _ = run_cmd_quiet("chown", &[&owner, &log.to_string_lossy()]);
+
+    write_config_file(&InitBackend::Supervisor, &path, &entry, name).unwrap_or_else(|e| {
+        exit_error(&format!("write {}: {}", path.display(), e));
+    });
+    println!("{C_GREEN}[INFO]{C_RESET} Wrote {}", path.display());
+    println!(
+        "{C_BGREEN}[SUCCESS]{C_RESET} {} scheduled via cron ({}). Output: {}",
+        name,
+        schedule,
+        log.display()
+    );
+}
+
+/// Remove a job's timer or cron entry if it has one.
+pub fn remove_schedule(dry_run: bool, skip_init: bool, service_name: &str) {
+    let timer = timer_unit_path(service_name);
+    let cron = cron_path(service_name);
+    if !timer.exists() && !cron.exists() {
+        return;
+    }
+    section("Removing schedule");
+    if dry_run {
+        for path in [&timer, &cron].into_iter().filter(|p| p.exists()) {
+            println!("{C_CYAN}[DRY-RUN]{C_RESET} Would remove: {}", path.display());
+        }
+        return;
+    }
+    if timer.exists() {
+        if !skip_init {
+            let _ = run_cmd("systemctl", &["disable", "--now", &format!("{}.timer", service_name)]);
+        }
+        remove_logged(&timer);
+    }
+    if cron.exists() {
+        remove_logged(&cron);
+    }
+}
+
+fn remove_logged(path: &std::path::Path) {
+    match fs::remove_file(path) {
+        Ok(()) => println!("{C_GREEN}[INFO]{C_RESET} Removed {}", path.display()),
+        Err(e) => eprintln!("{C_YELLOW}[WARN]{C_RESET} remove {}: {}", path.display(), e),
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Enable / disable / status
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Turn a scheduled job on or off: the timer on systemd, the cron line otherwise.
+pub fn set_schedule_enabled(service_info: &ServiceInfo, enabled: bool) {
+    let verb = if enabled { "enable" } else { "disable" };
+    match &service_info.backend {
+        InitBackend::Systemd { .. } => {
+            run_cmd_expect_ok(
+                "systemctl",
+                &[verb, "--now", &format!("{}.timer", service_info.name)],
+            );
+        }
+        InitBackend::Supervisor => {
+            let path = cron_path(&service_info.name);
+            let text = fs::read_to_string(&path).unwrap_or_else(|e| {
+                exit_error(&format!("Read {}: {}", path.display(), e));
+            });
+            if let Err(e) = fs::write(&path, toggle_cron_job(&text, enabled)) {
+                exit_error(&format!("write {}: {}", path.display(), e));
+            }
+        }
+    }
+    println!("{C_BGREEN}[OK]{C_RESET} Schedule for {} {}d", service_info.name, verb);
+}
+
+/// Whether the service's cron.d entry is switched on.
+pub fn cron_job_enabled(service_name: &str) -> bool {
+    fs::read_to_string(cron_path(service_name)).is_ok_and(|text| cron_job_active(&text))
+}
+
+fn cron_job_active(text: &str) -> bool {
+    text.lines()
+        .rev()
+        .find(|l| !l.trim().is_empty())
+        .is_some_and(|job| !job.starts_with(CRON_DISABLED))
+}
+
+/// Comment the job line (the last non-empty line) in or out.
+fn toggle_cron_job(text: &str, enabled: bool) -> String {
+    let mut lines: Vec<String> = text.lines().map(str::to_string).collect();
+    if let Some(job) = lines.iter_mut().rev().find(|l| !l.trim().is_empty()) {
+        let bare = job.strip_prefix(CRON_DISABLED).unwrap_or(job).to_string();
+        *job = if enabled { bare } else { format!("{}{}", CRON_DISABLED, bare) };
+    }
+    let mut out = lines.join("\n");
+    out.push('\n');
+    out
+}
+
+/// `next: …, last: …` for a scheduled job, or None for daemons.
+pub fn schedule_summary(service_info: &ServiceInfo) -> Option<String> {
+    let manifest = scheduled_manifest(&service_info.name)?;
+    let schedule = manifest.schedule.as_deref().unwrap_or_default();
+    let (next, last) = match &service_info.backend {
+        InitBackend::Systemd { .. } => {
+            let output = Command::new("systemctl")
+                .args(["show", "-p", "NextElapseUSecRealtime", "-p", "LastTriggerUSec"])
+                .arg(format!("{}.timer", service_info.name))
+                .output()
+                .ok()?;
+            let props = parse_properties(&String::from_utf8_lossy(&output.stdout));
+            (
+                props.get("NextElapseUSecRealtime").cloned(),
+                props.get("LastTriggerUSec").cloned(),
+            )
+        }
+        InitBackend::Supervisor => {
+            let disabled = fs::read_to_string(cron_path(&service_info.name))
+                .is_ok_and(|t| t.contains(CRON_DISABLED));
+            let next = if disabled {
+                Some("disabled".to_string())
+            } else {
+                next_elapse(schedule)
+            };
+            (next, last_write(&supervisor_log_path(&service_info.name)))
+        }
+    };
+    let show = |v: Option<String>| {
+        v.filter(|s| !s.is_empty() && s != "n/a")
+            .unwrap_or_else(|| "n/a".to_string())
+    };
+    Some(format!("schedule: {}, next: {}, last: {}", schedule, show(next), show(last)))
+}
+
+fn parse_properties(text: &str) -> BTreeMap<String, String> {
+    text.lines()
+        .filter_map(|l| l.split_once('='))
+        .map(|(k, v)| (k.to_string(), v.trim().to_string()))
+        .collect()
+}
+
+/// Next run per `systemd-analyze calendar` (works without systemd running).
+fn next_elapse(schedule: &str) -> Option<String> {
+    let output = Command::new("systemd-analyze")
+        .args(["calendar", schedule])
+        .output()
+        .ok()?;
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find_map(|l| l.trim().strip_prefix("Next elapse:").map(|v| v.trim().to_string()))
+}
+
+/// When the log was last written, i.e. the last run.
+fn last_write(path: &std::path::Path) -> Option<String> {
+    let output = Command::new("date")
+        .arg("-r")
+        .arg(path)
+        .arg("+%a %Y-%m-%d %H:%M:%S %Z")
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Tests
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_oncalendar_to_cron() {
+        assert_eq!(oncalendar_to_cron("*-*-* 03:00").unwrap(), "0 3 * * *");
+        assert_eq!(oncalendar_to_cron("03:30:00").unwrap(), "30 3 * * *");
+        assert_eq!(oncalendar_to_cron("Mon..Fri *-*-* 09:15").unwrap(), "15 9 * * 1-5");
+        assert_eq!(oncalendar_to_cron("Sat,Sun 12:00").unwrap(), "0 12 * * 6,0");
+        assert_eq!(oncalendar_to_cron("Sat..Sun 12:00").unwrap(), "0 12 * * 6-7");
+        assert_eq!(oncalendar_to_cron("Mon..Sun 12:00").unwrap(), "0 12 * * 1-7");
+        assert_eq!(oncalendar_to_cron("Sun..Tue 12:00").unwrap(), "0 12 * * 0-2");
+        assert!(oncalendar_to_cron("Fri..Mon 12:00").is_err());
+        assert_eq!(oncalendar_to_cron("*-*-01 00:00").unwrap(), "0 0 1 * *");
+        assert_eq!(oncalendar_to_cron("*-*-* *:0/15").unwrap(), "0-59/15 * * * *");
+        // Days and months count from 1
+        assert_eq!(oncalendar_to_cron("*-*-*/2 04:00").unwrap(), "0 4 1-31/2 * *");
+        assert_eq!(oncalendar_to_cron("*-*/3-* 04:00").unwrap(), "0 4 * 1-12/3 *");
+        assert!(oncalendar_to_cron("*-*-00 04:00").is_err());
+        assert_eq!(oncalendar_to_cron("daily").unwrap(), "0 0 * * *");
+        assert!(oncalendar_to_cron("2026-*-* 03:00").is_err());
+        assert!(oncalendar_to_cron("*-*-* 03:00:30").is_err());
+        assert!(oncalendar_to_cron("*-*-* 25:00").is_err());
+    }
+
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!(shell_quote("/usr/local/bin/x"), "/usr/local/bin/x");
+        assert_eq!(shell_quote("a b"), "'a b'");
+        assert_eq!(shell_quote("it's"), "'it'\\''s'");
+    }
+
+    #[test]
+    fn test_toggle_cron_job() {
+        let text = "# header\nFOO=1\n0 3 * * * app run\n";
+        let off = toggle_cron_job(text, false);
+        assert_eq!(off, "# header\nFOO=1\n# disabled: 0 3 * * * app run\n");
+        assert_eq!(toggle_cron_job(&off, true), text);
+        assert!(cron_job_active(text));
+        assert!(!cron_job_active(&off));
+    }
+
+    #[test]
+    fn test_parse_properties() {
+        let props = parse_properties(
+            "NextElapseUSecRealtime=Tue 2026-10-20 03:00:00 UTC\nLastTriggerUSec=n/a\n",
+        );
+        assert_eq!(props["NextElapseUSecRealtime"], "Tue 2026-10-20 03:00:00 UTC");
+        assert_eq!(props["LastTriggerUSec"], "n/a");
+    }
+}
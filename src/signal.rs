@@ -0,0 +1,146 @@
+use std::process::Command;
+
+use crate::init::{
+    elevate_if_needed, exit_error, scoped_command, InitBackend, ServiceInfo, C_BGREEN, C_GREEN,
+    C_RED, C_RESET,
+};
+use crate::logs::print_failure_context;
+use crate::manifest::load_manifest;
+use crate::schedule::scheduled_manifest;
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Signals (`corky reload`, `corky signal`)
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Default `[corky] reload_signal`.
+pub const DEFAULT_RELOAD_SIGNAL: &str = "HUP";
+
+/// Signals corky will send, by name without the SIG prefix.
+const SIGNALS: [&str; 13] = [
+    "HUP", "INT", "QUIT", "KILL", "USR1", "USR2", "ALRM", "TERM", "CONT", "STOP", "TSTP",
+    "WINCH", "IO",
+];
+
+/// `SIGHUP`, `sighup` or `HUP` -> `HUP`.
+pub fn normalize_signal(name: &str) -> Result<String, String> {
+    let upper = name.trim().to_ascii_uppercase();
+    let bare = upper.strip_prefix("SIG").unwrap_or(&upper);
+    if SIGNALS.contains(&bare) {
+        Ok(bare.to_string())
+    } else {
+        Err(format!(
+            "unknown signal '{}'; expected one of {}",
+            name,
+            SIGNALS.join(", ")
+        ))
+    }
+}
+
+/// The ExecReload= line sending `signal` to the main process.
+pub fn render_exec_reload(signal: &str) -> String {
+    format!("ExecReload=/bin/kill -{} $MAINPID\n", signal)
+}
+
+/// Ask a service to reload its configuration without restarting.
+pub fn run_service_reload(service_info: &ServiceInfo) -> ! {
+    elevate_if_needed(service_info);
+    refuse_scheduled(service_info, "reloaded");
+
+    match &service_info.backend {
+        InitBackend::Systemd { scope } => {
+            // ExecReload= carries the configured signal
+            let mut cmd = scoped_command("systemctl", scope);
+            cmd.args(["reload", &service_info.name]);
+            finish(service_info, cmd, "reloaded")
+        }
+        InitBackend::Supervisor => {
+            let signal = load_manifest(&service_info.name)
+                .map(|m| m.reload_signal)
+                .unwrap_or_else(|| DEFAULT_RELOAD_SIGNAL.to_string());
+            let mut cmd = Command::new("supervisorctl");
+            cmd.args(["signal", &signal, &service_info.name]);
+            finish(service_info, cmd, "reloaded")
+        }
+    }
+}
+
+/// Send an arbitrary signal to a service's main process.
+pub fn run_service_signal(service_info: &ServiceInfo, signal: &str) -> ! {
+    let signal = normalize_signal(signal).unwrap_or_else(|e| exit_error(&e));
+    elevate_if_needed(service_info);
+    refuse_scheduled(service_info, "signaled");
+
+    let cmd = match &service_info.backend {
+        InitBackend::Systemd { scope } => {
+            let mut cmd = scoped_command("systemctl", scope);
+            cmd.args([
+                "kill",
+                "--kill-who=main",
+                &format!("--signal={}", signal),
+                &service_info.name,
+            ]);
+            cmd
+        }
+        InitBackend::Supervisor => {
+            let mut cmd = Command::new("supervisorctl");
+            cmd.args(["signal", &signal, &service_info.name]);
+            cmd
+        }
+    };
+    finish(service_info, cmd, &format!("sent SIG{}", signal))
+}
+
+fn refuse_scheduled(service_info: &ServiceInfo, what: &str) {
+    if scheduled_manifest(&service_info.name).is_some() {
+        exit_error(&format!(
+            "{} is a scheduled job; there is no running process to be {}.",
+            service_info.name, what
+        ));
+    }
+}
+
+fn finish(service_info: &ServiceInfo, mut cmd: Command, done: &str) -> ! {
+    let program = cmd.get_program().to_string_lossy().to_string();
+    let args: Vec<String> = cmd
+        .get_args()
+        .map(|a| a.to_string_lossy().to_string())
+        .collect();
+    println!("{C_GREEN}[INFO]{C_RESET} Running: {} {}", program, args.join(" "));
+
+    let code = cmd.status().map(|s| s.code().unwrap_or(1)).unwrap_or_else(|e| {
+        exit_error(&format!("Failed to execute {}: {}", program, e));
+    });
+    if code == 0 {
+        println!("{C_BGREEN}[OK]{C_RESET} Service {} {}", service_info.name, done);
+    } else {
+        eprintln!(
+            "\n{C_RED}[ERROR]{C_RESET} {} failed for {}. Exit code: {}",
+            program, service_info.name, code
+        );
+        print_failure_context(service_info);
+    }
+    std::process::exit(code);
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Tests
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_signal() {
+        assert_eq!(normalize_signal("SIGHUP"), Ok("HUP".to_string()));
+        assert_eq!(normalize_signal("usr1"), Ok("USR1".to_string()));
+        assert_eq!(normalize_signal("sigterm"), Ok("TERM".to_string()));
+        assert!(normalize_signal("SIGFOO").is_err());
+        assert!(normalize_signal("").is_err());
+    }
+
+    #[test]
+    fn test_render_exec_reload() {
+        assert_eq!(render_exec_reload("USR2"), "ExecReload=/bin/kill -USR2 $MAINPID\n");
+    }
+}
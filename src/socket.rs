@@ -0,0 +1,164 @@
+use std::fs;
+use std::path::PathBuf;
+
+use crate::init::{
+    exit_error, render_systemd_unit, run_cmd, run_cmd_expect_ok, section, systemd_unit_path,
+    InitBackend, C_BGREEN, C_CYAN, C_GREEN, C_RESET, C_YELLOW, UNIT_DIR_SYSTEM,
+};
+use crate::manifest::{load_manifest, InstallManifest};
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Socket activation (`[corky] listen = [...]`)
+// ─────────────────────────────────────────────────────────────────────────────
+
+pub fn socket_unit_path(service_name: &str) -> PathBuf {
+    PathBuf::from(UNIT_DIR_SYSTEM).join(format!("{}.socket", service_name))
+}
+
+/// Whether the service is started on demand by its `.socket`.
+pub fn is_socket_activated(service_name: &str) -> bool {
+    load_manifest(service_name).is_some_and(|m| !m.listen.is_empty())
+}
+
+/// Reject listen addresses the backend can't serve before anything is installed.
+pub fn validate_listen(backend: &InitBackend, listen: &[String], scheduled: bool) {
+    if listen.is_empty() {
+        return;
+    }
+    if *backend == InitBackend::Supervisor {
+        exit_error(
+            "[corky] listen needs systemd socket activation; supervisor can't hold sockets for a \
+             service. Remove `listen` and have the service bind its own port.",
+        );
+    }
+    if scheduled {
+        exit_error("[corky] listen and schedule can't be combined.");
+    }
+    if let Some(bad) = listen
+        .iter()
+        .find(|l| l.is_empty() || l.chars().any(char::is_whitespace))
+    {
+        exit_error(&format!("[corky] listen: invalid address '{}'.", bad));
+    }
+}
+
+pub fn render_socket_unit(manifest: &InstallManifest) -> String {
+    let listen: String = manifest
+        .listen
+        .iter()
+        .map(|l| format!("ListenStream={}\n", l))
+        .collect();
+    format!(
+        r#"[Unit]
+Description=Socket for {description}
+
+[Socket]
+{listen}
+[Install]
+WantedBy=sockets.target
+"#,
+        description = manifest.description,
+        listen = listen,
+    )
+}
+
+/// Write the service and its `.socket`, then enable the socket instead of
+/// the service so the first connection starts it.
+pub fn install_systemd_socket(dry_run: bool, skip_init: bool, manifest: &InstallManifest) {
+    let name = manifest.service_name.as_str();
+    let unit_path = systemd_unit_path(name);
+    let socket_path = socket_unit_path(name);
+    let unit = render_systemd_unit(manifest);
+    let socket = render_socket_unit(manifest);
+    let socket_unit = format!("{}.socket", name);
+
+    section("Writing systemd unit & socket");
+    if dry_run {
+        for (path, contents) in [(&unit_path, &unit), (&socket_path, &socket)] {
+            println!("{C_CYAN}[DRY-RUN]{C_RESET} Would write: {}", path.display());
+            println!("---------- {} ----------\n{}", path.display(), contents);
+        }
+        if !skip_init {
+            println!("{C_CYAN}[DRY-RUN]{C_RESET} Would run: systemctl daemon-reload");
+            println!(
+                "{C_CYAN}[DRY-RUN]{C_RESET} Would run: systemctl enable --now {}",
+                socket_unit
+            );
+        }
+        return;
+    }
+    for (path, contents) in [(&unit_path, &unit), (&socket_path, &socket)] {
+        fs::write(path, contents).unwrap_or_else(|e| {
+            exit_error(&format!("write {}: {}", path.display(), e));
+        });
+        println!("{C_GREEN}[INFO]{C_RESET} Wrote {}", path.display());
+    }
+
+    section("Enabling socket");
+    if skip_init {
+        println!("{C_YELLOW}[SKIP]{C_RESET} systemctl daemon-reload");
+        println!("{C_YELLOW}[SKIP]{C_RESET} systemctl enable --now {}", socket_unit);
+        return;
+    }
+    run_cmd_expect_ok("systemctl", &["daemon-reload"]);
+    // A copy started before socket activation was configured would hold the port
+    let _ = run_cmd("systemctl", &["disable", "--now", &format!("{}.service", name)]);
+    run_cmd_expect_ok("systemctl", &["enable", "--now", &socket_unit]);
+    println!(
+        "{C_BGREEN}[SUCCESS]{C_RESET} {} listening on {}; it starts on the first connection.",
+        socket_unit,
+        manifest.listen.join(", ")
+    );
+}
+
+/// Stop and remove a service's `.socket` if it has one.
+pub fn remove_socket(dry_run: bool, skip_init: bool, service_name: &str) {
+    let path = socket_unit_path(service_name);
+    if !path.exists() {
+        return;
+    }
+    section("Removing socket");
+    if dry_run {
+        println!("{C_CYAN}[DRY-RUN]{C_RESET} Would remove: {}", path.display());
+        return;
+    }
+    if !skip_init {
+        let socket_unit = format!("{}.socket", service_name);
+        let _ = run_cmd("systemctl", &["disable", "--now", &socket_unit]);
+    }
+    match fs::remove_file(&path) {
+        Ok(()) => println!("{C_GREEN}[INFO]{C_RESET} Removed {}", path.display()),
+        Err(e) => eprintln!("{C_YELLOW}[WARN]{C_RESET} remove {}: {}", path.display(), e),
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Tests
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_socket_unit() {
+        let manifest: InstallManifest = toml::from_str(
+            r#"
+service_name = "corky-tool"
+description = "Internal tool"
+backend = "systemd"
+exec_path = "/usr/local/bin/corky-tool"
+working_dir = "/"
+user = "tool"
+group = "tool"
+listen = ["0.0.0.0:8080", "/run/corky-tool.sock"]
+"#,
+        )
+        .unwrap();
+        let socket = render_socket_unit(&manifest);
+        assert!(socket.contains(
+            "[Socket]\nListenStream=0.0.0.0:8080\nListenStream=/run/corky-tool.sock\n\n[Install]"
+        ));
+        assert!(socket.contains("WantedBy=sockets.target"));
+    }
+}
@@ -0,0 +1,238 @@
+use std::process::Command;
+
+use crate::init::{
+    exit_error, installing_user, primary_group_for_user, run_cmd, run_cmd_quiet, section,
+    InitBackend, C_CYAN, C_GREEN, C_RESET, C_YELLOW,
+};
+use crate::manifest::{load_manifest, InstallManifest};
+
+/// Shell given to users corky creates; they never log in.
+const NOLOGIN_SHELL: &str = "/usr/sbin/nologin";
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Service user selection
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Who a service runs as, from `[corky] user = "..."`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UserSpec {
+    /// Whoever ran `corky install` (the user who elevated); the default.
+    Invoker,
+    /// A transient systemd DynamicUser=.
+    Dynamic,
+    /// A locked system user (and group) corky creates if missing.
+    Create(String),
+    /// An account that must already exist.
+    Existing(String),
+}
+
+impl std::str::FromStr for UserSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s == "dynamic" {
+            return Ok(UserSpec::Dynamic);
+        }
+        let (spec, name): (fn(String) -> UserSpec, &str) = match s.strip_prefix("create:") {
+            Some(name) => (UserSpec::Create, name),
+            None => (UserSpec::Existing, s),
+        };
+        if !is_valid_user_name(name) {
+            return Err(format!("invalid user name '{}'", name));
+        }
+        Ok(spec(name.to_string()))
+    }
+}
+
+/// The identity recorded in the manifest for a service.
+pub struct ServiceUser {
+    pub user: String,
+    pub group: String,
+    pub dynamic: bool,
+    /// corky created the account and may remove it on uninstall.
+    pub created: bool,
+}
+
+/// Turn a spec into a concrete user, creating the account when asked to.
+pub fn resolve_service_user(
+    spec: &UserSpec,
+    backend: &InitBackend,
+    service_name: &str,
+    dry_run: bool,
+) -> ServiceUser {
+    let spec = match spec {
+        UserSpec::Dynamic if *backend == InitBackend::Supervisor => {
+            eprintln!(
+                "{C_YELLOW}[WARN]{C_RESET} Supervisor has no dynamic users; using a system user named {} instead.",
+                service_name
+            );
+            UserSpec::Create(service_name.to_string())
+        }
+        other => other.clone(),
+    };
+
+    match spec {
+        UserSpec::Invoker => {
+            let user = installing_user();
+            let group = primary_group_for_user(&user);
+            ServiceUser {
+                user,
+                group,
+                dynamic: false,
+                created: false,
+            }
+        }
+        UserSpec::Dynamic => ServiceUser {
+            user: service_name.to_string(),
+            group: service_name.to_string(),
+            dynamic: true,
+            created: false,
+        },
+        UserSpec::Create(name) => {
+            let previously_created = created_by_corky(load_manifest(service_name).as_ref(), &name);
+            let created = ensure_system_user(&name, dry_run) || previously_created;
+            ServiceUser {
+                group: primary_group_for_user(&name),
+                user: name,
+                dynamic: false,
+                created,
+            }
+        }
+        UserSpec::Existing(name) => {
+            if !user_exists(&name) {
+                exit_error(&format!(
+                    "User '{}' does not exist. Use user = \"create:{}\" to have corky create it.",
+                    name, name
+                ));
+            }
+            ServiceUser {
+                group: primary_group_for_user(&name),
+                user: name,
+                dynamic: false,
+                created: false,
+            }
+        }
+    }
+}
+
+/// `useradd` arguments for a locked system user with a matching group.
+pub fn useradd_args(name: &str) -> [&str; 8] {
+    [
+        "--system",
+        "--user-group",
+        "--no-create-home",
+        "--home-dir",
+        "/nonexistent",
+        "--shell",
+        NOLOGIN_SHELL,
+        name,
+    ]
+}
+
+/// Whether `previous` records corky creating the account `name`. Another
+/// account never inherits the flag, so `--remove-user` can't delete it.
+pub fn created_by_corky(previous: Option<&InstallManifest>, name: &str) -> bool {
+    previous.is_some_and(|m| m.created_user && m.user == name)
+}
+
+/// Create a locked system user with a matching group unless it exists.
+/// Returns whether the account was (or, in dry-run, would be) created.
+pub fn ensure_system_user(name: &str, dry_run: bool) -> bool {
+    if user_exists(name) {
+        println!("{C_GREEN}[INFO]{C_RESET} Using existing user {}", name);
+        return false;
+    }
+    section("Creating service user");
+    let args = useradd_args(name);
+    if dry_run {
+        println!("{C_CYAN}[DRY-RUN]{C_RESET} Would run: useradd {}", args.join(" "));
+        return true;
+    }
+    if !run_cmd("useradd", &args) {
+        exit_error(&format!("Could not create system user '{}'.", name));
+    }
+    // System accounts get no password, but lock explicitly in case of odd defaults
+    let _ = run_cmd_quiet("usermod", &["--lock", name]);
+    println!("{C_GREEN}[INFO]{C_RESET} Created locked system user {}", name);
+    true
+}
+
+/// Delete a user corky created (its user-private group goes with it).
+pub fn remove_system_user(name: &str, dry_run: bool) {
+    section("Removing service user");
+    if dry_run {
+        println!("{C_CYAN}[DRY-RUN]{C_RESET} Would run: userdel {}", name);
+        return;
+    }
+    if !user_exists(name) {
+        println!("{C_YELLOW}[WARN]{C_RESET} User {} no longer exists", name);
+        return;
+    }
+    if run_cmd("userdel", &[name]) {
+        println!("{C_GREEN}[INFO]{C_RESET} Removed user {}", name);
+    } else {
+        eprintln!("{C_YELLOW}[WARN]{C_RESET} Could not remove user {}", name);
+    }
+}
+
+pub fn user_exists(name: &str) -> bool {
+    Command::new("id")
+        .args(["-u", name])
+        .output()
+        .map(|o| o.status.success())
+        .unwrap_or(false)
+}
+
+/// Portable POSIX user name (what useradd accepts by default).
+pub fn is_valid_user_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_lowercase() || c == '_')
+        && chars.all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-')
+        && name.len() <= 32
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Tests
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_created_by_corky() {
+        let previous: InstallManifest = toml::from_str(
+            r#"
+service_name = "corky-api"
+description = "API"
+backend = "systemd"
+exec_path = "/usr/local/bin/api"
+working_dir = "/"
+user = "corky-api"
+group = "corky-api"
+created_user = true
+"#,
+        )
+        .unwrap();
+        assert!(created_by_corky(Some(&previous), "corky-api"));
+        // Switching to an existing account must not mark it as corky's
+        assert!(!created_by_corky(Some(&previous), "www-data"));
+        assert!(!created_by_corky(None, "corky-api"));
+    }
+
+    #[test]
+    fn test_parse_user_spec() {
+        assert_eq!("dynamic".parse::<UserSpec>(), Ok(UserSpec::Dynamic));
+        assert_eq!(
+            "create:corky-api".parse::<UserSpec>(),
+            Ok(UserSpec::Create("corky-api".to_string()))
+        );
+        assert_eq!(
+            "www-data".parse::<UserSpec>(),
+            Ok(UserSpec::Existing("www-data".to_string()))
+        );
+        assert!("create:".parse::<UserSpec>().is_err());
+        assert!("Bad User".parse::<UserSpec>().is_err());
+    }
+}